@@ -101,8 +101,8 @@ if (require.main === module) {
         }
         case ('conflate'): {
             const conflate_arg = require('minimist')(process.argv, Context.args({
-                string: ['in_persistent', 'in_address', 'output', 'languages', 'db'],
-                boolean: ['hecate'],
+                string: ['in_persistent', 'in_address', 'output', 'languages', 'db', 'progress', 'scope-source', 'scope-polygon', 'merge', 'source-priority', 'backend', 'summary'],
+                boolean: ['hecate', 'delete', 'prefer-accuracy'],
                 alias: {
                     database: 'db',
                     'in_address': 'in_addresses'
@@ -110,32 +110,58 @@ if (require.main === module) {
             }));
 
 
-            if (!conflate_arg.db) {
+            if (!conflate_arg.db && conflate_arg.backend !== 'memory') {
                 console.error('--db <DATABASE> argument required');
                 process.exit(1);
             }
 
-            require('./native/index.node').conflate({
+            // --merge=<PROPERTY>:<STRATEGY>,...
+            const merge = {};
+            if (conflate_arg.merge) {
+                for (const rule of String(conflate_arg.merge).split(',')) {
+                    const [prop, strategy] = rule.split(':');
+                    merge[prop] = strategy;
+                }
+            }
+
+            require('./lib/util/task').conflate({
                 in_persistent: conflate_arg.in_persistent,
                 in_address: conflate_arg.in_address,
                 output: conflate_arg.output,
                 languages: conflate_arg.languages,
                 hecate: conflate_arg.hecate,
                 context: new Context(conflate_arg).as_json(),
-                db: conflate_arg.db
+                db: conflate_arg.db,
+                radius: conflate_arg.radius,
+                delete: conflate_arg.delete,
+                scope_source: conflate_arg['scope-source'],
+                scope_polygon: conflate_arg['scope-polygon'],
+                delete_ratio: conflate_arg['delete-ratio'],
+                move_threshold: conflate_arg['move-threshold'],
+                prefer_accuracy: conflate_arg['prefer-accuracy'],
+                merge: merge,
+                source_priority: conflate_arg['source-priority'] ? conflate_arg['source-priority'].split(',').map((source) => source.trim()).filter((source) => source) : undefined,
+                backend: conflate_arg.backend,
+                workers: conflate_arg.workers,
+                window: conflate_arg.window,
+                summary: conflate_arg.summary,
+                progress: conflate_arg.progress
+            }).catch((err) => {
+                console.error(err);
+                process.exit(1);
             });
 
             break;
         }
         case ('consensus'): {
             const consensus_arg = require('minimist')(process.argv, Context.args({
-                string: ['query_points', 'languages', 'db', 'error_sources', 'error_query_points'],
+                string: ['query_points', 'languages', 'db', 'error_sources', 'error_query_points', 'buckets', 'progress'],
                 alias: {
                     database: 'db'
                 }
             }));
 
-            const { query_points, threshold, db, error_sources, error_query_points } = consensus_arg;
+            const { query_points, threshold, radius, db, error_sources, error_query_points, buckets, progress } = consensus_arg;
             const sources = consensus_arg._.slice(3);
             if (!query_points) {
                 console.error('--query-points=<FILE> argument required');
@@ -150,18 +176,24 @@ if (require.main === module) {
                 query_points,
                 context: new Context(consensus_arg).as_json(),
                 threshold,
+                radius,
                 db,
                 error_sources,
-                error_query_points
+                error_query_points,
+                buckets,
+                progress
             };
 
-            require('./native/index.node').consensus(args);
+            require('./lib/util/task').consensus(args).catch((err) => {
+                console.error(err);
+                process.exit(1);
+            });
 
             break;
         }
         case ('dedupe'): {
             const dedupe_arg = require('minimist')(process.argv, {
-                string: ['buildings', 'input', 'output', 'languages', 'db', 'country', 'region'],
+                string: ['buildings', 'input', 'output', 'languages', 'db', 'country', 'region', 'progress', 'partition'],
                 boolean: ['hecate'],
                 alias: {
                     database: 'db'
@@ -173,20 +205,28 @@ if (require.main === module) {
                 process.exit(1);
             }
 
-            require('./native/index.node').dedupe({
+            require('./lib/util/task').dedupe({
                 buildings: dedupe_arg.buildings,
                 input: dedupe_arg.input,
                 output: dedupe_arg.output,
                 hecate: dedupe_arg.hecate,
                 context: new Context(dedupe_arg).as_json(),
-                db: dedupe_arg.db
+                db: dedupe_arg.db,
+                workers: dedupe_arg.workers,
+                window: dedupe_arg.window,
+                pool: dedupe_arg.pool,
+                partition: dedupe_arg.partition,
+                progress: dedupe_arg.progress
+            }).catch((err) => {
+                console.error(err);
+                process.exit(1);
             });
 
             break;
         }
         case ('classify'): {
             const classify_arg = require('minimist')(process.argv, {
-                string: ['buildings', 'parcels', 'input', 'output', 'db'],
+                string: ['buildings', 'parcels', 'input', 'output', 'db', 'progress'],
                 boolean: ['hecate'],
                 alias: {
                     database: 'db',
@@ -209,13 +249,17 @@ if (require.main === module) {
                 process.exit(1);
             }
 
-            require('./native/index.node').classify({
+            require('./lib/util/task').classify({
                 buildings: classify_arg.buildings,
                 parcels: classify_arg.parcels,
                 input: classify_arg.input,
                 output: classify_arg.output,
                 db: classify_arg.db,
-                hecate: classify_arg.hecate
+                hecate: classify_arg.hecate,
+                progress: classify_arg.progress
+            }).catch((err) => {
+                console.error(err);
+                process.exit(1);
             });
 
             break;
@@ -237,6 +281,7 @@ if (require.main === module) {
         test: require('./lib/test'),
         testcsv: require('./lib/testcsv'),
         strip: require('./lib/strip'),
-        analyze: require('./lib/analyze')
+        analyze: require('./lib/analyze'),
+        tasks: require('./lib/util/task')
     };
 }
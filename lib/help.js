@@ -18,6 +18,7 @@ module.exports = function(argv) {
             console.log('   --buildings <FILE>        GeoJSONLD Building Polygons');
             console.log('   --parcels <FILE>          GeoJSONLD Parcel Polygons');
             console.log('   --hecate                  Expect Hecate input & produce hecate output');
+            console.log('   --progress <FILE>         Append progress events as JSON lines to a file, "-" for stderr');
             break;
         case ('testcsv'):
             console.log('');
@@ -97,10 +98,31 @@ module.exports = function(argv) {
             console.log('   --country=<ISO3166-1 Alpha2>             [optional] Optionally populate carmen:geocoder_stack');
             console.log('   --region=<ISO3166-2>                     [optional] Used by some "map" scripts to alter input text. IE state highways');
             console.log('   --intersections                          [optional] Generate and include intersections in the final output');
+            console.log('   --min-link-score=<SCORE>                 [optional] Addresses linked to a network with a lower score (0-100)');
+            console.log('                                                are output as orphans rather than interpolated');
+            console.log('   --binary                                 [optional] Import address & network data using the binary COPY format');
+            console.log('   --resume                                 [optional] Skip stages that completed in a previous run against the');
+            console.log('                                                same database and input, resuming address linking where it stopped');
+            console.log('   --cluster-radius=<METRES>                [optional] Distance within which like named addresses and networks are');
+            console.log('                                                clustered, defaults to 500');
+            console.log('   --cluster-gap=<METRES>                   [optional] Split network clusters whose unconnected parts are further');
+            console.log('                                                apart than this distance, defaults to 250');
+            console.log('   --cluster-angle=<DEGREES>                [optional] Split network clusters whose unconnected parts differ in');
+            console.log('                                                orientation by more than this angle, defaults to 45');
+            console.log('   --link-radius=<METRES>                   [optional] Distance within which a network can be linked to an address,');
+            console.log('                                                defaults to 2000');
+            console.log('   --intersection-radius=<METRES>           [optional] Distance within which intersections of the same streets are');
+            console.log('                                                merged, defaults to 50');
+            console.log('   --workers=<NUMBER>                       [optional] Number of address linking threads, defaults to the number of cpus');
+            console.log('   --window=<NUMBER>                        [optional] Number of address ids linked per transaction, defaults to 5000');
+            console.log('   --pool=<NUMBER>                          [optional] Maximum number of database connections used for linking');
+            console.log('   --partition=<range|keyset>               [optional] Split address ids into fixed width id ranges (default) or');
+            console.log('                                                windows of --window addresses, better suited to sparse ids');
             console.log('   --debug                                  [optional] Gives much richer info for `debug` mode module');
             console.log('   --error-network <FILE>                   [optional] Output invalid features to a given file');
             console.log('   --error-map <FILE>                       [optional] Output invalid features to a given file');
             console.log('   --warn <FILE>                            [optional] Output build warnings to a file');
+            console.log('   --progress=<FILE>                        [optional] Append progress events as JSON lines to a file, "-" for stderr');
             break;
         case ('conflate'):
             console.log('');
@@ -109,16 +131,46 @@ module.exports = function(argv) {
             console.log('usage: index.js conflate [--in-addresses=<FILE>] [--in-persistent=<FILE>] [--db <DATABASE>]');
             console.log('                    [--output <FILE>] [--languages=<CODE, ...>] [--country=<CODE> ] [--region <CODE>]');
             console.log('                    [--error-persistent <FILE>] [--error-addresses <FILE>]');
+            console.log('                    [--delete [--scope-source=<SOURCE>] [--scope-polygon=<FILE>] [--delete-ratio=<RATIO>]]');
+            console.log('                    [--move-threshold=<METRES> [--prefer-accuracy]]');
+            console.log('                    [--merge=<PROPERTY>:<STRATEGY>,...] [--source-priority=<SOURCE>,...]');
+            console.log('                    [--backend=<postgres|memory>] [--workers=<NUMBER>] [--window=<NUMBER>]');
+            console.log('                    [--summary=<FILE>]');
             console.log('');
             console.log('[options]:');
             console.log('   --in-addresses=<FILE.geojson>             line-delimited geojson of new address features');
             console.log('   --in-persistent=<FILE.geojson>            line-delimited geojson of persistent/existing address');
             console.log('   --db="<DATABASE>"                         Name of database to connect to w/ user "postgres"');
+            console.log('   --backend=<postgres|memory>               [optional] Hold persistent addresses in PostGIS or in an in memory');
+            console.log('                                               index that requires no database, defaults to postgres');
+            console.log('   --workers=<NUMBER>                        [optional] Number of address matching threads, defaults to the number of cpus');
+            console.log('   --window=<NUMBER>                         [optional] Number of new addresses matched per batch, defaults to 5000');
             console.log('   --output=<FILE.geojson>                   output line-delimited geojson diff of new addresses');
             console.log('   --error-persistent=<FILE>                 [optional] File to log persistent address import errors');
             console.log('   --languages=<ISO 639-1,ISO 639-1,...>     [optional] Language codes of abbreviation tokens to match');
             console.log('   --country=<ISO3166-1 Alpha2>              [optional]');
             console.log('   --region=<ISO3166-2>                      [optional]');
+            console.log('   --radius=<METRES>                         [optional] Distance within which persistent addresses are compared');
+            console.log('                                               to a new address, defaults to 1000');
+            console.log('   --delete                                  [optional] Delete persistent addresses within the scope that no new');
+            console.log('                                               address matched, requires --scope-source and/or --scope-polygon');
+            console.log('   --scope-source=<SOURCE>                   [optional] Only delete persistent addresses with this source value');
+            console.log('   --scope-polygon=<FILE.geojson>            [optional] Only delete persistent addresses within these polygons');
+            console.log('   --delete-ratio=<RATIO>                    [optional] Fail rather than delete more than this fraction of the');
+            console.log('                                               persistent addresses in scope, defaults to 0.1');
+            console.log('   --move-threshold=<METRES>                 [optional] Move matched persistent addresses to the new geometry');
+            console.log('                                               when they are further apart than this distance');
+            console.log('   --prefer-accuracy                         [optional] Never move a persistent address to a point with a lower');
+            console.log('                                               accuracy property (rooftop > parcel > point)');
+            console.log('   --merge=<PROPERTY>:<STRATEGY>,...          [optional] How properties of matched addresses are merged, one of');
+            console.log('                                               new, persistent, union, max, min or source. Properties without a');
+            console.log('                                               strategy keep their persistent value');
+            console.log('   --source-priority=<SOURCE>,...            [optional] Address sources from highest to lowest priority');
+            console.log('                                               New names from a lower priority source are ranked below the');
+            console.log('                                               persistent primary name & generated synonyms are dropped');
+            console.log('   --summary=<FILE.json>                     [optional] Write a report of the creates, modifies & deletes in the diff');
+            console.log('                                               by source & reason, with examples, before it is applied to hecate');
+            console.log('   --progress=<FILE>                         [optional] Append progress events as JSON lines to a file, "-" for stderr');
             break;
         case ('dedupe'):
             console.log('');
@@ -137,6 +189,12 @@ module.exports = function(argv) {
             console.log('   --output=<FILE.geojson>                   [optional] line-delimited geojson of deduped features to write to');
             console.log('   --languages=<ISO 639-1,ISO 639-1,...>     [optional] Language codes of abbreviation tokens to match');
             console.log('   --hecate                                  [optional] output duplicates as hecate deletions');
+            console.log('   --workers=<NUMBER>                        [optional] Number of dedupe threads, defaults to the number of cpus');
+            console.log('   --window=<NUMBER>                         [optional] Number of address ids processed per batch, defaults to 5000');
+            console.log('   --pool=<NUMBER>                           [optional] Maximum number of database connections, defaults to one per thread');
+            console.log('   --partition=<range|keyset>                [optional] Split address ids into fixed width id ranges (default) or');
+            console.log('                                               windows of --window addresses, better suited to sparse hecate ids');
+            console.log('   --progress=<FILE>                         [optional] Append progress events as JSON lines to a file, "-" for stderr');
             break;
         case ('strip'):
             console.log('');
@@ -166,18 +224,23 @@ module.exports = function(argv) {
             console.log('');
             console.log('usage: index.js consensus [<FILE>, <FILE>, ...] [--db <DATABASE>] [--threshold <INTEGER>]');
             console.log('                    [--languages=<CODE, ...>] [--country=<CODE> ] [--region <CODE>]');
-            console.log('                    [--error-sources <FILE>] [--error-query-points <FILE>]');
+            console.log('                    [--error-sources <FILE>] [--error-query-points <FILE>] [--buckets <FILE>]');
             console.log('');
             console.log('[options]:');
             console.log('   <FILE.geojson>                            Line-delimited geojson of address features');
             console.log('   --query-points=<FILE>                     Set of address points to serve as test queries against the source data');
             console.log('   --db="<DATABASE>"                         Name of database to connect to w/ user "postgres". Defaults to "consensus".');
             console.log('   --threshold="<INTEGER>"                   [optional] Maximum allowed dissimilarity between points. Defaults to 25.');
+            console.log('   --radius=<METRES>                         [optional] Distance within which source addresses are compared to a');
+            console.log('                                               query point. Defaults to 1000.');
             console.log('   --error-sources=<FILE>                    [optional] File to log source address import errors');
             console.log('   --error-query-points=<FILE>               [optional] File to log query point import errors');
+            console.log('   --buckets=<FILE>                          [optional] Line delimited (Multi)Polygon features with a .properties.name');
+            console.log('                                               to additionally report the metrics of the query points within each');
             console.log('   --languages=<ISO 639-1,ISO 639-1,...>     [optional] Language codes of abbreviation tokens to match');
             console.log('   --country=<ISO3166-1 Alpha2>              [optional]');
             console.log('   --region=<ISO3166-2>                      [optional]');
+            console.log('   --progress=<FILE>                         [optional] Append progress events as JSON lines to a file, "-" for stderr');
             break;
         default:
             console.log('usage: index.js <command> [--version] [--help]');
@@ -16,7 +16,7 @@ const {
     cluster_net,
     intersections,
     link_addr
-} = require('../native/index.node');
+} = require('./util/task');
 
 const CPUS = process.env.CI ? 10 : Math.min(16, os.cpus().length);
 
@@ -48,12 +48,16 @@ function main(argv, cb) {
                 'map-address',
                 'error-network',
                 'error-address',
-                'db'
+                'db',
+                'progress',
+                'partition'
             ],
             boolean: [
                 'name',
                 'debug',
-                'intersections'
+                'intersections',
+                'binary',
+                'resume'
             ],
             alias: {
                 'intersections': 'intersection',
@@ -115,53 +119,89 @@ function main(argv, cb) {
 
     const context = new Context(argv).as_json();
 
-    console.time('ok - address imported');
-    import_addr({
+    // Stages that completed in a previous run with the same input are skipped
+    const stage = {
         db: argv.db,
-        seq: true,
-        input: argv['in-address'],
-        context: context,
-        errors: argv['error-address']
-    });
-    console.timeEnd('ok - address imported');
-
-    console.time('ok - network imported');
-    import_net({
-        db: argv.db,
-        seq: true,
-        input: argv['in-network'],
-        context: context,
-        errors: argv['error-network']
-    });
-    console.timeEnd('ok - network imported');
+        resume: argv.resume,
+        cluster_radius: argv['cluster-radius'],
+        cluster_gap: argv['cluster-gap'],
+        cluster_angle: argv['cluster-angle'],
+        link_radius: argv['link-radius'],
+        intersection_radius: argv['intersection-radius']
+    };
+
+    pipeline().then(splitter).catch(cb);
+
+    /**
+     * Import, cluster & link the address & network data. Each native stage runs as a
+     * background task so that the event loop isn't blocked while it runs
+     * @return {Promise}
+     */
+    async function pipeline() {
+        console.time('ok - address imported');
+        await import_addr({
+            db: argv.db,
+            seq: true,
+            input: argv['in-address'],
+            context: context,
+            errors: argv['error-address'],
+            binary: argv.binary,
+            resume: argv.resume
+        });
+        console.timeEnd('ok - address imported');
+
+        console.time('ok - network imported');
+        await import_net({
+            db: argv.db,
+            seq: true,
+            input: argv['in-network'],
+            context: context,
+            errors: argv['error-network'],
+            binary: argv.binary,
+            resume: argv.resume
+        });
+        console.timeEnd('ok - network imported');
 
-    console.time('ok - clustered networks');
-    cluster_net(argv.db);
-    console.timeEnd('ok - clustered networks');
+        console.time('ok - clustered networks');
+        await cluster_net(stage);
+        console.timeEnd('ok - clustered networks');
 
-    console.time('ok - generated intersections');
-    intersections(argv.db);
-    console.timeEnd('ok - generated intersections');
+        console.time('ok - generated intersections');
+        await intersections(stage);
+        console.timeEnd('ok - generated intersections');
 
-    matcher();
+        await matcher();
+    }
 
     /**
      * Match network clusters with a proximal and textually similiar address cluster
+     * @return {Promise}
      */
-    function matcher() {
+    async function matcher() {
         console.time('ok - cross matched data');
-        link_addr(argv.db);
+        await link_addr({
+            db: argv.db,
+            resume: argv.resume,
+            link_radius: argv['link-radius'],
+            workers: argv.workers,
+            window: argv.window,
+            pool: argv.pool,
+            partition: argv.partition,
+            progress: argv.progress
+        });
         console.timeEnd('ok - cross matched data');
 
         console.time('ok - clustered addresses');
-        cluster_addr(argv.db, false);
+        await cluster_addr(stage, false, argv['min-link-score']);
         console.timeEnd('ok - clustered addresses');
 
         console.time('ok - clustered orphan addresses');
-        cluster_addr(argv.db, true);
+        await cluster_addr(stage, true, argv['min-link-score']);
         console.timeEnd('ok - clustered orphan addresses');
 
-        return splitter();
+        console.time('ok - clustered orphan networks');
+        await cluster_net(stage, true);
+        console.timeEnd('ok - clustered orphan networks');
     }
 
     /**
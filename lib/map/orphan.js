@@ -166,7 +166,8 @@ class Orphan {
     }
 
     /**
-     * Output all network_clusters not matched to an address_cluster
+     * Output all network_orphan_clusters, the network_clusters not matched to an address_cluster
+     * grouped by name and proximity by cluster_net(db, true)
      * @param {Function} cb Callback in (err, res)
      */
     network(cb) {
@@ -175,11 +176,14 @@ class Orphan {
         this.pool.connect((err, client, done) => {
             if (err) return cb(err);
 
+            // Intersections reference the network_clusters an orphan cluster is made up of,
+            // including those of merged intersections in a_ids & b_ids, map them onto the
+            // first of these so they can be matched against internal:nid
             const cursor = client.query(new Cursor(`
                 SELECT
-                    network_cluster.id AS nid,
-                    names AS name,
-                    ST_AsGeoJSON(geom)::JSON AS geom,
+                    o.cluster_ids[1] AS nid,
+                    o.names AS name,
+                    ST_AsGeoJSON(o.geom)::JSON AS geom,
                     (
                         SELECT
                             json_agg(json_build_object(
@@ -188,28 +192,40 @@ class Orphan {
                                  'b_id', i.b_id,
                                  'a_street', i.a_street,
                                  'b_street', i.b_street,
+                                 'a_display', i.a_display,
+                                 'b_display', i.b_display,
+                                 'synonyms', i.synonyms,
                                  'geom', i.geom::JSON
                             ))
                         FROM (
                             SELECT
                                 intersections.id AS id,
-                                intersections.a_id AS a_id,
-                                intersections.b_id AS b_id,
+                                CASE
+                                    WHEN intersections.a_ids && o.cluster_ids::BIGINT[] THEN o.cluster_ids[1]
+                                    ELSE intersections.a_id
+                                END AS a_id,
+                                CASE
+                                    WHEN intersections.b_ids && o.cluster_ids::BIGINT[] THEN o.cluster_ids[1]
+                                    ELSE intersections.b_id
+                                END AS b_id,
                                 intersections.a_street AS a_street,
                                 intersections.b_street AS b_street,
+                                intersections.a_display AS a_display,
+                                intersections.b_display AS b_display,
+                                intersections.synonyms AS synonyms,
                                 ST_AsGeoJSON(intersections.geom)::JSON AS geom
                             FROM
                                 intersections
                             WHERE
-                                intersections.a_id = network_cluster.id
-                                OR intersections.b_id = network_cluster.id
+                                intersections.a_ids && o.cluster_ids::BIGINT[]
+                                OR intersections.b_ids && o.cluster_ids::BIGINT[]
                         ) i
                     ) AS intersections
                 FROM
-                    network_cluster
+                    network_orphan_cluster o
                 WHERE
-                    network_cluster.address IS NULL
-                    AND network_cluster.geom is NOT NULL
+                    o.geom IS NOT NULL
+                    AND o.names IS NOT NULL
             `));
 
             return iterate();
@@ -94,17 +94,20 @@ class SplitFeat {
                     SELECT
                         JSON_Agg(JSON_Build_Object(
                             'id', intersections.id,
-                            'a_id', intersections.a_id,
-                            'b_id', intersections.b_id,
+                            'a_id', CASE WHEN ${nid} = ANY(intersections.a_ids) THEN ${nid} ELSE intersections.a_id END,
+                            'b_id', CASE WHEN ${nid} = ANY(intersections.b_ids) THEN ${nid} ELSE intersections.b_id END,
                             'a_street', intersections.a_street,
                             'b_street', intersections.b_street,
+                            'a_display', intersections.a_display,
+                            'b_display', intersections.b_display,
+                            'synonyms', intersections.synonyms,
                             'geom', ST_AsGeoJSON(intersections.geom)::JSON
                         ))
                     FROM
                         intersections
                     WHERE
-                        intersections.a_id = ${nid}
-                        OR intersections.b_id = ${nid}
+                        ${nid} = ANY(intersections.a_ids)
+                        OR ${nid} = ANY(intersections.b_ids)
                 ) AS intersections
             FROM
                 network_cluster
@@ -22,11 +22,13 @@ function post(feat, opts) {
         if (feat.properties['internal:nid'] === intersection.a_id) {
             return {
                 street: intersection.b_street,
+                names: others(intersection, 0),
                 geom: intersection.geom
             };
         } else if (feat.properties['internal:nid'] === intersection.b_id) {
             return {
                 street: intersection.a_street,
+                names: others(intersection, 1),
                 geom: intersection.geom
             };
         }
@@ -47,8 +49,7 @@ function post(feat, opts) {
 
     for (const intersection of intersections) {
         if (!intersection || !intersection.street) continue;
-        const streets = dedupe_syn(intersection.street);
-        for (const st of streets) {
+        for (const st of intersection.names) {
             if (final.indexOf(st) === -1) {
                 final.push(st);
                 feat.geometry.geometries[feat.geometry.geometries.length - 1].coordinates.push(intersection.geom.coordinates);
@@ -81,4 +82,25 @@ function post(feat, opts) {
     return feat;
 }
 
+/**
+ * Display names of the other street of an intersection, in the priority order of the
+ * synonym combinations picked when the intersections were deduped
+ * @param {Object} intersection Intersection of the feature
+ * @param {number} side         0 if the feature is the a street, 1 if it is the b street
+ * @return {Array}              Display names of the other street
+ */
+function others(intersection, side) {
+    const display = side === 0 ? intersection.a_display : intersection.b_display;
+
+    if (!intersection.synonyms || !intersection.synonyms.length) {
+        return dedupe_syn(side === 0 ? intersection.b_street : intersection.a_street);
+    }
+
+    return intersection.synonyms.filter((pair) => {
+        return pair[side] === display;
+    }).map((pair) => {
+        return pair[1 - side];
+    });
+}
+
 module.exports.post = post;
@@ -0,0 +1,83 @@
+'use strict';
+
+const native = require('../../native/index.node');
+
+/**
+ * How often (ms) the progress events of a background task are polled for
+ */
+const POLL = 250;
+
+/**
+ * Wrap a native mode so that it runs as a background task on the libuv
+ * thread pool instead of blocking the event loop
+ *
+ * @param {Function} mode Native mode function
+ * @param {number} [arity=1] Number of arguments the native mode takes before its callback
+ * @return {Function} Function that takes the mode's arguments and returns a Promise.
+ *                    The Promise has a cancel() function that stops the mode between batches,
+ *                    or between steps for modes that are not processed in batches.
+ *                    A progress function in the args object is called with each progress event
+ */
+function background(mode, arity = 1) {
+    return (...args) => {
+        let id;
+        let timer;
+
+        // Pad missing optional arguments so that the callback is always at the same index
+        const params = Array.from({ length: arity }, (_, i) => args[i]);
+
+        const progress = params[0] && typeof params[0].progress === 'function' ? params[0].progress : null;
+
+        /**
+         * Pass the events queued by the native task to the progress function
+         */
+        function deliver() {
+            for (const event of native.events(id)) progress(event);
+        }
+
+        const promise = new Promise((resolve, reject) => {
+            try {
+                id = mode(...params, (err, res) => {
+                    if (progress) {
+                        clearInterval(timer);
+                        deliver();
+                    }
+
+                    if (err) return reject(err);
+
+                    return resolve(res);
+                });
+            } catch (err) {
+                return reject(err);
+            }
+
+            if (progress) timer = setInterval(deliver, POLL);
+        });
+
+        promise.cancel = () => {
+            // The mode threw before it was scheduled, there is nothing to cancel
+            if (id === undefined) return false;
+
+            return native.cancel(id);
+        };
+
+        return promise;
+    };
+}
+
+module.exports = {
+    background,
+    classify: background(native.classify),
+    conflate: background(native.conflate),
+    consensus: background(native.consensus),
+    dedupe: background(native.dedupe),
+    pg_init: background(native.pg_init),
+    pg_optimize: background(native.pg_optimize),
+    import_addr: background(native.import_addr),
+    import_net: background(native.import_net),
+    cluster_addr: background(native.cluster_addr, 3),
+    cluster_net: background(native.cluster_net, 2),
+    link_addr: background(native.link_addr),
+    intersections: background(native.intersections),
+    topology: background(native.topology)
+};
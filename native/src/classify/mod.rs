@@ -12,6 +12,7 @@ use crate::{
     pg,
     pg::{InputTable, Table},
     stream::{AddrStream, GeoStream, PolyStream},
+    util::{progress, task},
     Tokens,
 };
 
@@ -38,19 +39,27 @@ impl ClassifyArgs {
     }
 }
 
-pub fn classify(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let args: ClassifyArgs = match cx.argument_opt(0) {
+pub fn classify(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let (progress, callback) = progress::from_args(&mut cx, 0)?;
+
+    let args: ClassifyArgs = match progress::args(&mut cx, 0)? {
         None => ClassifyArgs::new(),
-        Some(arg) => {
-            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
-                ClassifyArgs::new()
-            } else {
-                let arg_val = cx.argument::<JsValue>(0)?;
-                neon_serde::from_value(&mut cx, arg_val)?
-            }
-        }
+        Some(arg_val) => neon_serde::from_value(&mut cx, arg_val)?,
     };
 
+    task::run(&mut cx, 1, callback, move |token| {
+        run(args, progress, token)
+    })
+}
+
+///
+/// Import addresses & polygons, classifying the accuracy of each address & writing the result
+///
+fn run(
+    args: ClassifyArgs,
+    progress: progress::Progress,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
     let is_hecate = args.hecate.unwrap_or(false);
 
     let mut output = match args.output {
@@ -120,6 +129,8 @@ pub fn classify(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         None => (),
     };
 
+    let stage = progress.stage("classify", Some(4));
+
     conn.execute(
         "
         ALTER TABLE address
@@ -143,6 +154,8 @@ pub fn classify(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     )
     .unwrap();
     println!("ok - calculated accuracy: building");
+    stage.tick(1);
+    token.check()?;
 
     conn.execute(
         "
@@ -162,6 +175,8 @@ pub fn classify(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     )
     .unwrap();
     println!("ok - calculated parcel centroids");
+    stage.tick(1);
+    token.check()?;
 
     conn.execute(
         "
@@ -178,6 +193,8 @@ pub fn classify(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     )
     .unwrap();
     println!("ok - calculated accuracy: parcel");
+    stage.tick(1);
+    token.check()?;
 
     conn.execute(
         "
@@ -191,8 +208,12 @@ pub fn classify(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     )
     .unwrap();
     println!("ok - calculated accuracy: point");
+    stage.tick(1);
+    drop(stage);
+
+    let stage = progress.stage("classify_output", Some(address.count(&conn) as u64));
 
-    let modified = match is_hecate {
+    let modified: pg::Cursor = match is_hecate {
         true => {
             conn.execute(
                 r#"
@@ -276,15 +297,25 @@ pub fn classify(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     };
 
     for feat in modified {
+        let feat = match feat {
+            Ok(feat) => feat,
+            Err(err) => {
+                return Err(format!("Cursor Error: {}", err));
+            }
+        };
+
         let feat = format!("{}\n", feat.to_string());
         if output.write(feat.as_bytes()).is_err() {
             panic!("Failed to write to output stream");
         }
+
+        stage.tick(1);
+        token.check()?;
     }
 
     if output.flush().is_err() {
         panic!("Failed to flush output stream");
     }
 
-    Ok(cx.boolean(true))
+    Ok(serde_json::Value::Bool(true))
 }
@@ -0,0 +1,459 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+use crate::types::name::Source;
+use crate::Names;
+
+///
+/// How the values of a single property are merged when a persistent
+/// address is modified by one or more new addresses
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Strategy {
+    /// The first new address with the property wins
+    New,
+    /// The persistent value is kept, unless the persistent address doesn't have the property
+    Persistent,
+    /// Distinct values of all addresses are combined into an array
+    Union,
+    /// The largest numeric value wins
+    Max,
+    /// The smallest numeric value wins
+    Min,
+    /// The value of the address with the highest priority source wins
+    Source,
+}
+
+///
+/// The value of a single property & the source of the address it came from
+///
+struct Candidate<'a> {
+    source: &'a str,
+    value: &'a serde_json::Value,
+}
+
+///
+/// Merges the properties of new addresses into a persistent address. Only properties
+/// with a configured strategy are merged, all others retain their persistent value
+///
+#[derive(Debug, Clone)]
+pub struct Merger {
+    strategies: HashMap<String, Strategy>,
+    sources: Vec<String>,
+}
+
+impl Merger {
+    ///
+    /// Sources are ordered from highest to lowest priority
+    ///
+    pub fn new(strategies: HashMap<String, Strategy>, sources: Vec<String>) -> Self {
+        Merger {
+            strategies: strategies,
+            sources: sources,
+        }
+    }
+
+    ///
+    /// Rank of a source, lower being a higher priority. Unknown sources
+    /// rank below all known sources
+    ///
+    pub fn rank(&self, source: &str) -> usize {
+        self.sources
+            .iter()
+            .position(|s| s == source)
+            .unwrap_or(self.sources.len())
+    }
+
+    ///
+    /// Combine the names of a new address with those of the persistent address it matched.
+    ///
+    /// Without a source priority, or between sources of the same rank, new names are
+    /// preferenced over persistent names. A new source ranked above the persistent source
+    /// may demote the persistent primary name, while a new source ranked below it may not,
+    /// and has its generated synonyms dropped. Persistent names are never removed
+    ///
+    pub fn names(
+        &self,
+        persistent: &Names,
+        persistent_source: &str,
+        new: Names,
+        new_source: &str,
+    ) -> Names {
+        let order = if self.sources.is_empty() {
+            Ordering::Equal
+        } else {
+            self.rank(new_source).cmp(&self.rank(persistent_source))
+        };
+
+        let mut combined = match order {
+            Ordering::Less => {
+                let mut persistent = persistent.clone();
+                demote(&mut persistent, &new);
+
+                let mut combined = new;
+                combined.concat(persistent);
+                combined
+            }
+            Ordering::Greater => {
+                let mut new = new;
+                new.names
+                    .retain(|name| name.source != Some(Source::Generated));
+                demote(&mut new, persistent);
+
+                let mut combined = persistent.clone();
+                combined.concat(new);
+                combined
+            }
+            Ordering::Equal => {
+                let mut combined = new;
+                combined.concat(persistent.clone());
+                combined
+            }
+        };
+
+        combined.empty();
+        combined.sort();
+        combined.dedupe();
+        combined
+    }
+
+    ///
+    /// Merge the properties of each new (props, source) into the persistent properties.
+    /// The street property is never merged here as names are merged separately
+    ///
+    pub fn merge(
+        &self,
+        persistent: &serde_json::Map<String, serde_json::Value>,
+        persistent_source: &str,
+        news: &[(serde_json::Map<String, serde_json::Value>, String)],
+    ) -> serde_json::Map<String, serde_json::Value> {
+        let mut merged = persistent.clone();
+
+        for (key, strategy) in self.strategies.iter() {
+            if key == "street" {
+                continue;
+            }
+
+            let mut candidates: Vec<Candidate> = Vec::with_capacity(news.len() + 1);
+
+            if let Some(value) = persistent.get(key) {
+                candidates.push(Candidate {
+                    source: persistent_source,
+                    value: value,
+                });
+            }
+
+            let has_persistent = candidates.len() == 1;
+
+            for (props, source) in news {
+                if let Some(value) = props.get(key) {
+                    candidates.push(Candidate {
+                        source: source.as_str(),
+                        value: value,
+                    });
+                }
+            }
+
+            if let Some(value) = self.resolve(*strategy, has_persistent, &candidates) {
+                merged.insert(key.clone(), value);
+            }
+        }
+
+        merged
+    }
+
+    fn resolve(
+        &self,
+        strategy: Strategy,
+        has_persistent: bool,
+        candidates: &[Candidate],
+    ) -> Option<serde_json::Value> {
+        if candidates.is_empty() {
+            return None;
+        }
+
+        match strategy {
+            Strategy::Persistent => Some(candidates[0].value.clone()),
+            Strategy::New => {
+                let news = if has_persistent {
+                    &candidates[1..]
+                } else {
+                    candidates
+                };
+
+                news.first()
+                    .or(candidates.first())
+                    .map(|candidate| candidate.value.clone())
+            }
+            Strategy::Union => {
+                let mut values: Vec<serde_json::Value> = Vec::new();
+
+                for candidate in candidates {
+                    let items = match candidate.value {
+                        serde_json::Value::Array(items) => items.clone(),
+                        value => vec![value.clone()],
+                    };
+
+                    for item in items {
+                        if !values.contains(&item) {
+                            values.push(item);
+                        }
+                    }
+                }
+
+                Some(serde_json::Value::Array(values))
+            }
+            Strategy::Max | Strategy::Min => {
+                let mut best: Option<(f64, &serde_json::Value)> = None;
+
+                for candidate in candidates {
+                    let num = match number(candidate.value) {
+                        Some(num) => num,
+                        None => continue,
+                    };
+
+                    best = match best {
+                        Some((current, _)) if strategy == Strategy::Max && num <= current => best,
+                        Some((current, _)) if strategy == Strategy::Min && num >= current => best,
+                        _ => Some((num, candidate.value)),
+                    };
+                }
+
+                match best {
+                    Some((_, value)) => Some(value.clone()),
+                    None => Some(candidates[0].value.clone()),
+                }
+            }
+            Strategy::Source => {
+                // min_by_key returns the first of equally ranked candidates,
+                // so the persistent value wins ties
+                candidates
+                    .iter()
+                    .min_by_key(|candidate| self.rank(candidate.source))
+                    .map(|candidate| candidate.value.clone())
+            }
+        }
+    }
+}
+
+///
+/// Lower the priority of any name that would otherwise tie with or outrank the
+/// primary name of the preferred names, other than the primary name itself
+///
+fn demote(names: &mut Names, preferred: &Names) {
+    let mut preferred = preferred.clone();
+    preferred.sort();
+
+    let primary = match preferred.names.first() {
+        Some(primary) => primary,
+        None => return,
+    };
+
+    let tokenized = primary.tokenized_string();
+
+    for name in names.names.iter_mut() {
+        if name.priority >= primary.priority && name.tokenized_string() != tokenized {
+            name.priority = primary.priority.saturating_sub(1);
+        }
+    }
+}
+
+///
+/// Numeric value of a JSON number or numeric string
+///
+fn number(value: &serde_json::Value) -> Option<f64> {
+    match value {
+        serde_json::Value::Number(num) => num.as_f64(),
+        serde_json::Value::String(string) => string.trim().parse::<f64>().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::name::Name;
+    use crate::{Context, Tokens};
+
+    fn props(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        match value {
+            serde_json::Value::Object(props) => props,
+            _ => panic!("props must be an object"),
+        }
+    }
+
+    fn merger(strategies: &[(&str, Strategy)]) -> Merger {
+        Merger::new(
+            strategies
+                .iter()
+                .map(|(key, strategy)| (key.to_string(), *strategy))
+                .collect(),
+            vec![String::from("county"), String::from("osm")],
+        )
+    }
+
+    #[test]
+    fn test_merge_default() {
+        let persistent = props(serde_json::json!({ "postcode": "20002", "street": [] }));
+        let news = vec![(
+            props(serde_json::json!({ "postcode": "20003", "unit": "1" })),
+            String::from("osm"),
+        )];
+
+        // properties without a strategy are untouched
+        assert_eq!(merger(&[]).merge(&persistent, "county", &news), persistent);
+    }
+
+    #[test]
+    fn test_merge_strategies() {
+        let persistent = props(serde_json::json!({
+            "postcode": "20002",
+            "tags": ["a"],
+            "floors": 2,
+            "unit": "1",
+            "street": [{ "display": "Main St" }]
+        }));
+
+        let news = vec![
+            (
+                props(serde_json::json!({
+                    "postcode": "20003",
+                    "tags": ["b", "a"],
+                    "floors": "4",
+                    "unit": "2",
+                    "accuracy": "rooftop",
+                    "street": []
+                })),
+                String::from("osm"),
+            ),
+            (
+                props(serde_json::json!({
+                    "postcode": "20004",
+                    "tags": "c",
+                    "floors": 1,
+                    "unit": "3"
+                })),
+                String::from("county"),
+            ),
+        ];
+
+        let merged = merger(&[
+            ("postcode", Strategy::New),
+            ("tags", Strategy::Union),
+            ("floors", Strategy::Max),
+            ("unit", Strategy::Source),
+            ("accuracy", Strategy::Persistent),
+            ("street", Strategy::New),
+        ])
+        .merge(&persistent, "feed", &news);
+
+        assert_eq!(
+            serde_json::Value::Object(merged),
+            serde_json::json!({
+                "postcode": "20003",
+                "tags": ["a", "b", "c"],
+                "floors": "4",
+                "unit": "3",
+                "accuracy": "rooftop",
+                "street": [{ "display": "Main St" }]
+            })
+        );
+
+        let merged = merger(&[("floors", Strategy::Min), ("unit", Strategy::Source)]).merge(
+            &persistent,
+            "county",
+            &news,
+        );
+
+        assert_eq!(merged["floors"], serde_json::json!(1));
+        // the persistent value wins ties between sources of the same rank
+        assert_eq!(merged["unit"], serde_json::json!("1"));
+    }
+
+    fn names(names: &[(&str, i8, Source)], context: &Context) -> Names {
+        Names {
+            names: names
+                .iter()
+                .map(|(display, priority, source)| {
+                    Name::new(
+                        display.to_string(),
+                        *priority,
+                        Some(source.clone()),
+                        context,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    fn displays(names: &Names) -> Vec<(String, i8)> {
+        names
+            .names
+            .iter()
+            .map(|name| (name.display.clone(), name.priority))
+            .collect()
+    }
+
+    #[test]
+    fn test_names() {
+        let context = Context::new(
+            String::from("us"),
+            None,
+            Tokens::generate(vec![String::from("en")]),
+        );
+
+        let persistent = names(&[("Main St", 0, Source::Address)], &context);
+        let new = names(
+            &[
+                ("Main Street", 0, Source::Address),
+                ("Central Ave", 0, Source::Address),
+                ("Central", -1, Source::Generated),
+            ],
+            &context,
+        );
+
+        // without a source priority new names are preferenced
+        assert_eq!(
+            displays(&Merger::new(HashMap::new(), Vec::new()).names(
+                &persistent,
+                "feed",
+                new.clone(),
+                "osm"
+            )),
+            vec![
+                (String::from("Main Street"), 0),
+                (String::from("Central Ave"), 0),
+                (String::from("Central"), -1),
+            ]
+        );
+
+        // a lower ranked source can't demote the persistent primary & loses its generated synonyms
+        assert_eq!(
+            displays(&merger(&[]).names(&persistent, "county", new.clone(), "osm")),
+            vec![
+                (String::from("Main Street"), 0),
+                (String::from("Central Ave"), -1),
+            ]
+        );
+
+        // a higher ranked source may demote the persistent primary
+        let new = names(&[("Central Ave", 0, Source::Address)], &context);
+        assert_eq!(
+            displays(&merger(&[]).names(&persistent, "feed", new, "county")),
+            vec![
+                (String::from("Central Ave"), 0),
+                (String::from("Main St"), -1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rank() {
+        let merger = merger(&[]);
+
+        assert_eq!(merger.rank("county"), 0);
+        assert_eq!(merger.rank("osm"), 1);
+        assert_eq!(merger.rank("feed"), 2);
+    }
+}
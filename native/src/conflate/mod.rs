@@ -1,8 +1,7 @@
 use geojson::GeoJson;
-use postgres::{Connection, TlsMode};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::From;
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{BufWriter, Write};
 
 use neon::prelude::*;
@@ -11,12 +10,23 @@ use crate::{
     hecate,
     stream::{AddrStream, GeoStream},
     types::name::InputName,
-    util::linker,
+    util::{distance, linker, partition::Parallel, progress, task},
     Address, Names,
 };
 
-use super::pg;
-use super::pg::{InputTable, Table};
+mod merge;
+mod store;
+mod summary;
+
+pub use self::merge::{Merger, Strategy};
+pub use self::store::{Backend, Memory, Postgres, Store};
+pub use self::summary::{Reason, Summary};
+
+///
+/// Default maximum fraction of in scope persistent addresses that may be deleted
+/// before conflate refuses to output a diff, guarding against a truncated new source
+///
+pub const DELETE_RATIO: f64 = 0.1;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct ConflateArgs {
@@ -27,6 +37,19 @@ struct ConflateArgs {
     error_address: Option<String>,
     error_persistent: Option<String>,
     output: Option<String>,
+    radius: Option<f64>,
+    delete: Option<bool>,
+    scope_source: Option<String>,
+    scope_polygon: Option<String>,
+    delete_ratio: Option<f64>,
+    move_threshold: Option<f64>,
+    prefer_accuracy: Option<bool>,
+    merge: Option<HashMap<String, Strategy>>,
+    source_priority: Option<Vec<String>>,
+    backend: Option<Backend>,
+    workers: Option<usize>,
+    window: Option<i64>,
+    summary: Option<String>,
 }
 
 impl ConflateArgs {
@@ -39,76 +62,207 @@ impl ConflateArgs {
             error_address: None,
             error_persistent: None,
             output: None,
+            radius: None,
+            delete: None,
+            scope_source: None,
+            scope_polygon: None,
+            delete_ratio: None,
+            move_threshold: None,
+            prefer_accuracy: None,
+            merge: None,
+            source_priority: None,
+            backend: None,
+            workers: None,
+            window: None,
+            summary: None,
+        }
+    }
+
+    ///
+    /// Distance (metres) within which persistent addresses are compared to a new address
+    ///
+    pub fn radius(&self) -> f64 {
+        self.radius.unwrap_or(distance::SEARCH_RADIUS)
+    }
+
+    ///
+    /// Maximum fraction of in scope persistent addresses that may be deleted
+    ///
+    pub fn delete_ratio(&self) -> f64 {
+        self.delete_ratio.unwrap_or(DELETE_RATIO)
+    }
+
+    ///
+    /// Per property merge strategies, sources are ordered from highest to lowest priority
+    ///
+    pub fn merger(&self) -> Merger {
+        Merger::new(
+            self.merge.clone().unwrap_or(HashMap::new()),
+            self.source_priority.clone().unwrap_or(Vec::new()),
+        )
+    }
+
+    ///
+    /// New addresses are matched in windows of this many addresses, across the workers
+    ///
+    pub fn parallel(&self) -> Parallel {
+        Parallel {
+            workers: self.workers,
+            window: self.window,
+            pool: None,
+            partition: None,
+        }
+    }
+
+    pub fn moves(&self) -> Option<Moves> {
+        match self.move_threshold {
+            Some(threshold) => Some(Moves {
+                threshold: threshold,
+                prefer_accuracy: self.prefer_accuracy.unwrap_or(false),
+            }),
+            None => None,
+        }
+    }
+}
+
+///
+/// Policy for moving a persistent address to the geometry of the new address it matched
+///
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Moves {
+    /// Minimum displacement (metres) before a persistent address is moved
+    pub threshold: f64,
+
+    /// Never move a persistent address to a point of lower accuracy
+    pub prefer_accuracy: bool,
+}
+
+impl Moves {
+    ///
+    /// Should the persistent address be moved to the geometry of the new address
+    ///
+    pub fn should_move(&self, potential: &Address, persistent: &Address) -> bool {
+        if self.prefer_accuracy && accuracy(potential) < accuracy(persistent) {
+            return false;
         }
+
+        distance::metres(
+            [potential.geom[0], potential.geom[1]],
+            [persistent.geom[0], persistent.geom[1]],
+        ) > self.threshold
     }
 }
 
-pub fn conflate(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let args: ConflateArgs = match cx.argument_opt(0) {
+///
+/// Rank the accuracy property of an address, higher being more accurate
+///
+pub fn accuracy(addr: &Address) -> u8 {
+    match addr
+        .props
+        .get("accuracy")
+        .and_then(|accuracy| accuracy.as_str())
+    {
+        Some("rooftop") => 3,
+        Some("parcel") => 2,
+        Some("point") => 1,
+        _ => 0,
+    }
+}
+
+pub fn conflate(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let (progress, callback) = progress::from_args(&mut cx, 0)?;
+
+    let args: ConflateArgs = match progress::args(&mut cx, 0)? {
         None => ConflateArgs::new(),
         Some(arg) => {
-            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
-                ConflateArgs::new()
-            } else {
-                let arg_obj = cx.argument::<JsObject>(0)?;
-
-                let db = arg_obj.get(&mut cx, "db")?;
-                if db.is_a::<JsUndefined>() || db.is_a::<JsNull>() {
-                    let default = cx.string("conflate");
-                    arg_obj.set(&mut cx, "db", default)?;
-                }
+            let arg_obj = arg.downcast::<JsObject>().or_throw(&mut cx)?;
 
-                let arg_val = arg_obj.as_value(&mut cx);
-                neon_serde::from_value(&mut cx, arg_val)?
+            let db = arg_obj.get(&mut cx, "db")?;
+            if db.is_a::<JsUndefined>() || db.is_a::<JsNull>() {
+                let default = cx.string("conflate");
+                arg_obj.set(&mut cx, "db", default)?;
             }
+
+            let arg_val = arg_obj.as_value(&mut cx);
+            neon_serde::from_value(&mut cx, arg_val)?
         }
     };
+
+    task::run(&mut cx, 1, callback, move |token| {
+        run(args, progress, token)
+    })
+}
+
+///
+/// Import persistent addresses & conflate new addresses against them, writing the resulting hecate diff
+///
+/// The diff is written alongside the output file & only renamed into place once conflate
+/// succeeds, so that an error, ie: the delete ratio guard, never leaves a partial diff behind
+///
+fn run(
+    args: ConflateArgs,
+    progress: progress::Progress,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
     if args.in_persistent.is_none() {
         panic!("in_persistent argument is required");
     } else if args.in_address.is_none() {
         panic!("in_address argument is required");
     }
 
-    let mut output = match args.output {
+    let delete = args.delete.unwrap_or(false);
+    if delete && args.scope_source.is_none() && args.scope_polygon.is_none() {
+        panic!("delete requires a scope_source or scope_polygon argument");
+    }
+
+    let path = match args.output {
         None => panic!("Output file required"),
-        Some(output) => match File::create(output) {
-            Ok(outfile) => BufWriter::new(outfile),
-            Err(err) => panic!("Unable to write to output file: {}", err),
-        },
+        Some(ref output) => output.clone(),
     };
+    let partial = format!("{}.partial", &path);
 
-    let conn = Connection::connect(
-        format!("postgres://postgres@localhost:5432/{}", &args.db).as_str(),
-        TlsMode::None,
-    )
-    .unwrap();
-
-    conn.execute(
-        "
-        DROP TABLE IF EXISTS modified;
-    ",
-        &[],
-    )
-    .unwrap();
-
-    conn.execute(
-        "
-        CREATE UNLOGGED TABLE modified (
-            id BIGINT,
-            version BIGINT,
-            netid BIGINT,
-            names JSONB,
-            number TEXT,
-            source TEXT,
-            output BOOLEAN,
-            interpolate BOOLEAN,
-            props JSONB,
-            geom GEOMETRY(POINT, 4326)
-        );
-    ",
-        &[],
-    )
-    .unwrap();
+    let mut output = match File::create(&partial) {
+        Ok(outfile) => BufWriter::new(outfile),
+        Err(err) => panic!("Unable to write to output file: {}", err),
+    };
+
+    let result =
+        diff(args, delete, &mut output, progress, token).and_then(|res| match output.flush() {
+            Ok(_) => Ok(res),
+            Err(err) => Err(format!("Unable to write to output file: {}", err)),
+        });
+
+    drop(output);
+
+    match result {
+        Ok(res) => match fs::rename(&partial, &path) {
+            Ok(_) => Ok(res),
+            Err(err) => Err(format!("Unable to write to output file: {}", err)),
+        },
+        Err(err) => {
+            // The partial diff is of no use, the error is what should be surfaced
+            let _ = fs::remove_file(&partial);
+
+            Err(err)
+        }
+    }
+}
+
+///
+/// Conflate new addresses against the persistent addresses, writing the diff to output
+///
+fn diff(
+    args: ConflateArgs,
+    delete: bool,
+    output: &mut BufWriter<File>,
+    progress: progress::Progress,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
+    let radius = args.radius();
+    let delete_ratio = args.delete_ratio();
+    let moves = args.moves();
+    let merger = args.merger();
+    let parallel = args.parallel();
 
     let context = match args.context {
         Some(context) => crate::Context::from(context),
@@ -119,153 +273,260 @@ pub fn conflate(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         ),
     };
 
-    let pgaddress = pg::Address::new();
-    pgaddress.create(&conn);
-    pgaddress.input(
-        &conn,
-        AddrStream::new(
-            GeoStream::new(args.in_persistent),
-            context.clone(),
-            args.error_persistent,
-        ),
-    );
-    pgaddress.index(&conn);
-    pg::address::pre_conflate(&conn);
+    let mut store = store::create(
+        args.backend.unwrap_or(Backend::Postgres),
+        &args.db,
+        &context,
+        args.in_persistent.unwrap(),
+        args.error_persistent,
+        args.scope_polygon.clone(),
+    )?;
+
+    // persistent addresses matched by a new address, which are never deleted
+    let mut matched: HashSet<i64> = HashSet::new();
 
-    for addr in AddrStream::new(
+    // persistent addresses to move, the most accurate & then first new address matched wins
+    let mut moved: HashMap<i64, (u8, Vec<f64>, Option<serde_json::Value>)> = HashMap::new();
+
+    // deleted persistent addresses already restored by a new address
+    let mut restored: HashSet<i64> = HashSet::new();
+
+    let mut summary = Summary::new();
+
+    let stage = progress.stage("conflate", None);
+
+    let mut addrs = AddrStream::new(
         GeoStream::new(args.in_address),
         context.clone(),
         args.error_address,
-    ) {
-        // find all persistent addresses with the same address number
-        // within 0.01 decimal degrees (~ 1 km) of the new address
-        let rows = conn
-            .query(
-                "
-            SELECT
-                json_build_object(
-                    'id', p.id,
-                    'number', p.number,
-                    'version', p.version,
-                    'names', p.names,
-                    'output', p.output,
-                    'source', p.source,
-                    'props', p.props,
-                    'geom', ST_AsGeoJSON(p.geom)::TEXT
-                )
-            FROM
-                address p
-            WHERE
-                p.number = $1
-                AND ST_DWithin(ST_SetSRID(ST_Point($2, $3), 4326), p.geom, 0.01);
-        ",
-                &[&addr.number, &addr.geom[0], &addr.geom[1]],
-            )
-            .unwrap();
-
-        let mut persistents: Vec<Address> = Vec::with_capacity(rows.len());
-
-        for row in rows.iter() {
-            let paddr: serde_json::Value = row.get(0);
-            let paddr = Address::from_value(paddr).unwrap();
-            persistents.push(paddr);
+    );
+
+    loop {
+        let batch: Vec<Address> = addrs.by_ref().take(parallel.window() as usize).collect();
+
+        if batch.is_empty() {
+            break;
         }
 
-        match compare(&addr, &mut persistents) {
-            // persistent address matches new address, consider modifying persistent address
-            Some(link_id) => {
-                let mut pmatches: Vec<&mut Address> = persistents
-                    .iter_mut()
-                    .filter(|persistent| {
-                        // addresses with output set to false should not be modified
-                        link_id == persistent.id.unwrap() && persistent.output
-                    })
-                    .collect();
-
-                match pmatches.len() {
-                    // if all matches have output set to false, don't modify
-                    0 => continue,
-                    1 => {
-                        let paddr = pmatches.pop().unwrap();
-                        // if the new address has names the persistent address does not, modify persistent
-                        if paddr.names.has_diff(&addr.names) {
-                            // preference new names over persistent names
-                            let mut combined_names = addr.names;
-                            combined_names.concat(paddr.names.clone());
-                            combined_names.empty();
-                            combined_names.sort();
-                            combined_names.dedupe();
-
-                            let mut new_names: Vec<InputName> =
-                                Vec::with_capacity(combined_names.names.len());
-                            for name in combined_names.names {
-                                new_names.push(InputName::from(name));
+        token.check()?;
+
+        // find all persistent addresses with the same house number
+        // within the search radius (default 1 km) of each new address
+        let mut candidates = store.nearby_batch(&batch, radius);
+        let links = compare_batch(&batch, &mut candidates, parallel.workers());
+
+        // results are applied in input order so that output is deterministic
+        for ((addr, mut persistents), link) in batch.into_iter().zip(candidates).zip(links) {
+            stage.tick(1);
+
+            match link {
+                // persistent address matches new address, consider modifying persistent address
+                Some(link_id) => {
+                    matched.insert(link_id.abs());
+                    summary.matched(link_id.abs());
+
+                    // the new address matches a deleted persistent address, restore it
+                    // with the version of the delete rather than creating a duplicate
+                    if let Some(version) = store.deleted().get(&link_id.abs()).cloned() {
+                        if restored.insert(link_id.abs()) {
+                            let source = addr.source.clone();
+
+                            let mut addr = addr;
+                            addr.id = Some(link_id.abs());
+                            addr.version = version;
+
+                            let line =
+                                GeoJson::Feature(addr.to_geojson(hecate::Action::Restore, false))
+                                    .to_string();
+
+                            output.write(format!("{}\n", line).as_bytes()).unwrap();
+                            summary.restore(&source, &line);
+                        }
+
+                        continue;
+                    }
+
+                    let mut pmatches: Vec<&mut Address> = persistents
+                        .iter_mut()
+                        .filter(|persistent| {
+                            // addresses with output set to false should not be modified
+                            link_id == persistent.id.unwrap() && persistent.output
+                        })
+                        .collect();
+
+                    match pmatches.len() {
+                        // if all matches have output set to false, don't modify
+                        0 => continue,
+                        1 => {
+                            let paddr = pmatches.pop().unwrap();
+
+                            let new_props = addr.props.clone();
+                            let new_source = addr.source.clone();
+
+                            // do any of the properties with a merge strategy change
+                            let reprop = merger.merge(
+                                &paddr.props,
+                                &paddr.source,
+                                &[(new_props.clone(), new_source.clone())],
+                            ) != paddr.props;
+
+                            let relocate = match moves {
+                                Some(moves) => moves.should_move(&addr, paddr),
+                                None => false,
+                            };
+
+                            if relocate {
+                                let rank = accuracy(&addr);
+
+                                let replace = match moved.get(&link_id) {
+                                    Some((moved_rank, _, _)) => rank > *moved_rank,
+                                    None => true,
+                                };
+
+                                if replace {
+                                    moved.insert(
+                                        link_id,
+                                        (
+                                            rank,
+                                            addr.geom.clone(),
+                                            addr.props.get("accuracy").cloned(),
+                                        ),
+                                    );
+                                }
+                            }
+
+                            let rename = paddr.names.has_diff(&addr.names);
+
+                            if rename {
+                                summary.reason(link_id, Reason::Name);
+                            }
+                            if relocate {
+                                summary.reason(link_id, Reason::Moved);
+                            }
+                            if reprop {
+                                summary.reason(link_id, Reason::Property);
                             }
-                            let new_names = serde_json::to_value(new_names).unwrap();
 
-                            // overwrite the persistent street property with the combined names
-                            // retain all other persistent address properties
-                            paddr.props.insert(String::from("street"), new_names);
-                            paddr.to_db(&conn, "modified").unwrap();
+                            // if the new address has names the persistent address does not, modify persistent
+                            if rename {
+                                // order names by the priority of their sources
+                                let combined_names = merger.names(
+                                    &paddr.names,
+                                    &paddr.source,
+                                    addr.names,
+                                    &new_source,
+                                );
+
+                                let mut new_names: Vec<InputName> =
+                                    Vec::with_capacity(combined_names.names.len());
+                                for name in combined_names.names {
+                                    new_names.push(InputName::from(name));
+                                }
+                                let new_names = serde_json::to_value(new_names).unwrap();
+
+                                // overwrite the persistent street property with the combined names
+                                // retain all other persistent address properties
+                                paddr.props.insert(String::from("street"), new_names);
+                                store.modify(paddr, new_props, &new_source);
+                            } else if relocate || reprop {
+                                store.modify(paddr, new_props, &new_source);
+                            }
+                        }
+                        _ => {
+                            return Err(format!(
+                                "Persistent address {} has conflicting duplicate versions",
+                                link_id
+                            ));
                         }
                     }
-                    _ => panic!("Duplicate IDs are not allowed in input data"),
                 }
-            }
-            // no match in persistent addresses, write new address to output
-            None => {
-                output
-                    .write(
-                        format!(
-                            "{}\n",
-                            GeoJson::Feature(addr.to_geojson(hecate::Action::Create, false))
-                                .to_string()
-                        )
-                        .as_bytes(),
-                    )
-                    .unwrap();
-            }
-        };
+                // no match in persistent addresses, write new address to output
+                None => {
+                    let source = addr.source.clone();
+                    let line = GeoJson::Feature(addr.to_geojson(hecate::Action::Create, false))
+                        .to_string();
+
+                    output.write(format!("{}\n", line).as_bytes()).unwrap();
+                    summary.create(&source, &line);
+                }
+            };
+        }
     }
 
-    let modifieds = pg::Cursor::new(
-        conn,
-        format!(
-            "
-        SELECT
-            json_build_object(
-                'id', id,
-                'type', 'Feature',
-                'action', 'modify',
-                'version', version,
-                'properties', JSONB_AGG(props),
-                'geometry', ST_AsGeoJSON(geom)::JSON
-            )
-        FROM
-            modified
-        GROUP BY
-            id,
-            version,
-            geom
-    "
-        ),
-    )
-    .unwrap();
+    drop(stage);
+
+    // every modification of a moved address takes the winning geometry & its accuracy
+    for (id, (_, geom, accuracy)) in moved.iter() {
+        store.relocate(*id, geom, accuracy);
+    }
+
+    let deletes = if delete {
+        let scoped = store.scoped(&args.scope_source);
+        let total = scoped.len();
+
+        let deletes: Vec<Address> = scoped
+            .into_iter()
+            .filter(|paddr| !matched.contains(&paddr.id.unwrap()))
+            .collect();
+
+        if total > 0 && deletes.len() as f64 / total as f64 > delete_ratio {
+            return Err(format!(
+                "Refusing to delete {} of {} persistent addresses in scope, above the delete ratio of {}",
+                deletes.len(),
+                total,
+                delete_ratio
+            ));
+        }
+
+        deletes
+    } else {
+        Vec::new()
+    };
+
+    let stage = progress.stage("conflate_modified", None);
+
+    let modifieds = store.modifieds()?;
+
+    for modified in modifieds {
+        let mut modified = match modified {
+            Ok(modified) => modified,
+            Err(err) => {
+                return Err(format!("Cursor Error: {}", err));
+            }
+        };
 
-    for mut modified in modifieds {
         let modified_obj = modified.as_object_mut().unwrap();
+
+        let source = match modified_obj.remove(&String::from("source")) {
+            Some(serde_json::Value::String(source)) => source,
+            _ => String::from(""),
+        };
+
+        let new_props = modified_obj.remove(&String::from("new_props")).unwrap();
+        let new_sources = modified_obj.remove(&String::from("new_sources")).unwrap();
+        let news: Vec<(serde_json::Map<String, serde_json::Value>, String)> = new_props
+            .as_array()
+            .unwrap()
+            .iter()
+            .zip(new_sources.as_array().unwrap().iter())
+            .filter_map(|(props, source)| match props {
+                serde_json::Value::Object(props) => {
+                    Some((props.clone(), String::from(source.as_str().unwrap_or(""))))
+                }
+                _ => None,
+            })
+            .collect();
+
+        let id = modified_obj.get("id").and_then(|id| id.as_i64()).unwrap();
+        let sources: Vec<String> = news.iter().map(|(_, source)| source.clone()).collect();
+
         let mut props = modified_obj.remove(&String::from("properties")).unwrap();
         let props_arr = props.as_array_mut().unwrap();
-        if props_arr.len() == 1 {
-            let props = props_arr.pop().unwrap();
-
-            modified_obj.insert(String::from("properties"), props);
+        let props_base = if props_arr.len() == 1 {
+            props_arr.pop().unwrap()
         } else {
             // if a single persistent address matches multiple new addresses and should be updated
-
-            // Future TODO: This currently just grabs the first property
-            // and merges names together, it does not attempt to merge
-            // other properties
+            // the names of every modification are merged together
             let mut props_base = props_arr.pop().unwrap();
             let props_base_obj = props_base.as_object_mut().unwrap();
             let names_base: Vec<InputName> =
@@ -295,8 +556,20 @@ pub fn conflate(mut cx: FunctionContext) -> JsResult<JsBoolean> {
                 String::from("street"),
                 serde_json::to_value(names_final).unwrap(),
             );
-            modified_obj.insert(String::from("properties"), props_base);
-        }
+
+            props_base
+        };
+
+        // all other properties are merged using their configured strategy
+        let props_base = match props_base {
+            serde_json::Value::Object(props_base) => merger.merge(&props_base, &source, &news),
+            _ => panic!("Modified properties should always be an object"),
+        };
+
+        modified_obj.insert(
+            String::from("properties"),
+            serde_json::Value::Object(props_base),
+        );
 
         let modified = match modified {
             serde_json::Value::Object(modified) => modified,
@@ -308,12 +581,78 @@ pub fn conflate(mut cx: FunctionContext) -> JsResult<JsBoolean> {
             Err(e) => panic!(e),
         };
 
-        output
-            .write(format!("{}\n", modified.to_string()).as_bytes())
-            .unwrap();
+        let line = modified.to_string();
+        output.write(format!("{}\n", line).as_bytes()).unwrap();
+
+        let sources: Vec<&str> = sources.iter().map(|source| source.as_str()).collect();
+        summary.modify(id, &sources, &line);
+
+        stage.tick(1);
+        token.check()?;
+    }
+
+    drop(stage);
+    let stage = progress.stage("conflate_deleted", Some(deletes.len() as u64));
+
+    for paddr in deletes {
+        let source = paddr.source.clone();
+        let line = GeoJson::Feature(paddr.to_geojson(hecate::Action::Delete, false)).to_string();
+
+        output.write(format!("{}\n", line).as_bytes()).unwrap();
+        summary.delete(&source, &line);
+
+        stage.tick(1);
+        token.check()?;
+    }
+
+    if let Some(path) = args.summary {
+        let report = match File::create(path) {
+            Ok(report) => report,
+            Err(err) => panic!("Unable to write to summary file: {}", err),
+        };
+
+        serde_json::to_writer_pretty(BufWriter::new(report), &summary.finish()).unwrap();
+    }
+
+    Ok(serde_json::Value::Bool(true))
+}
+
+///
+/// Compare each address of a batch against its list of proximal addresses, splitting
+/// the batch across worker threads. Results are returned in the order of the batch
+///
+pub fn compare_batch(
+    batch: &[Address],
+    persistents: &mut [Vec<Address>],
+    workers: usize,
+) -> Vec<Option<i64>> {
+    if batch.is_empty() {
+        return Vec::new();
     }
 
-    Ok(cx.boolean(true))
+    let size = (batch.len() + workers.max(1) - 1) / workers.max(1);
+
+    crossbeam::scope(|scope| {
+        let strands: Vec<_> = batch
+            .chunks(size)
+            .zip(persistents.chunks_mut(size))
+            .map(|(addrs, persistents)| {
+                scope.spawn(move |_| {
+                    addrs
+                        .iter()
+                        .zip(persistents.iter_mut())
+                        .map(|(addr, persistents)| compare(addr, persistents))
+                        .collect::<Vec<Option<i64>>>()
+                })
+            })
+            .collect();
+
+        strands
+            .into_iter()
+            .flat_map(|strand| strand.join().unwrap())
+            .collect()
+    })
+    .unwrap()
 }
 
 ///
@@ -0,0 +1,1180 @@
+use postgres::{Connection, TlsMode};
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use geo::algorithm::contains::Contains;
+use geo::algorithm::euclidean_distance::EuclideanDistance;
+
+use crate::{
+    pg::{self, InputTable, Table},
+    stream::{AddrStream, GeoStream, PolyStream},
+    types::HouseNumber,
+    util::distance,
+    Address, Context,
+};
+
+///
+/// Where persistent addresses are held while conflating
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum Backend {
+    /// Persistent addresses are imported into PostGIS
+    Postgres,
+    /// Persistent addresses are loaded into an in memory RTree, no database is required
+    Memory,
+}
+
+///
+/// Persistent addresses & the modifications made to them by new addresses
+///
+pub trait Store {
+    ///
+    /// Persistent addresses with the same house number as the new address, within radius
+    /// metres of it. Candidates are found by the numeric key of the number & then
+    /// filtered by HouseNumber::same
+    ///
+    fn nearby(&self, addr: &Address, radius: f64) -> Vec<Address>;
+
+    ///
+    /// Persistent addresses near each of a batch of new addresses, in the order of the batch
+    ///
+    fn nearby_batch(&self, addrs: &[Address], radius: f64) -> Vec<Vec<Address>> {
+        addrs.iter().map(|addr| self.nearby(addr, radius)).collect()
+    }
+
+    ///
+    /// Record the modification of a persistent address by a new address, with the
+    /// properties & source of the new address for merging
+    ///
+    fn modify(
+        &mut self,
+        paddr: &Address,
+        new_props: serde_json::Map<String, serde_json::Value>,
+        new_source: &str,
+    );
+
+    ///
+    /// Move every modification of a persistent address to the given geometry & accuracy
+    ///
+    fn relocate(&mut self, id: i64, geom: &[f64], accuracy: &Option<serde_json::Value>);
+
+    ///
+    /// Return the current version of every persistent address within the delete scope,
+    /// limited to the given source and/or the scope polygons, ordered by id
+    ///
+    fn scoped(&self, source: &Option<String>) -> Vec<Address>;
+
+    ///
+    /// Ids of persistent addresses whose latest version is a delete, with the version of the delete
+    ///
+    fn deleted(&self) -> &HashMap<i64, i64>;
+
+    ///
+    /// Modified features, one per persistent address ordered by id, with the properties,
+    /// new properties & new sources of every modification in the order they were made
+    ///
+    fn modifieds(
+        self: Box<Self>,
+    ) -> Result<Box<dyn Iterator<Item = Result<serde_json::Value, String>>>, String>;
+}
+
+///
+/// Create the store for the given backend, loading the persistent addresses & scope polygons
+///
+/// Fails if the persistent addresses contain more than one feature with the same id & version
+///
+pub fn create(
+    backend: Backend,
+    db: &str,
+    context: &Context,
+    persistent: String,
+    error_persistent: Option<String>,
+    scope_polygon: Option<String>,
+) -> Result<Box<dyn Store>, String> {
+    let deletes = deletes(&persistent);
+
+    let addrs = AddrStream::new(
+        GeoStream::new(Some(persistent)),
+        context.clone(),
+        error_persistent,
+    );
+
+    Ok(match backend {
+        Backend::Postgres => Box::new(Postgres::new(db, addrs, deletes, scope_polygon)?),
+        Backend::Memory => Box::new(Memory::new(addrs, deletes, scope_polygon)?),
+    })
+}
+
+///
+/// Scan persistent input for Hecate history features with a delete action,
+/// returning the id & latest deleted version of each
+///
+/// Only lines mentioning a delete are parsed, as deletes are rare in persistent input
+///
+pub fn deletes(path: &str) -> HashMap<i64, i64> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) => panic!("Unable to open input file: {}", err),
+    };
+
+    let mut deletes: HashMap<i64, i64> = HashMap::new();
+
+    for line in BufReader::new(file).lines() {
+        let line = line.unwrap();
+
+        if !line.contains("delete") {
+            continue;
+        }
+
+        let feat: serde_json::Value = match serde_json::from_str(line.trim_matches('\u{001E}')) {
+            Ok(feat) => feat,
+            Err(_) => continue,
+        };
+
+        if feat.get("action").and_then(|action| action.as_str()) != Some("delete") {
+            continue;
+        }
+
+        let (id, version) = match (
+            feat.get("id").and_then(|id| id.as_i64()),
+            feat.get("version").and_then(|version| version.as_i64()),
+        ) {
+            (Some(id), Some(version)) => (id, version),
+            _ => continue,
+        };
+
+        let latest = deletes.entry(id).or_insert(version);
+        if version > *latest {
+            *latest = version;
+        }
+    }
+
+    deletes
+}
+
+///
+/// Map each distinct persistent number to its HouseNumber key, so that candidates
+/// can be found by key without reimplementing the key rules in SQL
+///
+fn keys(conn: &Connection) {
+    conn.execute(
+        "
+        DROP TABLE IF EXISTS address_number_keys;
+    ",
+        &[],
+    )
+    .unwrap();
+
+    conn.execute(
+        "
+        CREATE UNLOGGED TABLE address_number_keys (
+            number TEXT,
+            key TEXT
+        );
+    ",
+        &[],
+    )
+    .unwrap();
+
+    let rows = conn
+        .query(
+            "
+        SELECT DISTINCT
+            number
+        FROM
+            address
+    ",
+            &[],
+        )
+        .unwrap();
+
+    let mut data = String::new();
+    for row in rows.iter() {
+        let number: String = row.get(0);
+        let key = HouseNumber::new(&number).key;
+
+        data.push_str(&format!("{}\t{}\n", number, key));
+    }
+
+    let stmt = conn
+        .prepare(
+            r#"
+        COPY address_number_keys (
+            number,
+            key
+        )
+        FROM STDIN
+        WITH (
+            FORMAT CSV,
+            NULL '',
+            DELIMITER E'\t',
+            QUOTE E'\b'
+        )
+    "#,
+        )
+        .unwrap();
+
+    stmt.copy_in(&[], &mut data.as_bytes()).unwrap();
+
+    conn.execute(
+        "
+        CREATE INDEX address_number_keys_idx ON address_number_keys (key);
+    ",
+        &[],
+    )
+    .unwrap();
+}
+
+///
+/// Error describing a persistent address with duplicate features of the same version
+///
+fn conflict(id: i64, version: i64, count: i64) -> String {
+    format!(
+        "Persistent address {} has {} conflicting features with version {}, ids & versions must be unique",
+        id, count, version
+    )
+}
+
+///
+/// PostGIS backed store, persistent addresses are imported into the address table &
+/// modifications are recorded in the modified table
+///
+pub struct Postgres {
+    conn: Connection,
+    scope: bool,
+    deleted: HashMap<i64, i64>,
+}
+
+impl Postgres {
+    pub fn new(
+        db: &str,
+        addrs: AddrStream,
+        deletes: HashMap<i64, i64>,
+        scope_polygon: Option<String>,
+    ) -> Result<Self, String> {
+        let conn = Connection::connect(
+            format!("postgres://postgres@localhost:5432/{}", db).as_str(),
+            TlsMode::None,
+        )
+        .unwrap();
+
+        conn.execute(
+            "
+            DROP TABLE IF EXISTS modified;
+        ",
+            &[],
+        )
+        .unwrap();
+
+        conn.execute(
+            "
+            CREATE UNLOGGED TABLE modified (
+                id BIGINT,
+                version BIGINT,
+                netid BIGINT,
+                names JSONB,
+                number TEXT,
+                source TEXT,
+                output BOOLEAN,
+                interpolate BOOLEAN,
+                props JSONB,
+                new_props JSONB,
+                new_source TEXT,
+                seq SERIAL,
+                geom GEOMETRY(POINT, 4326)
+            );
+        ",
+            &[],
+        )
+        .unwrap();
+
+        let pgaddress = pg::Address::new();
+        pgaddress.create(&conn);
+        pgaddress.input(&conn, addrs);
+        pgaddress.index(&conn);
+        pg::address::pre_conflate(&conn);
+        keys(&conn);
+
+        let rows = conn
+            .query(
+                "
+            SELECT
+                ABS(id),
+                version,
+                COUNT(*)
+            FROM
+                address
+            GROUP BY
+                ABS(id),
+                version
+            HAVING
+                COUNT(*) > 1
+            ORDER BY
+                ABS(id),
+                version
+            LIMIT 1
+        ",
+                &[],
+            )
+            .unwrap();
+
+        if !rows.is_empty() {
+            let row = rows.get(0);
+            return Err(conflict(row.get(0), row.get(1), row.get(2)));
+        }
+
+        // addresses deleted at or after their latest version are no longer output,
+        // in the same fashion as the historical versions negated by pre_conflate
+        let (ids, versions): (Vec<i64>, Vec<i64>) = deletes.into_iter().unzip();
+
+        let rows = conn
+            .query(
+                "
+            UPDATE address a
+                SET
+                    output = false,
+                    id = ABS(a.id) * -1
+                FROM
+                    unnest($1::BIGINT[], $2::BIGINT[]) AS d(id, version)
+                WHERE
+                    ABS(a.id) = d.id
+                    AND d.version >= (
+                        SELECT
+                            MAX(m.version)
+                        FROM
+                            address m
+                        WHERE
+                            ABS(m.id) = d.id
+                    )
+                RETURNING
+                    d.id,
+                    d.version
+        ",
+                &[&ids, &versions],
+            )
+            .unwrap();
+
+        let mut deleted: HashMap<i64, i64> = HashMap::new();
+        for row in rows.iter() {
+            deleted.insert(row.get(0), row.get(1));
+        }
+
+        let scope = match scope_polygon {
+            Some(scope_polygon) => {
+                let polygon = pg::Polygon::new("conflate_scope");
+                polygon.create(&conn);
+                polygon.input(
+                    &conn,
+                    PolyStream::new(GeoStream::new(Some(scope_polygon)), None),
+                );
+                polygon.index(&conn);
+
+                true
+            }
+            None => false,
+        };
+
+        Ok(Postgres {
+            conn: conn,
+            scope: scope,
+            deleted: deleted,
+        })
+    }
+}
+
+impl Store for Postgres {
+    fn nearby(&self, addr: &Address, radius: f64) -> Vec<Address> {
+        let rows = self
+            .conn
+            .query(
+                "
+            SELECT
+                json_build_object(
+                    'id', p.id,
+                    'number', p.number,
+                    'version', p.version,
+                    'names', p.names,
+                    'output', p.output,
+                    'source', p.source,
+                    'props', p.props,
+                    'geom', ST_AsGeoJSON(p.geom)::TEXT
+                )
+            FROM
+                address p
+            WHERE
+                p.number IN (
+                    SELECT
+                        k.number
+                    FROM
+                        address_number_keys k
+                    WHERE
+                        k.key = $1
+                )
+                AND ST_DWithin(ST_SetSRID(ST_Point($2, $3), 4326), p.geom, $4)
+                AND ST_DWithin(ST_SetSRID(ST_Point($2, $3), 4326)::GEOGRAPHY, ST_Force2D(p.geom)::GEOGRAPHY, $5)
+            ORDER BY
+                p.id;
+        ",
+                &[
+                    &addr.house_number().key,
+                    &addr.geom[0],
+                    &addr.geom[1],
+                    &distance::degrees(radius, addr.geom[1]),
+                    &radius,
+                ],
+            )
+            .unwrap();
+
+        let number = addr.house_number();
+        let mut persistents: Vec<Address> = Vec::with_capacity(rows.len());
+
+        for row in rows.iter() {
+            let paddr: serde_json::Value = row.get(0);
+            let paddr = Address::from_value(paddr).unwrap();
+
+            if number.same(&paddr.house_number()) {
+                persistents.push(paddr);
+            }
+        }
+
+        persistents
+    }
+
+    ///
+    /// A single set based query for the whole batch, rather than a query per new address
+    ///
+    fn nearby_batch(&self, addrs: &[Address], radius: f64) -> Vec<Vec<Address>> {
+        let numbers: Vec<HouseNumber> = addrs.iter().map(|addr| addr.house_number()).collect();
+        let keys: Vec<String> = numbers.iter().map(|number| number.key.clone()).collect();
+        let lons: Vec<f64> = addrs.iter().map(|addr| addr.geom[0]).collect();
+        let lats: Vec<f64> = addrs.iter().map(|addr| addr.geom[1]).collect();
+        let degrees: Vec<f64> = addrs
+            .iter()
+            .map(|addr| distance::degrees(radius, addr.geom[1]))
+            .collect();
+
+        let rows = self
+            .conn
+            .query(
+                "
+            SELECT
+                n.i,
+                json_build_object(
+                    'id', p.id,
+                    'number', p.number,
+                    'version', p.version,
+                    'names', p.names,
+                    'output', p.output,
+                    'source', p.source,
+                    'props', p.props,
+                    'geom', ST_AsGeoJSON(p.geom)::TEXT
+                )
+            FROM
+                unnest($1::TEXT[], $2::FLOAT8[], $3::FLOAT8[], $4::FLOAT8[])
+                    WITH ORDINALITY AS n(key, lon, lat, degrees, i)
+                JOIN address_number_keys k
+                    ON k.key = n.key
+                JOIN address p
+                    ON p.number = k.number
+                    AND ST_DWithin(ST_SetSRID(ST_Point(n.lon, n.lat), 4326), p.geom, n.degrees)
+                    AND ST_DWithin(ST_SetSRID(ST_Point(n.lon, n.lat), 4326)::GEOGRAPHY, ST_Force2D(p.geom)::GEOGRAPHY, $5)
+            ORDER BY
+                n.i,
+                p.id;
+        ",
+                &[&keys, &lons, &lats, &degrees, &radius],
+            )
+            .unwrap();
+
+        let mut persistents: Vec<Vec<Address>> = addrs.iter().map(|_| Vec::new()).collect();
+
+        for row in rows.iter() {
+            // ordinality is 1 based
+            let i = row.get::<_, i64>(0) as usize - 1;
+            let paddr: serde_json::Value = row.get(1);
+            let paddr = Address::from_value(paddr).unwrap();
+
+            if numbers[i].same(&paddr.house_number()) {
+                persistents[i].push(paddr);
+            }
+        }
+
+        persistents
+    }
+
+    fn modify(
+        &mut self,
+        paddr: &Address,
+        new_props: serde_json::Map<String, serde_json::Value>,
+        new_source: &str,
+    ) {
+        self.conn
+            .execute(
+                "
+            INSERT INTO modified (
+                id,
+                version,
+                names,
+                number,
+                source,
+                output,
+                props,
+                new_props,
+                new_source,
+                geom
+            ) VALUES (
+                $1,
+                $2,
+                $3,
+                $4,
+                $5,
+                $6,
+                $7,
+                $8,
+                $9,
+                ST_SetSRID(ST_MakePoint($10, $11), 4326)
+            )
+        ",
+                &[
+                    &paddr.id,
+                    &paddr.version,
+                    &serde_json::to_value(&paddr.names.names).unwrap(),
+                    &paddr.number,
+                    &paddr.source,
+                    &paddr.output,
+                    &serde_json::Value::from(paddr.props.clone()),
+                    &serde_json::Value::from(new_props),
+                    &new_source,
+                    &paddr.geom[0],
+                    &paddr.geom[1],
+                ],
+            )
+            .unwrap();
+    }
+
+    fn relocate(&mut self, id: i64, geom: &[f64], accuracy: &Option<serde_json::Value>) {
+        self.conn
+            .execute(
+                "
+            UPDATE modified
+                SET
+                    geom = ST_SetSRID(ST_MakePoint($2, $3), 4326),
+                    props = CASE
+                        WHEN $4::JSONB IS NULL THEN props
+                        ELSE props || JSONB_Build_Object('accuracy', $4::JSONB)
+                    END
+                WHERE
+                    id = $1
+        ",
+                &[&id, &geom[0], &geom[1], accuracy],
+            )
+            .unwrap();
+    }
+
+    fn scoped(&self, source: &Option<String>) -> Vec<Address> {
+        let polygon = if self.scope {
+            "
+                AND EXISTS (
+                    SELECT
+                        1
+                    FROM
+                        conflate_scope s
+                    WHERE
+                        ST_Intersects(s.geom, p.geom)
+                )
+        "
+        } else {
+            ""
+        };
+
+        let rows = self
+            .conn
+            .query(
+                format!(
+                    "
+            SELECT
+                json_build_object(
+                    'id', p.id,
+                    'number', p.number,
+                    'version', p.version,
+                    'names', p.names,
+                    'output', p.output,
+                    'source', p.source,
+                    'props', p.props,
+                    'geom', ST_AsGeoJSON(p.geom)::TEXT
+                )
+            FROM
+                address p
+            WHERE
+                p.output
+                AND ($1::TEXT IS NULL OR p.source = $1)
+                {polygon}
+            ORDER BY
+                p.id
+        ",
+                    polygon = polygon
+                )
+                .as_str(),
+                &[source],
+            )
+            .unwrap();
+
+        let mut paddrs: Vec<Address> = Vec::with_capacity(rows.len());
+
+        for row in rows.iter() {
+            let paddr: serde_json::Value = row.get(0);
+            paddrs.push(Address::from_value(paddr).unwrap());
+        }
+
+        paddrs
+    }
+
+    fn deleted(&self) -> &HashMap<i64, i64> {
+        &self.deleted
+    }
+
+    fn modifieds(
+        self: Box<Self>,
+    ) -> Result<Box<dyn Iterator<Item = Result<serde_json::Value, String>>>, String> {
+        let cursor: pg::Cursor = pg::Cursor::new(
+            self.conn,
+            String::from(
+                "
+            SELECT
+                json_build_object(
+                    'id', id,
+                    'type', 'Feature',
+                    'action', 'modify',
+                    'version', version,
+                    'properties', JSONB_AGG(props ORDER BY seq),
+                    'source', (ARRAY_AGG(source ORDER BY seq))[1],
+                    'new_props', JSONB_AGG(new_props ORDER BY seq),
+                    'new_sources', JSONB_AGG(new_source ORDER BY seq),
+                    'geometry', ST_AsGeoJSON(geom)::JSON
+                )
+            FROM
+                modified
+            GROUP BY
+                id,
+                version,
+                geom
+            ORDER BY
+                id
+        ",
+            ),
+        )?;
+
+        Ok(Box::new(cursor))
+    }
+}
+
+///
+/// Persistent address coordinates as stored in the Memory RTrees
+///
+struct Indexed {
+    coord: [f64; 2],
+    index: usize,
+}
+
+impl rstar::RTreeObject for Indexed {
+    type Envelope = rstar::AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        rstar::AABB::from_point(self.coord)
+    }
+}
+
+impl rstar::PointDistance for Indexed {
+    fn distance_2(&self, point: &[f64; 2]) -> f64 {
+        let x = self.coord[0] - point[0];
+        let y = self.coord[1] - point[1];
+
+        x * x + y * y
+    }
+}
+
+///
+/// A single modification of a persistent address, equivalent to a row of the modified table
+///
+struct Modification {
+    id: i64,
+    version: i64,
+    source: String,
+    props: serde_json::Map<String, serde_json::Value>,
+    new_props: serde_json::Map<String, serde_json::Value>,
+    new_source: String,
+    geom: Vec<f64>,
+}
+
+///
+/// Pure rust alternative to the Postgres store that does not require PostGIS
+///
+/// Persistent addresses are held in memory, with an RTree of their coordinates
+/// for each address number
+///
+pub struct Memory {
+    addresses: Vec<Address>,
+    trees: HashMap<String, rstar::RTree<Indexed>>,
+    scope: Option<Vec<geo::MultiPolygon<f64>>>,
+    deleted: HashMap<i64, i64>,
+    modified: Vec<Modification>,
+}
+
+impl Memory {
+    pub fn new(
+        addrs: impl IntoIterator<Item = Address>,
+        deletes: HashMap<i64, i64>,
+        scope_polygon: Option<String>,
+    ) -> Result<Self, String> {
+        let mut addresses: Vec<Address> = addrs.into_iter().collect();
+
+        let mut versions: HashMap<i64, i64> = HashMap::new();
+        let mut counts: HashMap<(i64, i64), i64> = HashMap::new();
+        for addr in addresses.iter() {
+            if let Some(id) = addr.id {
+                let version = versions.entry(id).or_insert(addr.version);
+                if addr.version > *version {
+                    *version = addr.version;
+                }
+
+                *counts.entry((id, addr.version)).or_insert(0) += 1;
+            }
+        }
+
+        let mut conflicts: Vec<(&(i64, i64), &i64)> =
+            counts.iter().filter(|(_, count)| **count > 1).collect();
+        conflicts.sort();
+        if let Some(((id, version), count)) = conflicts.first() {
+            return Err(conflict(*id, *version, **count));
+        }
+
+        // addresses deleted at or after their latest version are no longer output
+        let deleted: HashMap<i64, i64> = deletes
+            .into_iter()
+            .filter(|(id, version)| match versions.get(id) {
+                Some(latest) => version >= latest,
+                None => false,
+            })
+            .collect();
+
+        // as with pg::address::pre_conflate, only the latest version of an address
+        // is output, older versions & deleted addresses are kept with a negated id
+        for addr in addresses.iter_mut() {
+            if let Some(id) = addr.id {
+                if versions[&id] != addr.version || deleted.contains_key(&id) {
+                    addr.output = false;
+                    addr.id = Some(id * -1);
+                }
+            }
+        }
+
+        let mut points: HashMap<String, Vec<Indexed>> = HashMap::new();
+        for (index, addr) in addresses.iter().enumerate() {
+            points
+                .entry(addr.house_number().key)
+                .or_insert_with(Vec::new)
+                .push(Indexed {
+                    coord: [addr.geom[0], addr.geom[1]],
+                    index: index,
+                });
+        }
+
+        let trees = points
+            .into_iter()
+            .map(|(number, points)| (number, rstar::RTree::bulk_load(points)))
+            .collect();
+
+        let scope = match scope_polygon {
+            Some(scope_polygon) => Some(
+                GeoStream::new(Some(scope_polygon))
+                    .map(|feat| {
+                        let feat = match feat {
+                            geojson::GeoJson::Feature(feat) => feat,
+                            _ => panic!("Scope polygons must be (Multi)Polygon Features"),
+                        };
+
+                        let geom: geo::Geometry<f64> =
+                            feat.geometry.unwrap().value.try_into().unwrap();
+
+                        match geom {
+                            geo::Geometry::Polygon(poly) => geo::MultiPolygon(vec![poly]),
+                            geo::Geometry::MultiPolygon(mpoly) => mpoly,
+                            _ => panic!("Scope polygons must be (Multi)Polygon Features"),
+                        }
+                    })
+                    .collect(),
+            ),
+            None => None,
+        };
+
+        Ok(Memory {
+            addresses: addresses,
+            trees: trees,
+            scope: scope,
+            deleted: deleted,
+            modified: Vec::new(),
+        })
+    }
+
+    ///
+    /// Number of persistent addresses loaded
+    ///
+    pub fn size(&self) -> usize {
+        self.addresses.len()
+    }
+}
+
+impl Store for Memory {
+    fn nearby(&self, addr: &Address, radius: f64) -> Vec<Address> {
+        use rstar::PointDistance;
+
+        let number = addr.house_number();
+
+        let tree = match self.trees.get(&number.key) {
+            Some(tree) => tree,
+            None => return Vec::new(),
+        };
+
+        let coord = [addr.geom[0], addr.geom[1]];
+
+        // Degrees are only used to narrow down candidates, the final check is in metres
+        let degrees = distance::degrees(radius, coord[1]);
+        let degrees_2 = degrees * degrees;
+
+        let indexes: Vec<usize> = tree
+            .nearest_neighbor_iter(&coord)
+            .take_while(|point| point.distance_2(&coord) <= degrees_2)
+            .filter(|point| distance::metres(point.coord, coord) <= radius)
+            .map(|point| point.index)
+            .collect();
+
+        let mut persistents: Vec<Address> = indexes
+            .into_iter()
+            .map(|index| &self.addresses[index])
+            .filter(|paddr| number.same(&paddr.house_number()))
+            .cloned()
+            .collect();
+
+        // candidates are ordered by id, as they are by the Postgres store
+        persistents.sort_by_key(|paddr| paddr.id);
+
+        persistents
+    }
+
+    fn modify(
+        &mut self,
+        paddr: &Address,
+        new_props: serde_json::Map<String, serde_json::Value>,
+        new_source: &str,
+    ) {
+        self.modified.push(Modification {
+            id: paddr.id.unwrap(),
+            version: paddr.version,
+            source: paddr.source.clone(),
+            props: paddr.props.clone(),
+            new_props: new_props,
+            new_source: String::from(new_source),
+            geom: paddr.geom.clone(),
+        });
+    }
+
+    fn relocate(&mut self, id: i64, geom: &[f64], accuracy: &Option<serde_json::Value>) {
+        for modification in self.modified.iter_mut().filter(|m| m.id == id) {
+            modification.geom = geom.to_vec();
+
+            if let Some(accuracy) = accuracy {
+                modification
+                    .props
+                    .insert(String::from("accuracy"), accuracy.clone());
+            }
+        }
+    }
+
+    fn scoped(&self, source: &Option<String>) -> Vec<Address> {
+        let mut paddrs: Vec<Address> = self
+            .addresses
+            .iter()
+            .filter(|paddr| paddr.output)
+            .filter(|paddr| match source {
+                Some(source) => &paddr.source == source,
+                None => true,
+            })
+            .filter(|paddr| match self.scope {
+                Some(ref scope) => {
+                    let point = geo::Point::new(paddr.geom[0], paddr.geom[1]);
+
+                    // As with ST_Intersects, addresses on the boundary of a scope polygon are in scope
+                    scope.iter().any(|polygon| {
+                        polygon.contains(&point)
+                            || polygon
+                                .0
+                                .iter()
+                                .any(|poly| point.euclidean_distance(poly) == 0.0)
+                    })
+                }
+                None => true,
+            })
+            .cloned()
+            .collect();
+
+        paddrs.sort_by_key(|paddr| paddr.id);
+
+        paddrs
+    }
+
+    fn deleted(&self) -> &HashMap<i64, i64> {
+        &self.deleted
+    }
+
+    fn modifieds(
+        self: Box<Self>,
+    ) -> Result<Box<dyn Iterator<Item = Result<serde_json::Value, String>>>, String> {
+        let mut ids: Vec<i64> = Vec::new();
+        let mut grouped: HashMap<i64, Vec<Modification>> = HashMap::new();
+
+        for modification in self.modified {
+            let group = grouped.entry(modification.id).or_insert_with(|| {
+                ids.push(modification.id);
+                Vec::new()
+            });
+
+            group.push(modification);
+        }
+
+        ids.sort();
+
+        Ok(Box::new(ids.into_iter().map(move |id| {
+            let group = grouped.remove(&id).unwrap();
+            let first = &group[0];
+
+            Ok(serde_json::json!({
+                "id": first.id,
+                "type": "Feature",
+                "action": "modify",
+                "version": first.version,
+                "properties": group.iter().map(|m| serde_json::Value::from(m.props.clone())).collect::<Vec<_>>(),
+                "source": first.source,
+                "new_props": group.iter().map(|m| serde_json::Value::from(m.new_props.clone())).collect::<Vec<_>>(),
+                "new_sources": group.iter().map(|m| m.new_source.clone()).collect::<Vec<_>>(),
+                "geometry": {
+                    "type": "Point",
+                    "coordinates": [first.geom[0], first.geom[1]]
+                }
+            }))
+        })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Tokens;
+
+    fn address(id: i64, version: i64, number: &str, geom: [f64; 2]) -> Address {
+        let context = Context::new(
+            String::from("us"),
+            None,
+            Tokens::generate(vec![String::from("en")]),
+        );
+
+        let feat: geojson::GeoJson = serde_json::json!({
+                "id": id,
+                "version": version,
+                "type": "Feature",
+                "properties": {
+                    "number": number,
+                    "source": "county",
+                    "street": [{ "display": "Main St", "priority": 0 }]
+                },
+                "geometry": {
+                    "type": "Point",
+                "coordinates": geom
+            }
+        })
+        .to_string()
+        .parse()
+        .unwrap();
+
+        Address::new(feat, &context).unwrap()
+    }
+
+    #[test]
+    fn test_memory_nearby() {
+        let store = Memory::new(
+            vec![
+                address(1, 1, "10", [-77.0, 38.0]),
+                address(2, 1, "10", [-77.0, 38.005]),
+                address(3, 1, "12", [-77.0, 38.0]),
+                address(4, 1, "10", [-77.0, 38.05]),
+            ],
+            HashMap::new(),
+            None,
+        )
+        .unwrap();
+
+        assert_eq!(store.size(), 4);
+
+        let ids: Vec<Option<i64>> = store
+            .nearby(&address(5, 1, "10", [-77.0, 38.0001]), 1000.0)
+            .into_iter()
+            .map(|paddr| paddr.id)
+            .collect();
+
+        assert_eq!(ids, vec![Some(1), Some(2)]);
+
+        assert!(store
+            .nearby(&address(5, 1, "14", [-77.0, 38.0]), 1000.0)
+            .is_empty());
+    }
+
+    #[test]
+    fn test_memory_versions() {
+        let store = Memory::new(
+            vec![
+                address(1, 1, "10", [-77.0, 38.0]),
+                address(1, 2, "10", [-77.0, 38.0]),
+            ],
+            HashMap::new(),
+            None,
+        )
+        .unwrap();
+
+        let nearby = store.nearby(&address(5, 1, "10", [-77.0, 38.0]), 1000.0);
+
+        assert_eq!(nearby.len(), 2);
+        assert_eq!((nearby[0].id, nearby[0].output), (Some(-1), false));
+        assert_eq!((nearby[1].id, nearby[1].output), (Some(1), true));
+
+        let scoped: Vec<Option<i64>> = store
+            .scoped(&Some(String::from("county")))
+            .into_iter()
+            .map(|paddr| paddr.id)
+            .collect();
+
+        assert_eq!(scoped, vec![Some(1)]);
+    }
+
+    #[test]
+    fn test_memory_scope() {
+        let store = Memory::new(
+            vec![
+                address(1, 1, "10", [-77.0008, 38.8913]),
+                address(2, 1, "10", [-77.00080543756485, 38.89148752230519]),
+                address(3, 1, "10", [-77.00080543756485, 38.89168752230519]),
+            ],
+            HashMap::new(),
+            Some(format!(
+                "{}/../test/fixtures/conflate-scope-polygon.geojson",
+                env!("CARGO_MANIFEST_DIR")
+            )),
+        )
+        .unwrap();
+
+        // 2 is on the boundary of the scope polygon, 3 is outside of it
+        let scoped: Vec<Option<i64>> = store
+            .scoped(&None)
+            .into_iter()
+            .map(|paddr| paddr.id)
+            .collect();
+
+        assert_eq!(scoped, vec![Some(1), Some(2)]);
+    }
+
+    #[test]
+    fn test_memory_conflicts() {
+        let err = Memory::new(
+            vec![
+                address(1, 2, "10", [-77.0, 38.0]),
+                address(1, 2, "10", [-77.0, 38.001]),
+            ],
+            HashMap::new(),
+            None,
+        )
+        .err()
+        .unwrap();
+
+        assert_eq!(err, "Persistent address 1 has 2 conflicting features with version 2, ids & versions must be unique");
+    }
+
+    #[test]
+    fn test_memory_deleted() {
+        let mut deletes = HashMap::new();
+        deletes.insert(1, 3);
+        deletes.insert(2, 1);
+        deletes.insert(4, 1);
+
+        let store = Memory::new(
+            vec![
+                address(1, 2, "10", [-77.0, 38.0]),
+                address(2, 2, "10", [-77.0, 38.0]),
+                address(3, 1, "10", [-77.0, 38.0]),
+            ],
+            deletes,
+            None,
+        )
+        .unwrap();
+
+        // only deletes at or after the latest version apply
+        let mut expected = HashMap::new();
+        expected.insert(1, 3);
+        assert_eq!(store.deleted(), &expected);
+
+        let nearby: Vec<(Option<i64>, bool)> = store
+            .nearby(&address(5, 1, "10", [-77.0, 38.0]), 1000.0)
+            .into_iter()
+            .map(|paddr| (paddr.id, paddr.output))
+            .collect();
+
+        assert_eq!(
+            nearby,
+            vec![(Some(-1), false), (Some(2), true), (Some(3), true)]
+        );
+    }
+
+    #[test]
+    fn test_memory_modifieds() {
+        let mut store = Memory::new(
+            vec![
+                address(2, 1, "10", [-77.0, 38.0]),
+                address(1, 1, "12", [-77.0, 38.0]),
+            ],
+            HashMap::new(),
+            None,
+        )
+        .unwrap();
+
+        let paddrs = store.nearby(&address(5, 1, "10", [-77.0, 38.0]), 1000.0);
+        store.modify(&paddrs[0], serde_json::Map::new(), "osm");
+        store.modify(&paddrs[0], serde_json::Map::new(), "feed");
+
+        let paddrs = store.nearby(&address(5, 1, "12", [-77.0, 38.0]), 1000.0);
+        store.modify(&paddrs[0], serde_json::Map::new(), "osm");
+
+        store.relocate(2, &[-77.1, 38.1], &Some(serde_json::json!("rooftop")));
+
+        let modifieds: Vec<serde_json::Value> = Box::new(store)
+            .modifieds()
+            .unwrap()
+            .map(|modified| modified.unwrap())
+            .collect();
+
+        assert_eq!(modifieds.len(), 2);
+        assert_eq!(modifieds[0]["id"], serde_json::json!(1));
+        assert_eq!(modifieds[1]["id"], serde_json::json!(2));
+        assert_eq!(
+            modifieds[1]["new_sources"],
+            serde_json::json!(["osm", "feed"])
+        );
+        assert_eq!(
+            modifieds[1]["properties"][1]["accuracy"],
+            serde_json::json!("rooftop")
+        );
+        assert_eq!(
+            modifieds[1]["geometry"],
+            serde_json::json!({ "type": "Point", "coordinates": [-77.1, 38.1] })
+        );
+    }
+}
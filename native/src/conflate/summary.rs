@@ -0,0 +1,218 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+///
+/// Default number of example features kept for each action
+///
+pub const SAMPLES: usize = 5;
+
+///
+/// Why a persistent address is modified
+///
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum Reason {
+    /// A new address added names the persistent address did not have
+    Name,
+    /// The persistent address was moved to the geometry of a new address
+    Moved,
+    /// A property with a merge strategy changed
+    Property,
+}
+
+impl Reason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Reason::Name => "name",
+            Reason::Moved => "moved",
+            Reason::Property => "property",
+        }
+    }
+}
+
+///
+/// Counts & examples of a single hecate action
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct Actions {
+    pub total: usize,
+    pub sources: BTreeMap<String, usize>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    pub reasons: BTreeMap<String, usize>,
+    pub samples: Vec<serde_json::Value>,
+}
+
+impl Actions {
+    fn add(&mut self, sources: &[&str], line: &str, samples: usize) {
+        self.total += 1;
+
+        for source in sources {
+            *self.sources.entry(source.to_string()).or_insert(0) += 1;
+        }
+
+        if self.samples.len() < samples {
+            if let Ok(feat) = serde_json::from_str(line) {
+                self.samples.push(feat);
+            }
+        }
+    }
+}
+
+///
+/// Dry run report of what a conflate diff will do when applied to Hecate
+///
+/// Creates are counted by the source of the new address, modifies by the distinct
+/// sources of the new addresses that modified them, restores by the source of the
+/// new address & deletes by the source of the persistent address
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq, Default)]
+pub struct Summary {
+    pub create: Actions,
+    pub modify: Actions,
+    pub delete: Actions,
+    pub restore: Actions,
+
+    /// Ids of persistent addresses matched by more than one new address
+    pub multiple: Vec<i64>,
+
+    #[serde(skip)]
+    samples: usize,
+    #[serde(skip)]
+    matches: HashMap<i64, usize>,
+    #[serde(skip)]
+    reasons: HashMap<i64, BTreeSet<Reason>>,
+}
+
+impl Summary {
+    pub fn new() -> Self {
+        Summary {
+            samples: SAMPLES,
+            ..Default::default()
+        }
+    }
+
+    ///
+    /// Builder style setter for the number of example features kept for each action
+    ///
+    pub fn set_samples(mut self, samples: usize) -> Self {
+        self.samples = samples;
+        self
+    }
+
+    ///
+    /// Record that a persistent address was matched by a new address
+    ///
+    pub fn matched(&mut self, id: i64) {
+        *self.matches.entry(id).or_insert(0) += 1;
+    }
+
+    ///
+    /// Record a reason a persistent address is modified
+    ///
+    pub fn reason(&mut self, id: i64, reason: Reason) {
+        self.reasons
+            .entry(id)
+            .or_insert_with(BTreeSet::new)
+            .insert(reason);
+    }
+
+    pub fn create(&mut self, source: &str, line: &str) {
+        self.create.add(&[source], line, self.samples);
+    }
+
+    pub fn modify(&mut self, id: i64, sources: &[&str], line: &str) {
+        let mut sources = sources.to_vec();
+        sources.sort();
+        sources.dedup();
+
+        self.modify.add(&sources, line, self.samples);
+
+        if let Some(reasons) = self.reasons.get(&id) {
+            for reason in reasons {
+                *self
+                    .modify
+                    .reasons
+                    .entry(String::from(reason.as_str()))
+                    .or_insert(0) += 1;
+            }
+        }
+    }
+
+    pub fn delete(&mut self, source: &str, line: &str) {
+        self.delete.add(&[source], line, self.samples);
+    }
+
+    pub fn restore(&mut self, source: &str, line: &str) {
+        self.restore.add(&[source], line, self.samples);
+    }
+
+    ///
+    /// Finish the report, collecting the ids of persistent addresses matched multiple times
+    ///
+    pub fn finish(mut self) -> Self {
+        let mut multiple: Vec<i64> = self
+            .matches
+            .iter()
+            .filter(|(_, count)| **count > 1)
+            .map(|(id, _)| *id)
+            .collect();
+
+        multiple.sort();
+
+        self.multiple = multiple;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        let mut summary = Summary::new().set_samples(1);
+
+        summary.create("osm", r#"{ "id": 1 }"#);
+        summary.create("osm", r#"{ "id": 2 }"#);
+        summary.create("", r#"{ "id": 3 }"#);
+
+        summary.matched(10);
+        summary.matched(10);
+        summary.matched(11);
+        summary.reason(10, Reason::Name);
+        summary.reason(10, Reason::Moved);
+        summary.reason(10, Reason::Name);
+        summary.modify(10, &["osm", "county", "osm"], r#"{ "id": 10 }"#);
+
+        summary.delete("county", r#"{ "id": 12 }"#);
+        summary.restore("osm", r#"{ "id": 13 }"#);
+
+        let summary = summary.finish();
+
+        assert_eq!(
+            serde_json::to_value(&summary).unwrap(),
+            serde_json::json!({
+                "create": {
+                    "total": 3,
+                    "sources": { "": 1, "osm": 2 },
+                    "samples": [{ "id": 1 }]
+                },
+                "modify": {
+                    "total": 1,
+                    "sources": { "county": 1, "osm": 1 },
+                    "reasons": { "moved": 1, "name": 1 },
+                    "samples": [{ "id": 10 }]
+                },
+                "delete": {
+                    "total": 1,
+                    "sources": { "county": 1 },
+                    "samples": [{ "id": 12 }]
+                },
+                "restore": {
+                    "total": 1,
+                    "sources": { "osm": 1 },
+                    "samples": [{ "id": 13 }]
+                },
+                "multiple": [10]
+            })
+        );
+    }
+}
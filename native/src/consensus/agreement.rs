@@ -1,10 +1,19 @@
 use kodama::{linkage, Method};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Results {
     agreement_count: u32,
     hit_count: u32,
+    /// Query points with nearby addresses of the same number, none of which the linker
+    /// matched by name
+    name_disagreement_count: u32,
+    /// Ratio of query points the source matched
+    coverage: f64,
+    /// Distance (metres) of matched addresses from the consensus centroid
+    distance: Option<Distance>,
+    #[serde(skip)]
+    distances: Vec<f64>,
 }
 
 impl Results {
@@ -12,15 +21,69 @@ impl Results {
         Results {
             agreement_count: 0,
             hit_count: 0,
+            name_disagreement_count: 0,
+            coverage: 0.0,
+            distance: None,
+            distances: Vec::new(),
         }
     }
 }
 
+///
+/// Distribution of the distances of a source from the consensus centroids, rounded to
+/// the centimetre
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Distance {
+    mean: f64,
+    median: f64,
+    p90: f64,
+}
+
+impl Distance {
+    pub fn new(distances: &[f64]) -> Option<Self> {
+        if distances.is_empty() {
+            return None;
+        }
+
+        let mut sorted = distances.to_vec();
+        sorted.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        let mean = sorted.iter().sum::<f64>() / sorted.len() as f64;
+
+        Some(Distance {
+            mean: centimetres(mean),
+            median: centimetres(percentile(&sorted, 0.5)),
+            p90: centimetres(percentile(&sorted, 0.9)),
+        })
+    }
+}
+
+///
+/// Agreement metrics of the query points within a single boundary polygon
+///
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub struct Bucket {
+    results: HashMap<String, Results>,
+    sample_count: u32,
+}
+
+///
+/// The outcome of a single query point for a source that matched it
+///
+struct Hit<'a> {
+    source: &'a String,
+    agreed: bool,
+    distance: Option<f64>,
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct Agreement {
     results: HashMap<String, Results>,
     threshold: u32,
     sample_count: u32,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty", default)]
+    buckets: BTreeMap<String, Bucket>,
 }
 
 impl Agreement {
@@ -30,6 +93,7 @@ impl Agreement {
             results,
             threshold,
             sample_count: 0,
+            buckets: BTreeMap::new(),
         }
     }
 
@@ -37,27 +101,78 @@ impl Agreement {
     // Take in set of matching address points for each source, and perform hierarchical clustering
     // to determine which points "agree" with eachother
     //
-    pub fn process_points(&mut self, source_map: &HashMap<String, Option<(f64, f64)>>) {
+    // The metrics are recorded overall & for each of the named boundary buckets the query
+    // point is within
+    //
+    pub fn process_points(
+        &mut self,
+        source_map: &HashMap<String, Option<(f64, f64)>>,
+        buckets: &[String],
+    ) {
+        let hits = self.hits(source_map);
+
         self.sample_count += 1;
+        record(&mut self.results, source_map, &hits);
+
+        for bucket in buckets {
+            let bucket = self.buckets.entry(bucket.clone()).or_default();
+
+            bucket.sample_count += 1;
+            record(&mut bucket.results, source_map, &hits);
+        }
+    }
+
+    ///
+    /// Record that the linker found addresses of a source with the query point's number
+    /// nearby, but none of them with a matching name
+    ///
+    pub fn name_disagreement(&mut self, source: &str, buckets: &[String]) {
+        self.results
+            .entry(String::from(source))
+            .or_insert_with(Results::new)
+            .name_disagreement_count += 1;
+
+        for bucket in buckets {
+            self.buckets
+                .entry(bucket.clone())
+                .or_default()
+                .results
+                .entry(String::from(source))
+                .or_insert_with(Results::new)
+                .name_disagreement_count += 1;
+        }
+    }
+
+    ///
+    /// Finish the metrics, calculating the coverage & distance distribution of each source
+    ///
+    pub fn finish(mut self) -> Self {
+        summarize(&mut self.results, self.sample_count);
+
+        for bucket in self.buckets.values_mut() {
+            summarize(&mut bucket.results, bucket.sample_count);
+        }
 
-        let mut source_hit_count = 0;
+        self
+    }
+
+    fn hits<'a>(&self, source_map: &'a HashMap<String, Option<(f64, f64)>>) -> Vec<Hit<'a>> {
         let mut coordinates = vec![];
-        let mut labels = vec![];
+        let mut hits = vec![];
         for (source, coord) in source_map.iter() {
             if let Some(point) = coord {
-                source_hit_count += 1;
-                labels.push(source);
-                coordinates.push(point);
-                self.results
-                    .entry(String::from(source))
-                    .or_insert(Results::new())
-                    .hit_count += 1;
+                coordinates.push(*point);
+                hits.push(Hit {
+                    source: source,
+                    agreed: false,
+                    distance: None,
+                });
             }
         }
 
         // Return early if we don't have enough hits to determine agreement
-        if source_hit_count < 3 {
-            return;
+        if coordinates.len() < 3 {
+            return hits;
         }
 
         // Build a condensed matrix by computing the dissimilarity between all
@@ -65,7 +180,7 @@ impl Agreement {
         let mut condensed = vec![];
         for row in 0..coordinates.len() - 1 {
             for col in row + 1..coordinates.len() {
-                condensed.push(haversine(*coordinates[row], *coordinates[col]));
+                condensed.push(haversine(coordinates[row], coordinates[col]));
             }
         }
 
@@ -83,20 +198,92 @@ impl Agreement {
             }
         }
 
-        // Re-associate indexes with source values, and update the agreement counts for each
-        let modal_cluster: Vec<&String> = modal_cluster
+        // Re-associate indexes with sources, and mark the sources that agree
+        let modal_cluster: Vec<usize> = modal_cluster
             .into_iter()
             .filter(|&x| x < dend.observations())
-            .map(|x| labels[x])
             .collect();
-        for source in modal_cluster {
-            self.results
-                .entry(String::from(source))
-                .and_modify(|e| e.agreement_count += 1);
+
+        if modal_cluster.is_empty() {
+            return hits;
+        }
+
+        for &x in &modal_cluster {
+            hits[x].agreed = true;
+        }
+
+        // The consensus is the centroid of the agreeing points, each source that matched
+        // is measured against it, including those that disagree
+        let centroid = (
+            modal_cluster.iter().map(|&x| coordinates[x].0).sum::<f64>()
+                / modal_cluster.len() as f64,
+            modal_cluster.iter().map(|&x| coordinates[x].1).sum::<f64>()
+                / modal_cluster.len() as f64,
+        );
+
+        for (hit, coordinate) in hits.iter_mut().zip(coordinates) {
+            hit.distance = Some(haversine(coordinate, centroid));
+        }
+
+        hits
+    }
+}
+
+///
+/// Update the results of each source with the outcome of a single query point
+///
+fn record(
+    results: &mut HashMap<String, Results>,
+    source_map: &HashMap<String, Option<(f64, f64)>>,
+    hits: &[Hit],
+) {
+    // Sources without a match are still listed so their coverage is reported
+    for source in source_map.keys() {
+        results
+            .entry(String::from(source))
+            .or_insert_with(Results::new);
+    }
+
+    for hit in hits {
+        let result = results.get_mut(hit.source).unwrap();
+
+        result.hit_count += 1;
+
+        if hit.agreed {
+            result.agreement_count += 1;
+        }
+
+        if let Some(distance) = hit.distance {
+            result.distances.push(distance);
+        }
+    }
+}
+
+fn summarize(results: &mut HashMap<String, Results>, sample_count: u32) {
+    for result in results.values_mut() {
+        if sample_count > 0 {
+            result.coverage = result.hit_count as f64 / sample_count as f64;
         }
+
+        result.distance = Distance::new(&result.distances);
     }
 }
 
+///
+/// Linearly interpolated percentile (0 - 1) of a sorted, non-empty list
+///
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = p * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+fn centimetres(metres: f64) -> f64 {
+    (metres * 100.0).round() / 100.0
+}
+
 fn haversine((lon1, lat1): (f64, f64), (lon2, lat2): (f64, f64)) -> f64 {
     const EARTH_RADIUS: f64 = 6371.0; // kilometers
 
@@ -123,7 +310,7 @@ mod tests {
         source_map.insert(String::from("source2"), Some((-77.0013338, 38.8959407)));
         source_map.insert(String::from("source3"), Some((-77.0013311, 38.8955170)));
 
-        agreement.process_points(&source_map);
+        agreement.process_points(&source_map, &[]);
 
         source_map
             .entry(String::from("source1"))
@@ -135,7 +322,7 @@ mod tests {
             .entry(String::from("source3"))
             .and_modify(|e| *e = Some((-77.0038872, 38.8970513)));
 
-        agreement.process_points(&source_map);
+        agreement.process_points(&source_map, &[]);
 
         assert_eq!(agreement.results.get("source1").unwrap().agreement_count, 2);
         assert_eq!(agreement.results.get("source2").unwrap().agreement_count, 2);
@@ -151,7 +338,7 @@ mod tests {
         source_map.insert(String::from("source2"), Some((-76.9733476, 38.9163518)));
         source_map.insert(String::from("source3"), Some((-76.9731089, 38.9175434)));
 
-        agreement.process_points(&source_map);
+        agreement.process_points(&source_map, &[]);
 
         source_map
             .entry(String::from("source1"))
@@ -163,7 +350,7 @@ mod tests {
             .entry(String::from("source3"))
             .and_modify(|e| *e = Some((-76.9720950, 38.9308064)));
 
-        agreement.process_points(&source_map);
+        agreement.process_points(&source_map, &[]);
 
         assert_eq!(agreement.results.get("source1").unwrap().agreement_count, 0);
         assert_eq!(agreement.results.get("source2").unwrap().agreement_count, 0);
@@ -179,7 +366,7 @@ mod tests {
         source_map.insert(String::from("source2"), Some((-76.9733476, 38.9163518)));
         source_map.insert(String::from("source3"), None);
 
-        agreement.process_points(&source_map);
+        agreement.process_points(&source_map, &[]);
 
         source_map
             .entry(String::from("source1"))
@@ -191,7 +378,7 @@ mod tests {
             .entry(String::from("source3"))
             .and_modify(|e| *e = Some((-76.9720950, 38.9308064)));
 
-        agreement.process_points(&source_map);
+        agreement.process_points(&source_map, &[]);
 
         assert_eq!(agreement.results.get("source1").unwrap().agreement_count, 0);
         assert_eq!(agreement.results.get("source1").unwrap().hit_count, 1);
@@ -219,7 +406,7 @@ mod tests {
         );
         source_map.insert(String::from("Westborough"), Some((-71.6166667, 42.2694444)));
 
-        agreement.process_points(&source_map);
+        agreement.process_points(&source_map, &[]);
 
         assert_eq!(
             agreement.results.get("Fitchburg").unwrap().agreement_count,
@@ -262,4 +449,80 @@ mod tests {
             1
         );
     }
+
+    #[test]
+    fn test_agreement_distance() {
+        assert_eq!(Distance::new(&[]), None);
+        assert_eq!(
+            Distance::new(&[10.0, 1.0, 4.0, 3.0, 2.0]),
+            Some(Distance {
+                mean: 4.0,
+                median: 3.0,
+                p90: 7.6,
+            })
+        );
+    }
+
+    #[test]
+    fn test_agreement_metrics() {
+        let mut agreement = Agreement::new(25);
+        let dc = vec![String::from("dc")];
+
+        let mut source_map = HashMap::new();
+        source_map.insert(String::from("source1"), Some((-77.0, 38.9)));
+        source_map.insert(String::from("source2"), Some((-77.0, 38.9)));
+        source_map.insert(String::from("source3"), Some((-77.001, 38.9)));
+
+        agreement.process_points(&source_map, &dc);
+
+        source_map
+            .entry(String::from("source2"))
+            .and_modify(|e| *e = None);
+        source_map
+            .entry(String::from("source3"))
+            .and_modify(|e| *e = None);
+
+        agreement.name_disagreement("source2", &[]);
+        agreement.process_points(&source_map, &[]);
+
+        let agreement = agreement.finish();
+
+        let source1 = agreement.results.get("source1").unwrap();
+        assert_eq!(source1.agreement_count, 1);
+        assert_eq!(source1.hit_count, 2);
+        assert_eq!(source1.coverage, 1.0);
+        assert_eq!(
+            source1.distance,
+            Some(Distance {
+                mean: 0.0,
+                median: 0.0,
+                p90: 0.0,
+            })
+        );
+
+        let source2 = agreement.results.get("source2").unwrap();
+        assert_eq!(source2.hit_count, 1);
+        assert_eq!(source2.name_disagreement_count, 1);
+        assert_eq!(source2.coverage, 0.5);
+
+        let source3 = agreement.results.get("source3").unwrap();
+        assert_eq!(source3.agreement_count, 0);
+        assert_eq!(source3.coverage, 0.5);
+        assert_eq!(
+            source3.distance,
+            Some(Distance {
+                mean: 86.54,
+                median: 86.54,
+                p90: 86.54,
+            })
+        );
+
+        let dc = agreement.buckets.get("dc").unwrap();
+        assert_eq!(dc.sample_count, 1);
+        assert_eq!(dc.results.get("source2").unwrap().coverage, 1.0);
+        assert_eq!(
+            dc.results.get("source2").unwrap().name_disagreement_count,
+            0
+        );
+    }
 }
@@ -1,3 +1,4 @@
+use geo::algorithm::contains::Contains;
 use postgres::{Connection, TlsMode};
 use std::collections::HashMap;
 use std::convert::From;
@@ -8,14 +9,13 @@ use neon::prelude::*;
 
 use crate::{
     stream::{AddrStream, GeoStream},
-    util::linker,
+    util::{distance, linker, progress, task},
     Address,
 };
 
 use super::pg;
 use super::pg::{InputTable, Table};
 
-const SEARCH_RADIUS: f64 = 0.01;
 const WGS84: i32 = 4326;
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -27,6 +27,8 @@ struct ConsensusArgs {
     query_points: String,
     error_sources: Option<String>,
     error_query_points: Option<String>,
+    radius: Option<f64>,
+    buckets: Option<String>,
 }
 
 impl ConsensusArgs {
@@ -39,8 +41,17 @@ impl ConsensusArgs {
             query_points: String::from(""),
             error_sources: None,
             error_query_points: None,
+            radius: None,
+            buckets: None,
         }
     }
+
+    ///
+    /// Distance (metres) within which source addresses are compared to a query point
+    ///
+    pub fn radius(&self) -> f64 {
+        self.radius.unwrap_or(distance::SEARCH_RADIUS)
+    }
 }
 
 //
@@ -52,26 +63,38 @@ impl ConsensusArgs {
 // metrics, then returns those metrics as the final result after each query point is processed.
 //
 pub fn consensus(mut cx: FunctionContext) -> JsResult<JsValue> {
-    let args: ConsensusArgs = match cx.argument_opt(0) {
+    let (progress, callback) = progress::from_args(&mut cx, 0)?;
+
+    let args: ConsensusArgs = match progress::args(&mut cx, 0)? {
         None => ConsensusArgs::new(),
         Some(arg) => {
-            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
-                ConsensusArgs::new()
-            } else {
-                let arg_obj = cx.argument::<JsObject>(0)?;
-
-                let db = arg_obj.get(&mut cx, "db")?;
-                if db.is_a::<JsUndefined>() || db.is_a::<JsNull>() {
-                    let default = cx.string("consensus");
-                    arg_obj.set(&mut cx, "db", default)?;
-                }
+            let arg_obj = arg.downcast::<JsObject>().or_throw(&mut cx)?;
 
-                let arg_val = arg_obj.as_value(&mut cx);
-                neon_serde::from_value(&mut cx, arg_val)?
+            let db = arg_obj.get(&mut cx, "db")?;
+            if db.is_a::<JsUndefined>() || db.is_a::<JsNull>() {
+                let default = cx.string("consensus");
+                arg_obj.set(&mut cx, "db", default)?;
             }
+
+            let arg_val = arg_obj.as_value(&mut cx);
+            neon_serde::from_value(&mut cx, arg_val)?
         }
     };
 
+    task::run(&mut cx, 1, callback, move |token| {
+        run(args, progress, token)
+    })
+}
+
+///
+/// Import sources & query points, returning the agreement metrics of the sources
+///
+fn run(
+    args: ConsensusArgs,
+    progress: progress::Progress,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
+    let radius = args.radius();
     let sources = args.sources;
     let query_points = args.query_points;
 
@@ -132,26 +155,57 @@ pub fn consensus(mut cx: FunctionContext) -> JsResult<JsValue> {
         WHERE
             p.number = $1
             AND p.source = $4
-            AND ST_DWithin(ST_SetSRID(ST_Point($2, $3), {0}), p.geom, {1});
+            AND ST_DWithin(ST_SetSRID(ST_Point($2, $3), {0}), p.geom, $5)
+            AND ST_DWithin(ST_SetSRID(ST_Point($2, $3), {0})::GEOGRAPHY, ST_Force2D(p.geom)::GEOGRAPHY, $6);
     ",
-        WGS84, SEARCH_RADIUS
+        WGS84
     );
 
+    // named boundary polygons to report the metrics of each source within
+    let buckets = match args.buckets {
+        Some(buckets) => crate::stats::tree::load(Some(buckets)),
+        None => rstar::RTree::bulk_load(vec![]),
+    };
+
     let sources: Vec<String> = source_map.keys().cloned().collect();
     let threshold = args.threshold.unwrap_or(25);
     let mut agreement = agreement::Agreement::new(threshold);
 
+    let stage = progress.stage("consensus", None);
+
     for addr in AddrStream::new(
         GeoStream::new(Some(query_points)),
         context.clone(),
         args.error_query_points,
     ) {
+        stage.tick(1);
+        token.check()?;
+
+        let point = geo::Point::new(addr.geom[0], addr.geom[1]);
+        let within: Vec<String> = buckets
+            .locate_all_at_point(&[addr.geom[0], addr.geom[1]])
+            .filter(|bucket| bucket.geom.contains(&point))
+            .map(|bucket| bucket.name.clone())
+            .collect();
+
+        // clear the matches of the previous query point
+        for coords in source_map.values_mut() {
+            *coords = None;
+        }
+
         for source in &sources {
-            // pull the addresses matching this address number within 1 km
+            // pull the addresses matching this address number within the search radius
             let rows = conn
                 .query(
                     &query,
-                    &[&addr.number, &addr.geom[0], &addr.geom[1], &source],
+                    &[
+                        &addr.number,
+                        &addr.geom[0],
+                        &addr.geom[1],
+                        &source,
+                        &distance::degrees(radius, addr.geom[1]),
+                        &radius,
+                    ],
                 )
                 .unwrap();
 
@@ -187,15 +241,19 @@ pub fn consensus(mut cx: FunctionContext) -> JsResult<JsValue> {
                         ),
                     }
                 }
+                // nearby addresses with this number, but none with a matching name
+                None if !potential_matches.is_empty() => {
+                    agreement.name_disagreement(source, &within);
+                }
                 None => (),
             };
         }
 
         // update agreement with current set of matched points
-        agreement.process_points(&source_map);
+        agreement.process_points(&source_map, &within);
     }
 
-    Ok(neon_serde::to_value(&mut cx, &agreement)?)
+    serde_json::to_value(&agreement.finish()).map_err(|err| err.to_string())
 }
 
 ///
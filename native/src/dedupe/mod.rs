@@ -1,8 +1,9 @@
-use postgres::{Connection, TlsMode};
+use postgres::{Connection, GenericConnection, TlsMode};
 use std::collections::HashMap;
 use std::convert::From;
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::sync::Arc;
 use std::thread;
 
 use neon::prelude::*;
@@ -10,6 +11,10 @@ use neon::prelude::*;
 use crate::{
     stream::{AddrStream, GeoStream, PolyStream},
     types::hecate,
+    util::{
+        partition::{self, Parallel, Partition},
+        progress, task,
+    },
     Address,
 };
 
@@ -24,6 +29,10 @@ struct DedupeArgs {
     input: Option<String>,
     output: Option<String>,
     hecate: Option<bool>,
+    workers: Option<usize>,
+    window: Option<i64>,
+    pool: Option<usize>,
+    partition: Option<Partition>,
 }
 
 impl DedupeArgs {
@@ -35,23 +44,44 @@ impl DedupeArgs {
             input: None,
             output: None,
             hecate: None,
+            workers: None,
+            window: None,
+            pool: None,
+            partition: None,
+        }
+    }
+
+    pub fn parallel(&self) -> Parallel {
+        Parallel {
+            workers: self.workers,
+            window: self.window,
+            pool: self.pool,
+            partition: self.partition,
         }
     }
 }
 
-pub fn dedupe(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let args: DedupeArgs = match cx.argument_opt(0) {
+pub fn dedupe(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let (progress, callback) = progress::from_args(&mut cx, 0)?;
+
+    let args: DedupeArgs = match progress::args(&mut cx, 0)? {
         None => DedupeArgs::new(),
-        Some(arg) => {
-            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
-                DedupeArgs::new()
-            } else {
-                let arg_val = cx.argument::<JsValue>(0)?;
-                neon_serde::from_value(&mut cx, arg_val)?
-            }
-        }
+        Some(arg_val) => neon_serde::from_value(&mut cx, arg_val)?,
     };
 
+    task::run(&mut cx, 1, callback, move |token| {
+        run(args, progress, token)
+    })
+}
+
+///
+/// Import addresses & write out exact duplicates
+///
+fn run(
+    args: DedupeArgs,
+    progress: progress::Progress,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
     let is_hecate = args.hecate.unwrap_or(false);
 
     let conn = Connection::connect(
@@ -98,37 +128,41 @@ pub fn dedupe(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     };
 
     let count = address.count(&conn);
-    let cpus = num_cpus::get() as i64;
-    let mut web = Vec::new();
+    let stage = progress.stage("dedupe", Some(count as u64));
+
+    // Hecate ids can be sparse, so windows are bound by the min & max id rather than the count
+    let parallel = args.parallel();
+    let pool = pg::Pool::new(&args.db, parallel.pool())?;
+    let windows = match parallel.windows(&pool, "address") {
+        Some((_, _, windows)) => windows,
+        None => partition::IdWindows::range(0, -1, parallel.window()),
+    };
+    let windows = Arc::new(windows);
 
-    let batch_extra = count % cpus;
-    let batch = (count - batch_extra) / cpus;
+    let mut web = Vec::new();
 
     let (tx, rx) = crossbeam::channel::unbounded();
 
-    for cpu in 0..cpus {
-        let db_conn = args.db.clone();
+    for cpu in 0..parallel.workers() {
+        let pool = pool.clone();
+        let windows = windows.clone();
         let tx_n = tx.clone();
+        let stage = stage.clone();
+        let token = token.clone();
 
         let strand = match thread::Builder::new()
             .name(format!("Exact Dup #{}", &cpu))
             .spawn(move || {
-                let mut min_id = batch * cpu;
-                let max_id = batch * cpu + batch + batch_extra;
+                while !token.is_cancelled() {
+                    let (min_id, max_id) = match windows.next() {
+                        Some(window) => window,
+                        None => break,
+                    };
 
-                if cpu != 0 {
-                    min_id = min_id + batch_extra + 1;
-                }
+                    let conn = pool.get();
 
-                let conn = match Connection::connect(
-                    format!("postgres://postgres@localhost:5432/{}", &db_conn).as_str(),
-                    TlsMode::None,
-                ) {
-                    Ok(conn) => conn,
-                    Err(err) => panic!("Connection Error: {}", err.to_string()),
-                };
-
-                exact_batch(is_hecate, min_id, max_id, conn, tx_n);
+                    exact_batch(is_hecate, min_id, max_id, &*conn, &tx_n, &stage, &token);
+                }
             }) {
             Ok(strand) => strand,
             Err(err) => panic!("Thread Creation Error: {}", err.to_string()),
@@ -138,6 +172,8 @@ pub fn dedupe(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     }
 
     drop(tx);
+    drop(stage);
+    drop(progress);
 
     match args.output {
         Some(outpath) => {
@@ -157,7 +193,9 @@ pub fn dedupe(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         strand.join().unwrap();
     }
 
-    Ok(cx.boolean(true))
+    token.check()?;
+
+    Ok(serde_json::Value::Bool(true))
 }
 
 fn output(is_hecate: bool, receive: crossbeam::Receiver<Address>, mut sink: impl Write) {
@@ -183,13 +221,13 @@ fn exact_batch(
     is_hecate: bool,
     min_id: i64,
     max_id: i64,
-    conn: postgres::Connection,
-    tx: crossbeam::Sender<Address>,
+    conn: &impl GenericConnection,
+    tx: &crossbeam::Sender<Address>,
+    stage: &progress::Stage,
+    token: &task::CancelToken,
 ) {
-    let exact_dups = match pg::Cursor::new(
-        conn,
-        format!(
-            r#"
+    let exact_dups = match conn.query(
+        r#"
         SELECT
             JSON_Build_Object(
                 'primary', JSON_Build_Object(
@@ -223,19 +261,23 @@ fn exact_batch(
         FROM
             address a
         WHERE
-            a.id >= {min_id}
-            AND a.id <= {max_id}
+            a.id >= $1
+            AND a.id <= $2
     "#,
-            min_id = min_id,
-            max_id = max_id
-        ),
+        &[&min_id, &max_id],
     ) {
-        Ok(cursor) => cursor,
+        Ok(rows) => rows,
         Err(err) => panic!("ERR: {}", err.to_string()),
     };
 
-    for dup_feats in exact_dups {
-        let mut dup_feats = match dup_feats {
+    for row in exact_dups.iter() {
+        if token.is_cancelled() {
+            return;
+        }
+
+        stage.tick(1);
+
+        let mut dup_feats = match row.get(0) {
             serde_json::value::Value::Object(object) => object,
             _ => panic!("result must be JSON Object"),
         };
@@ -286,7 +328,7 @@ fn exact_batch(
         // Since this operation is performed in parallel - duplicates could be potentially
         // processed by multiple threads - resulting in duplicate output. To avoid this
         // the dup_feat will only be processed if the lowest ID in the match falls within
-        // the min_id/max_id that the given thread is processing
+        // the min_id/max_id window that is being processed
         //
         if dup_feats[0].id.unwrap() < feat.id.unwrap()
             || dup_feats[0].id.unwrap() < min_id
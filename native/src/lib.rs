@@ -61,6 +61,7 @@ register_module!(mut m, {
     m.export_function("cluster_net", map::cluster_net)?;
 
     m.export_function("intersections", map::intersections)?;
+    m.export_function("topology", map::topology)?;
 
     m.export_function("dedupe_syn", map::dedupe_syn)?;
 
@@ -73,5 +74,8 @@ register_module!(mut m, {
     m.export_function("stats", stats::stats)?;
     m.export_function("dedupe", dedupe::dedupe)?;
 
+    m.export_function("cancel", util::task::cancel)?;
+    m.export_function("events", util::task::events)?;
+
     Ok(())
 });
@@ -1,9 +1,13 @@
 use postgres::{Connection, TlsMode};
 use std::collections::HashMap;
 use std::convert::From;
+use std::sync::Arc;
 use std::thread;
 
-use crate::util::linker;
+use crate::conflate::Backend;
+use crate::util::partition::{self, Parallel, Partition};
+use crate::util::treelinker::TreeLinker;
+use crate::util::{checksum, distance, graph, linker, progress, task, topology};
 use crate::Context as CrateContext;
 use crate::{Name, Names, Tokens};
 
@@ -12,14 +16,24 @@ use neon::prelude::*;
 use super::stream::{AddrStream, GeoStream, NetStream};
 
 use super::pg;
-use super::pg::{InputTable, Table};
+use super::pg::{BinaryInputTable, InputTable, Table};
 
-pub fn pg_init(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+pub fn pg_init(mut cx: FunctionContext) -> JsResult<JsValue> {
     let db = match cx.argument_opt(0) {
-        Some(arg) => arg.downcast::<JsString>().or_throw(&mut cx)?.value(),
+        Some(arg) => {
+            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
+                String::from("pt_test")
+            } else {
+                arg.downcast::<JsString>().or_throw(&mut cx)?.value()
+            }
+        }
         None => String::from("pt_test"),
     };
 
+    task::run(&mut cx, 1, None, move |token| pg_init_run(db, token))
+}
+
+fn pg_init_run(db: String, token: task::CancelToken) -> Result<serde_json::Value, String> {
     let conn = match Connection::connect(
         format!("postgres://postgres@localhost:5432/{}", &db).as_str(),
         TlsMode::None,
@@ -37,6 +51,8 @@ pub fn pg_init(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     address.create(&conn);
     network.create(&conn);
 
+    token.check()?;
+
     let networkcluster = pg::NetworkCluster::new(false);
     let addresscluster = pg::AddressCluster::new(false);
 
@@ -49,18 +65,36 @@ pub fn pg_init(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     networkcluster.create(&conn);
     addresscluster.create(&conn);
 
+    token.check()?;
+
     let intersections = pg::Intersections::new();
     intersections.create(&conn);
 
-    Ok(cx.boolean(true))
+    // All tables have been recreated, any previous stage state is invalid
+    let pipeline = pg::Pipeline::new();
+    pipeline.create(&conn);
+    pipeline.index(&conn);
+    pipeline.reset(&conn);
+
+    Ok(serde_json::Value::Bool(true))
 }
 
-pub fn pg_optimize(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+pub fn pg_optimize(mut cx: FunctionContext) -> JsResult<JsValue> {
     let db = match cx.argument_opt(0) {
-        Some(arg) => arg.downcast::<JsString>().or_throw(&mut cx)?.value(),
+        Some(arg) => {
+            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
+                String::from("pt_test")
+            } else {
+                arg.downcast::<JsString>().or_throw(&mut cx)?.value()
+            }
+        }
         None => String::from("pt_test"),
     };
 
+    task::run(&mut cx, 1, None, move |token| pg_optimize_run(db, token))
+}
+
+fn pg_optimize_run(db: String, token: task::CancelToken) -> Result<serde_json::Value, String> {
     let conn = match Connection::connect(
         format!("postgres://postgres@localhost:5432/{}", &db).as_str(),
         TlsMode::None,
@@ -78,10 +112,139 @@ pub fn pg_optimize(mut cx: FunctionContext) -> JsResult<JsBoolean> {
     address.seq_id(&conn);
     network.seq_id(&conn);
 
+    token.check()?;
+
     address.index(&conn);
     network.index(&conn);
 
-    Ok(cx.boolean(true))
+    Ok(serde_json::Value::Bool(true))
+}
+
+///
+/// Arguments common to each map stage. For backwards compatibility the first argument
+/// of a stage can either be the name of the database or an object of the form
+/// { db: String, resume: bool }
+///
+/// Stages that process ids in parallel (link_addr) additionally accept
+/// { workers: Number, window: Number, pool: Number }
+///
+/// link_addr can link against an in memory RTree of the network clusters
+/// instead of PostGIS with { backend: "memory" }
+///
+/// Clustering & linking distances can be set in metres with
+/// { cluster_radius: Number, link_radius: Number }, and network cluster splitting
+/// with { cluster_gap: Number (metres), cluster_angle: Number (degrees) }
+///
+#[derive(Serialize, Deserialize, Debug)]
+struct StageArgs {
+    db: String,
+    resume: Option<bool>,
+    cluster_radius: Option<f64>,
+    cluster_gap: Option<f64>,
+    cluster_angle: Option<f64>,
+    link_radius: Option<f64>,
+    snap_tolerance: Option<f64>,
+    intersection_radius: Option<f64>,
+    workers: Option<usize>,
+    window: Option<i64>,
+    pool: Option<usize>,
+    partition: Option<Partition>,
+    backend: Option<Backend>,
+}
+
+impl StageArgs {
+    pub fn new() -> Self {
+        StageArgs {
+            db: String::from("pt_test"),
+            resume: None,
+            cluster_radius: None,
+            cluster_gap: None,
+            cluster_angle: None,
+            link_radius: None,
+            snap_tolerance: None,
+            intersection_radius: None,
+            workers: None,
+            window: None,
+            pool: None,
+            partition: None,
+            backend: None,
+        }
+    }
+
+    pub fn resume(&self) -> bool {
+        self.resume.unwrap_or(false)
+    }
+
+    pub fn cluster_radius(&self) -> f64 {
+        self.cluster_radius.unwrap_or(distance::CLUSTER_RADIUS)
+    }
+
+    pub fn cluster_gap(&self) -> f64 {
+        self.cluster_gap.unwrap_or(topology::CLUSTER_GAP)
+    }
+
+    pub fn cluster_angle(&self) -> f64 {
+        self.cluster_angle.unwrap_or(topology::CLUSTER_ANGLE)
+    }
+
+    pub fn link_radius(&self) -> f64 {
+        self.link_radius.unwrap_or(distance::LINK_RADIUS)
+    }
+
+    pub fn snap_tolerance(&self) -> f64 {
+        self.snap_tolerance.unwrap_or(topology::SNAP_TOLERANCE)
+    }
+
+    pub fn intersection_radius(&self) -> f64 {
+        self.intersection_radius
+            .unwrap_or(distance::INTERSECTION_RADIUS)
+    }
+
+    pub fn backend(&self) -> Backend {
+        self.backend.unwrap_or(Backend::Postgres)
+    }
+
+    pub fn parallel(&self) -> Parallel {
+        Parallel {
+            workers: self.workers,
+            window: self.window,
+            pool: self.pool,
+            partition: self.partition,
+        }
+    }
+}
+
+fn stage_args(cx: &mut FunctionContext) -> NeonResult<StageArgs> {
+    match progress::args(cx, 0)? {
+        None => Ok(StageArgs::new()),
+        Some(arg) => {
+            if arg.is_a::<JsString>() {
+                let mut args = StageArgs::new();
+                args.db = arg.downcast::<JsString>().or_throw(cx)?.value();
+
+                Ok(args)
+            } else {
+                Ok(neon_serde::from_value(cx, arg)?)
+            }
+        }
+    }
+}
+
+///
+/// Record the start of a stage in the pipeline table, returning whether
+/// it can be skipped, resumed, or must be run from scratch
+///
+fn begin_stage(
+    conn: &Connection,
+    stage: &str,
+    checksum: Option<&str>,
+    params: &serde_json::Value,
+    resume: bool,
+) -> pg::StageState {
+    let pipeline = pg::Pipeline::new();
+    pipeline.create(conn);
+    pipeline.index(conn);
+    pipeline.begin(conn, stage, checksum, params, resume)
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -91,6 +254,8 @@ struct MapArgs {
     seq: bool,
     input: Option<String>,
     errors: Option<String>,
+    binary: Option<bool>,
+    resume: Option<bool>,
 }
 
 impl MapArgs {
@@ -101,11 +266,45 @@ impl MapArgs {
             context: None,
             input: None,
             errors: None,
+            binary: None,
+            resume: None,
+        }
+    }
+
+    ///
+    /// Parameters that affect the output of an import. A completed import will
+    /// only be skipped if these and the input checksum are unchanged
+    ///
+    pub fn params(&self) -> serde_json::Value {
+        serde_json::json!({
+            "seq": self.seq,
+            "context": self.context
+        })
+    }
+
+    ///
+    /// Checksum of the input file. Input from stdin can't be checksummed
+    /// and as such is never considered complete
+    ///
+    pub fn checksum(&self) -> Result<Option<String>, String> {
+        match self.input {
+            Some(ref input) => Ok(Some(checksum::file(input)?)),
+            None => Ok(None),
+        }
+    }
+
+    ///
+    /// Format that input features should be sent to the database in
+    ///
+    pub fn format(&self) -> pg::CopyFormat {
+        match self.binary {
+            Some(true) => pg::CopyFormat::Binary,
+            _ => pg::CopyFormat::Text,
         }
     }
 }
 
-pub fn import_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+pub fn import_addr(mut cx: FunctionContext) -> JsResult<JsValue> {
     let args: MapArgs = match cx.argument_opt(0) {
         None => MapArgs::new(),
         Some(arg) => {
@@ -118,6 +317,10 @@ pub fn import_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         }
     };
 
+    task::run(&mut cx, 1, None, move |token| import_addr_run(args, token))
+}
+
+fn import_addr_run(args: MapArgs, token: task::CancelToken) -> Result<serde_json::Value, String> {
     let conn = match Connection::connect(
         format!("postgres://postgres@localhost:5432/{}", &args.db).as_str(),
         TlsMode::None,
@@ -138,21 +341,46 @@ pub fn import_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         ),
     };
 
+    let checksum = match args.checksum() {
+        Ok(checksum) => checksum,
+        Err(err) => {
+            return Err(err);
+        }
+    };
+
+    if begin_stage(
+        &conn,
+        "import_addr",
+        checksum.as_deref(),
+        &args.params(),
+        args.resume.unwrap_or(false) && checksum.is_some(),
+    ) == pg::StageState::Complete
+    {
+        return Ok(serde_json::Value::Bool(true));
+    }
+
     let address = pg::Address::new();
     address.create(&conn);
-    address.input(
-        &conn,
-        AddrStream::new(GeoStream::new(args.input), context, args.errors),
-    );
+    let format = args.format();
+    let stream =
+        AddrStream::new(GeoStream::new(args.input), context, args.errors).set_format(format);
+    match format {
+        pg::CopyFormat::Text => address.input(&conn, stream),
+        pg::CopyFormat::Binary => address.input_binary(&conn, stream),
+    };
+    token.check()?;
     if args.seq {
         address.seq_id(&conn);
     }
+    token.check()?;
     address.index(&conn);
 
-    Ok(cx.boolean(true))
+    pg::Pipeline::new().complete(&conn, "import_addr");
+
+    Ok(serde_json::Value::Bool(true))
 }
 
-pub fn import_net(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+pub fn import_net(mut cx: FunctionContext) -> JsResult<JsValue> {
     let args: MapArgs = match cx.argument_opt(0) {
         None => MapArgs::new(),
         Some(arg) => {
@@ -165,6 +393,10 @@ pub fn import_net(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         }
     };
 
+    task::run(&mut cx, 1, None, move |token| import_net_run(args, token))
+}
+
+fn import_net_run(args: MapArgs, token: task::CancelToken) -> Result<serde_json::Value, String> {
     let conn = match Connection::connect(
         format!("postgres://postgres@localhost:5432/{}", &args.db).as_str(),
         TlsMode::None,
@@ -185,33 +417,83 @@ pub fn import_net(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         ),
     };
 
+    let checksum = match args.checksum() {
+        Ok(checksum) => checksum,
+        Err(err) => {
+            return Err(err);
+        }
+    };
+
+    if begin_stage(
+        &conn,
+        "import_net",
+        checksum.as_deref(),
+        &args.params(),
+        args.resume.unwrap_or(false) && checksum.is_some(),
+    ) == pg::StageState::Complete
+    {
+        return Ok(serde_json::Value::Bool(true));
+    }
+
     let network = pg::Network::new();
     network.create(&conn);
-    network.input(
-        &conn,
-        NetStream::new(GeoStream::new(args.input), context, args.errors),
-    );
+    let format = args.format();
+    let stream =
+        NetStream::new(GeoStream::new(args.input), context, args.errors).set_format(format);
+    match format {
+        pg::CopyFormat::Text => network.input(&conn, stream),
+        pg::CopyFormat::Binary => network.input_binary(&conn, stream),
+    };
+    token.check()?;
     if args.seq {
         network.seq_id(&conn);
     }
+    token.check()?;
     network.index(&conn);
 
-    Ok(cx.boolean(true))
+    pg::Pipeline::new().complete(&conn, "import_net");
+
+    Ok(serde_json::Value::Bool(true))
 }
 
-pub fn cluster_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let db = match cx.argument_opt(0) {
-        Some(arg) => arg.downcast::<JsString>().or_throw(&mut cx)?.value(),
-        None => String::from("pt_test"),
-    };
+pub fn cluster_addr(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let args = stage_args(&mut cx)?;
 
     let orphan = match cx.argument_opt(1) {
-        Some(arg) => arg.downcast::<JsBoolean>().or_throw(&mut cx)?.value(),
+        Some(arg) => {
+            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
+                false
+            } else {
+                arg.downcast::<JsBoolean>().or_throw(&mut cx)?.value()
+            }
+        }
         None => false,
     };
 
+    let min_score = match cx.argument_opt(2) {
+        Some(arg) => {
+            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
+                None
+            } else {
+                Some(arg.downcast::<JsNumber>().or_throw(&mut cx)?.value())
+            }
+        }
+        None => None,
+    };
+
+    task::run(&mut cx, 3, None, move |token| {
+        cluster_addr_run(args, orphan, min_score, token)
+    })
+}
+
+fn cluster_addr_run(
+    args: StageArgs,
+    orphan: bool,
+    min_score: Option<f64>,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
     let conn = match Connection::connect(
-        format!("postgres://postgres@localhost:5432/{}", &db).as_str(),
+        format!("postgres://postgres@localhost:5432/{}", &args.db).as_str(),
         TlsMode::None,
     ) {
         Ok(conn) => conn,
@@ -221,22 +503,59 @@ pub fn cluster_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         }
     };
 
-    let cluster = pg::AddressCluster::new(orphan);
+    let stage = if orphan {
+        "cluster_addr_orphan"
+    } else {
+        "cluster_addr"
+    };
+
+    if begin_stage(
+        &conn,
+        stage,
+        None,
+        &serde_json::json!({
+            "min_score": min_score,
+            "radius": args.cluster_radius()
+        }),
+        args.resume(),
+    ) == pg::StageState::Complete
+    {
+        return Ok(serde_json::Value::Bool(true));
+    }
+
+    let cluster = pg::AddressCluster::new(orphan)
+        .set_min_score(min_score)
+        .set_radius(args.cluster_radius());
     cluster.create(&conn);
+    token.check()?;
     cluster.generate(&conn);
+    token.check()?;
     cluster.index(&conn);
 
-    Ok(cx.boolean(true))
+    pg::Pipeline::new().complete(&conn, stage);
+
+    Ok(serde_json::Value::Bool(true))
 }
 
-pub fn link_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let db = match cx.argument_opt(0) {
-        Some(arg) => arg.downcast::<JsString>().or_throw(&mut cx)?.value(),
-        None => String::from("pt_test"),
-    };
+pub fn link_addr(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let (progress, callback) = progress::from_args(&mut cx, 0)?;
+    let args = stage_args(&mut cx)?;
+
+    task::run(&mut cx, 1, callback, move |token| {
+        link_run(args, progress, token)
+    })
+}
 
+///
+/// Link each address to the most similar proximal network cluster
+///
+fn link_run(
+    args: StageArgs,
+    progress: progress::Progress,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
     let conn = match Connection::connect(
-        format!("postgres://postgres@localhost:5432/{}", &db).as_str(),
+        format!("postgres://postgres@localhost:5432/{}", &args.db).as_str(),
         TlsMode::None,
     ) {
         Ok(conn) => conn,
@@ -246,42 +565,97 @@ pub fn link_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         }
     };
 
-    let count = pg::Address::new().max(&conn);
+    let radius = args.link_radius();
 
-    let cpus = num_cpus::get() as i64;
-    let mut web = Vec::new();
+    let checkpoints = match begin_stage(
+        &conn,
+        "link_addr",
+        None,
+        &serde_json::json!({ "radius": radius }),
+        args.resume(),
+    ) {
+        pg::StageState::Complete => {
+            return Ok(serde_json::Value::Bool(true));
+        }
+        pg::StageState::Fresh => {
+            // Links are only written for addresses that match, so the links of a
+            // previous run, ie: with another radius, must not survive a fresh start
+            conn.execute(
+                "
+                UPDATE address
+                    SET
+                        netid = NULL,
+                        link_score = NULL,
+                        link_name = NULL,
+                        link_net_name = NULL,
+                        link_path = NULL,
+                        link_candidates = NULL
+            ",
+                &[],
+            )
+            .unwrap();
+
+            Arc::new(pg::Pipeline::new().checkpoints(&conn, "link_addr"))
+        }
+        pg::StageState::Resume => Arc::new(pg::Pipeline::new().checkpoints(&conn, "link_addr")),
+    };
+
+    let parallel = args.parallel();
+    let pool = pg::Pool::new(&args.db, parallel.pool())?;
 
-    let batch_extra = count % cpus;
-    let batch = (count - batch_extra) / cpus;
+    let (min, max, windows) = match parallel.windows(&pool, "address") {
+        Some(windows) => windows,
+        None => {
+            pg::Pipeline::new().complete(&conn, "link_addr");
+            return Ok(serde_json::Value::Bool(true));
+        }
+    };
+    let windows = Arc::new(windows);
 
-    for cpu in 0..cpus {
-        let db_conn = db.clone();
+    // Every worker shares a single tree of the network clusters
+    let tree = match args.backend() {
+        Backend::Memory => Some(Arc::new(TreeLinker::from_db(&conn).set_radius(radius))),
+        Backend::Postgres => None,
+    };
+
+    let stage = progress.stage("link_addr", Some(partition::span(min, max)));
+
+    let mut web = Vec::new();
+
+    for cpu in 0..parallel.workers() {
+        let pool = pool.clone();
+        let windows = windows.clone();
+        let checkpoints = checkpoints.clone();
+        let stage = stage.clone();
+        let token = token.clone();
+        let tree = tree.clone();
 
         let strand = match thread::Builder::new()
             .name(format!("Linker #{}", &cpu))
             .spawn(move || {
-                let mut min_id = batch * cpu;
-                let max_id = batch * cpu + batch + batch_extra;
-
-                if cpu != 0 {
-                    min_id = min_id + batch_extra + 1;
-                }
+                let pipeline = pg::Pipeline::new();
+
+                while !token.is_cancelled() {
+                    let (min, max) = match windows.next() {
+                        Some(window) => window,
+                        None => break,
+                    };
+
+                    // Window was committed by a previous run
+                    if !checkpoints.covers(min, max) {
+                        let conn = pool.get();
+                        let trans = conn.transaction().unwrap();
+
+                        match tree {
+                            Some(ref tree) => tree_process(&trans, tree, min, max),
+                            None => link_process(&trans, min, max, radius),
+                        };
+                        pipeline.checkpoint(&trans, "link_addr", min, max);
 
-                let conn = match Connection::connect(
-                    format!("postgres://postgres@localhost:5432/{}", &db_conn).as_str(),
-                    TlsMode::None,
-                ) {
-                    Ok(conn) => conn,
-                    Err(err) => {
-                        println!("Connection Error: {}", err.to_string());
-                        panic!("Connection Error: {}", err.to_string());
+                        trans.commit().unwrap();
                     }
-                };
 
-                let mut it = min_id;
-                while it < max_id {
-                    link_process(&conn, it, it + 5000);
-                    it += 5001;
+                    stage.tick(partition::span(min, max));
                 }
             }) {
             Ok(strand) => strand,
@@ -294,6 +668,8 @@ pub fn link_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         web.push(strand);
     }
 
+    drop(stage);
+
     for strand in web {
         match strand.join() {
             Err(err) => {
@@ -309,7 +685,11 @@ pub fn link_addr(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         };
     }
 
-    Ok(cx.boolean(true))
+    token.check()?;
+
+    pg::Pipeline::new().complete(&conn, "link_addr");
+
+    Ok(serde_json::Value::Bool(true))
 }
 
 #[derive(Serialize, Deserialize)]
@@ -323,9 +703,14 @@ pub struct DbType {
     names: Names,
 }
 
-pub fn link_process(conn: &impl postgres::GenericConnection, min: i64, max: i64) {
+///
+/// Link the addresses with ids between min & max to a network cluster within radius metres
+///
+pub fn link_process(conn: &impl postgres::GenericConnection, min: i64, max: i64, radius: f64) {
+    // The degree radius is only used as an index friendly prefilter, the distance is checked on geography
     match conn.query(
-        "
+        format!(
+            "
         SELECT
             a.id AS id,
             a.names::JSON AS name,
@@ -339,14 +724,18 @@ pub fn link_process(conn: &impl postgres::GenericConnection, min: i64, max: i64)
         FROM
             address a
             INNER JOIN network_cluster nc
-            ON ST_DWithin(a.geom, nc.geom, 0.02)
+            ON ST_DWithin(a.geom, nc.geom, {degrees})
+            AND ST_DWithin(ST_Force2D(a.geom)::GEOGRAPHY, nc.geom::GEOGRAPHY, $3)
         WHERE a.id >= $1 AND a.id <= $2
         GROUP BY
             a.id,
             a.names,
             a.geom
     ",
-        &[&min, &max],
+            degrees = distance::sql_degrees("$3", "a.geom")
+        )
+        .as_str(),
+        &[&min, &max, &radius],
     ) {
         Ok(results) => {
             let trans = match conn.transaction() {
@@ -396,21 +785,76 @@ pub fn link_process(conn: &impl postgres::GenericConnection, min: i64, max: i64)
                     .map(|potential| linker::Link::new(potential.id, &potential.names))
                     .collect();
 
-                match linker::linker(primary, potentials, false) {
-                    Some(link_match) => {
-                        match trans.execute(
-                            &*"
-                            UPDATE address SET netid = $1 WHERE id = $2 AND interpolate = true;
-                        ",
-                            &[&link_match.id, &id],
-                        ) {
-                            Err(err) => {
-                                println!("Transaction Statement Error: {}", err.to_string());
-                                panic!("Transaction Statement Error: {}", err.to_string());
-                            }
-                            Ok(trans) => trans,
-                        };
+                match linker::linker_detail(primary, potentials, false) {
+                    Some(link_match) => link_write(&trans, id, &link_match),
+                    None => (),
+                };
+            }
+
+            match trans.commit() {
+                Err(err) => {
+                    println!("Transaction Commit Error: {}", err.to_string());
+                    panic!("Transaction Commit Error: {}", err.to_string());
+                }
+                _ => (),
+            };
+        }
+        Err(err) => {
+            println!("{}", err.to_string());
+            panic!("{}", err.to_string());
+        }
+    };
+}
+
+///
+/// Link the addresses with ids between min & max using an in memory tree of the
+/// network clusters, producing the same links as link_process
+///
+pub fn tree_process(
+    conn: &impl postgres::GenericConnection,
+    tree: &TreeLinker,
+    min: i64,
+    max: i64,
+) {
+    match conn.query(
+        "
+        SELECT
+            a.id AS id,
+            a.names::JSON AS name,
+            ST_X(a.geom) AS lon,
+            ST_Y(a.geom) AS lat
+        FROM
+            address a
+        WHERE a.id >= $1 AND a.id <= $2
+    ",
+        &[&min, &max],
+    ) {
+        Ok(results) => {
+            let trans = match conn.transaction() {
+                Err(err) => {
+                    println!("Transaction Create Error: {}", err.to_string());
+                    panic!("Transaction Create Error: {}", err.to_string());
+                }
+                Ok(trans) => trans,
+            };
+
+            for result in results.iter() {
+                let id: i64 = result.get(0);
+                let names: serde_json::Value = result.get(1);
+                let names: Vec<Name> = match serde_json::from_value(names) {
+                    Err(err) => {
+                        println!("JSON Failure: {}", err.to_string());
+                        panic!("JSON Failure: {}", err.to_string());
                     }
+                    Ok(names) => names,
+                };
+
+                let names = Names { names: names };
+
+                let point: [f64; 2] = [result.get(2), result.get(3)];
+
+                match tree.link_detail(id, &names, point) {
+                    Some(link_match) => link_write(&trans, id, &link_match),
                     None => (),
                 };
             }
@@ -430,19 +874,68 @@ pub fn link_process(conn: &impl postgres::GenericConnection, min: i64, max: i64)
     };
 }
 
-pub fn cluster_net(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let db = match cx.argument_opt(0) {
-        Some(arg) => arg.downcast::<JsString>().or_throw(&mut cx)?.value(),
-        None => String::from("pt_test"),
+///
+/// Write the network an address was linked to, along with the provenance of the match
+///
+fn link_write(conn: &impl postgres::GenericConnection, id: i64, link_match: &linker::LinkDetail) {
+    match conn.execute(
+        &*"
+        UPDATE address
+            SET
+                netid = $1,
+                link_score = $2,
+                link_name = $3,
+                link_net_name = $4,
+                link_path = $5,
+                link_candidates = $6
+            WHERE
+                id = $7
+                AND interpolate = true;
+    ",
+        &[
+            &link_match.id,
+            &link_match.score,
+            &link_match.name,
+            &link_match.matched,
+            &link_match.path.as_str(),
+            &link_match.candidates,
+            &id,
+        ],
+    ) {
+        Err(err) => {
+            println!("Transaction Statement Error: {}", err.to_string());
+            panic!("Transaction Statement Error: {}", err.to_string());
+        }
+        Ok(_) => (),
     };
+}
+
+pub fn cluster_net(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let args = stage_args(&mut cx)?;
 
     let orphan = match cx.argument_opt(1) {
-        Some(arg) => arg.downcast::<JsBoolean>().or_throw(&mut cx)?.value(),
+        Some(arg) => {
+            if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
+                false
+            } else {
+                arg.downcast::<JsBoolean>().or_throw(&mut cx)?.value()
+            }
+        }
         None => false,
     };
 
+    task::run(&mut cx, 2, None, move |token| {
+        cluster_net_run(args, orphan, token)
+    })
+}
+
+fn cluster_net_run(
+    args: StageArgs,
+    orphan: bool,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
     let conn = match Connection::connect(
-        format!("postgres://postgres@localhost:5432/{}", &db).as_str(),
+        format!("postgres://postgres@localhost:5432/{}", &args.db).as_str(),
         TlsMode::None,
     ) {
         Ok(conn) => conn,
@@ -452,22 +945,56 @@ pub fn cluster_net(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         }
     };
 
-    let cluster = pg::NetworkCluster::new(orphan);
+    let stage = if orphan {
+        "cluster_net_orphan"
+    } else {
+        "cluster_net"
+    };
+
+    if begin_stage(
+        &conn,
+        stage,
+        None,
+        &serde_json::json!({
+            "radius": args.cluster_radius(),
+            "gap": args.cluster_gap(),
+            "angle": args.cluster_angle()
+        }),
+        args.resume(),
+    ) == pg::StageState::Complete
+    {
+        return Ok(serde_json::Value::Bool(true));
+    }
+
+    let cluster = pg::NetworkCluster::new(orphan)
+        .set_radius(args.cluster_radius())
+        .set_gap(args.cluster_gap())
+        .set_angle(args.cluster_angle());
     cluster.create(&conn);
+    token.check()?;
     cluster.generate(&conn);
+    token.check()?;
     cluster.index(&conn);
 
-    Ok(cx.boolean(true))
+    pg::Pipeline::new().complete(&conn, stage);
+
+    Ok(serde_json::Value::Bool(true))
 }
 
-pub fn intersections(mut cx: FunctionContext) -> JsResult<JsBoolean> {
-    let db = match cx.argument_opt(0) {
-        Some(arg) => arg.downcast::<JsString>().or_throw(&mut cx)?.value(),
-        None => String::from("pt_test"),
-    };
+pub fn intersections(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let args = stage_args(&mut cx)?;
 
+    task::run(&mut cx, 1, None, move |token| {
+        intersections_run(args, token)
+    })
+}
+
+fn intersections_run(
+    args: StageArgs,
+    token: task::CancelToken,
+) -> Result<serde_json::Value, String> {
     let conn = match Connection::connect(
-        format!("postgres://postgres@localhost:5432/{}", &db).as_str(),
+        format!("postgres://postgres@localhost:5432/{}", &args.db).as_str(),
         TlsMode::None,
     ) {
         Ok(conn) => conn,
@@ -477,12 +1004,62 @@ pub fn intersections(mut cx: FunctionContext) -> JsResult<JsBoolean> {
         }
     };
 
+    if begin_stage(
+        &conn,
+        "intersections",
+        None,
+        &serde_json::json!({
+            "radius": args.intersection_radius()
+        }),
+        args.resume(),
+    ) == pg::StageState::Complete
+    {
+        return Ok(serde_json::Value::Bool(true));
+    }
+
     let intersections = pg::Intersections::new();
     intersections.create(&conn);
+    token.check()?;
     intersections.generate(&conn);
+    token.check()?;
+    intersections.dedupe(&conn, args.intersection_radius());
+    token.check()?;
     intersections.index(&conn);
 
-    Ok(cx.boolean(true))
+    pg::Pipeline::new().complete(&conn, "intersections");
+
+    Ok(serde_json::Value::Bool(true))
+}
+
+///
+/// Build a node/edge graph of the imported network table and return a
+/// summary of its shape for QA, without modifying the database
+///
+pub fn topology(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let args = stage_args(&mut cx)?;
+
+    task::run(&mut cx, 1, None, move |token| topology_run(args, token))
+}
+
+fn topology_run(args: StageArgs, token: task::CancelToken) -> Result<serde_json::Value, String> {
+    let conn = match Connection::connect(
+        format!("postgres://postgres@localhost:5432/{}", &args.db).as_str(),
+        TlsMode::None,
+    ) {
+        Ok(conn) => conn,
+        Err(err) => {
+            println!("Connection Error: {}", err.to_string());
+            panic!("Connection Error: {}", err.to_string());
+        }
+    };
+
+    let graph = graph::Graph::new()
+        .set_tolerance(args.snap_tolerance())
+        .from_db(&conn);
+
+    token.check()?;
+
+    serde_json::to_value(graph.summary()).map_err(|err| err.to_string())
 }
 
 ///
@@ -1,4 +1,4 @@
-use super::{InputTable, Table};
+use super::{BinaryInputTable, InputTable, Table};
 use postgres::Connection;
 use std::io::Read;
 
@@ -49,6 +49,11 @@ impl Table for Address {
                 id BIGINT,
                 version BIGINT,
                 netid BIGINT,
+                link_score DOUBLE PRECISION,
+                link_name TEXT,
+                link_net_name TEXT,
+                link_path TEXT,
+                link_candidates BIGINT,
                 names JSONB,
                 number TEXT,
                 source TEXT,
@@ -189,6 +194,34 @@ impl InputTable for Address {
     }
 }
 
+impl BinaryInputTable for Address {
+    fn input_binary(&self, conn: &Connection, mut data: impl Read) {
+        let stmt = conn
+            .prepare(
+                r#"
+            COPY address (
+                id,
+                version,
+                names,
+                number,
+                source,
+                output,
+                interpolate,
+                props,
+                geom
+            )
+            FROM STDIN
+            WITH (
+                FORMAT BINARY
+            )
+        "#,
+            )
+            .unwrap();
+
+        stmt.copy_in(&[], &mut data).unwrap();
+    }
+}
+
 // Prepare address to be run through conflate by setting output=false and id to its inverse
 // for all past versions of a feature.
 // This ensures that past features are not modified but will match addresses being conflated.
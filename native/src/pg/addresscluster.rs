@@ -1,13 +1,41 @@
-use super::Table;
+use super::{reach, Table};
+use crate::util::distance;
 use postgres::Connection;
 
 pub struct AddressCluster {
     orphan: bool,
+    min_score: Option<f64>,
+    radius: f64,
 }
 
 impl AddressCluster {
     pub fn new(orphan: bool) -> Self {
-        AddressCluster { orphan: orphan }
+        AddressCluster {
+            orphan: orphan,
+            min_score: None,
+            radius: distance::CLUSTER_RADIUS,
+        }
+    }
+
+    ///
+    /// Builder style cluster radius setter (metres)
+    ///
+    /// Orphan addresses with the same names within this distance of each other are clustered
+    ///
+    pub fn set_radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    ///
+    /// Builder style minimum link score setter
+    ///
+    /// Addresses linked with a score lower than the given value are clustered as
+    /// orphans, their link is kept so that clustering can be rerun with another score
+    ///
+    pub fn set_min_score(mut self, min_score: Option<f64>) -> Self {
+        self.min_score = min_score;
+        self
     }
 
     ///
@@ -15,20 +43,38 @@ impl AddressCluster {
     ///
     pub fn generate(&self, conn: &postgres::Connection) {
         if self.orphan {
+            reach::cluster(
+                conn,
+                r#"
+                SELECT
+                    id,
+                    names::TEXT,
+                    geom
+                FROM
+                    address
+                WHERE
+                    netid IS NULL
+                    OR link_score < $1::FLOAT8
+            "#,
+                &[&self.min_score],
+                self.radius,
+            );
+
             conn.execute(
                 r#"
                 INSERT INTO address_orphan_cluster (names, geom)
                     SELECT
                         addr.names,
-                        ST_Multi(ST_CollectionExtract(addr.geom, 1)) AS geom
-                    FROM (
-                        SELECT
-                            names,
-                            unnest(ST_ClusterWithin(geom, 0.005)) AS geom
-                        FROM address
-                        WHERE netid IS NULL
-                        GROUP BY names
-                    ) addr;
+                        ST_Multi(ST_CollectionExtract(ST_Collect(addr.geom ORDER BY addr.id), 1)) AS geom
+                    FROM
+                        address addr
+                        INNER JOIN reach r
+                        ON r.id = addr.id
+                    GROUP BY
+                        addr.names,
+                        r.cluster
+                    ORDER BY
+                        r.cluster;
             "#,
                 &[],
             )
@@ -49,13 +95,39 @@ impl AddressCluster {
                             address
                         WHERE
                             netid IS NOT NULL
+                            AND NOT COALESCE(link_score < $1::FLOAT8, FALSE)
                         GROUP BY
                             netid,
                             names
                     ) a
                     GROUP BY
                         netid;
-            "#, &[]).unwrap();
+            "#, &[&self.min_score]).unwrap();
+
+            conn.execute(
+                r#"
+                UPDATE address_cluster c
+                    SET
+                        link_score = l.link_score,
+                        link_paths = l.link_paths
+                    FROM (
+                        SELECT
+                            netid,
+                            MIN(link_score) AS link_score,
+                            Array_Agg(DISTINCT link_path ORDER BY link_path) AS link_paths
+                        FROM
+                            address
+                        WHERE
+                            netid IS NOT NULL
+                            AND NOT COALESCE(link_score < $1::FLOAT8, FALSE)
+                        GROUP BY
+                            netid
+                    ) l
+                    WHERE c.netid = l.netid;
+            "#,
+                &[&self.min_score],
+            )
+            .unwrap();
 
             conn.execute(
                 r#"
@@ -118,7 +190,9 @@ impl Table for AddressCluster {
                     netid BIGINT,
                     names JSONB,
                     geom GEOMETRY(MULTIPOINTZ, 4326),
-                    props JSONB
+                    props JSONB,
+                    link_score DOUBLE PRECISION,
+                    link_paths TEXT[]
                 )
             "#,
                 &[],
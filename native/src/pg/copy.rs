@@ -0,0 +1,112 @@
+///
+/// Format that a stream will be written to the database via COPY
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum CopyFormat {
+    /// Tab delimited text, geometries as hex encoded EWKB
+    Text,
+
+    /// PostgreSQL binary COPY format, geometries as EWKB
+    Binary,
+}
+
+///
+/// Binary COPY header - signature, flags field & header extension length
+///
+pub const HEADER: &[u8] = b"PGCOPY\n\xff\r\n\0\0\0\0\0\0\0\0\0";
+
+///
+/// Binary COPY trailer - a field count of -1
+///
+pub const TRAILER: &[u8] = &[0xff, 0xff];
+
+///
+/// Build a single tuple in the PostgreSQL binary COPY format
+///
+/// Fields must be written in the same order as the columns
+/// given to the COPY statement
+///
+pub struct BinaryRow {
+    buf: Vec<u8>,
+}
+
+impl BinaryRow {
+    pub fn new(fields: i16) -> Self {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&fields.to_be_bytes());
+
+        BinaryRow { buf: buf }
+    }
+
+    pub fn null(&mut self) {
+        self.buf.extend_from_slice(&(-1_i32).to_be_bytes());
+    }
+
+    pub fn bytes(&mut self, value: &[u8]) {
+        self.buf
+            .extend_from_slice(&(value.len() as i32).to_be_bytes());
+        self.buf.extend_from_slice(value);
+    }
+
+    pub fn int8(&mut self, value: Option<i64>) {
+        match value {
+            Some(value) => self.bytes(&value.to_be_bytes()),
+            None => self.null(),
+        };
+    }
+
+    pub fn bool(&mut self, value: bool) {
+        self.bytes(&[value as u8]);
+    }
+
+    pub fn text(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    ///
+    /// JSONB is sent as a version byte followed by the text representation
+    ///
+    pub fn jsonb(&mut self, value: &serde_json::Value) {
+        let mut jsonb = vec![1];
+        jsonb.append(&mut value.to_string().into_bytes());
+
+        self.bytes(&jsonb);
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        self.buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_header() {
+        assert_eq!(HEADER.len(), 19);
+        assert_eq!(&HEADER[0..11], b"PGCOPY\n\xff\r\n\0");
+    }
+
+    #[test]
+    fn test_binary_row() {
+        let mut row = BinaryRow::new(5);
+        row.int8(Some(2));
+        row.int8(None);
+        row.bool(true);
+        row.text("ab");
+        row.jsonb(&serde_json::Value::Bool(false));
+
+        assert_eq!(
+            row.finish(),
+            vec![
+                0, 5, // field count
+                0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 2, // int8
+                255, 255, 255, 255, // null
+                0, 0, 0, 1, 1, // bool
+                0, 0, 0, 2, 97, 98, // text
+                0, 0, 0, 6, 1, 102, 97, 108, 115, 101 // jsonb
+            ]
+        );
+    }
+}
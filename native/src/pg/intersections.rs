@@ -1,5 +1,9 @@
 use super::Table;
+use crate::util::distance;
+use crate::{Name, Names};
 use postgres::Connection;
+use std::collections::HashMap;
+use std::hash::Hash;
 
 pub struct Intersections();
 
@@ -11,6 +15,10 @@ impl Intersections {
     ///
     /// Create intersections from network data
     ///
+    /// Clusters intersect if any pair of their source networks physically connect,
+    /// that is they share a vertex, or they cross on the same layer & neither is a
+    /// limited access highway crossing a street that isn't, as per their network grade
+    ///
     pub fn generate(&self, conn: &Connection) {
         conn.execute(
             "
@@ -18,13 +26,29 @@ impl Intersections {
                 SELECT
                     a.id,
                     b.id,
-                    ST_PointOnSurface(ST_Intersection(a.geom, b.geom)) AS geom
+                    ST_PointOnSurface(ST_Union(ST_Intersection(ST_Force2D(na.geom), ST_Force2D(nb.geom)))) AS geom
                 FROM
                     network_cluster AS a
                     INNER JOIN network_cluster AS b
                     ON
                         a.id != b.id
                         AND ST_Intersects(a.geom, b.geom)
+                    INNER JOIN network AS na
+                    ON
+                        na.id = ANY(a.source_ids)
+                    INNER JOIN network AS nb
+                    ON
+                        nb.id = ANY(b.source_ids)
+                        AND ST_Intersects(na.geom, nb.geom)
+                WHERE
+                    ST_Intersects(ST_Points(ST_Force2D(na.geom)), ST_Points(ST_Force2D(nb.geom)))
+                    OR (
+                        COALESCE((na.grade->>'layer')::BIGINT, 0) = COALESCE((nb.grade->>'layer')::BIGINT, 0)
+                        AND COALESCE((na.grade->>'limited')::BOOLEAN, FALSE) = COALESCE((nb.grade->>'limited')::BOOLEAN, FALSE)
+                    )
+                GROUP BY
+                    a.id,
+                    b.id
             )
         ",
             &[],
@@ -53,6 +77,294 @@ impl Intersections {
         )
         .unwrap();
     }
+
+    ///
+    /// Canonicalize, merge & name generated intersections
+    ///
+    /// Each pair of network clusters is only kept once, ordered by id. Intersections of
+    /// the same streets within radius metres of each other, ie: where dual carriageways
+    /// cross, are merged into the first of them at their average point. The network
+    /// clusters of every merged intersection are kept in a_ids & b_ids so that each
+    /// cluster still finds the intersection. The display names & every combination of
+    /// synonyms are ordered by name priority, with the primary combination first, for
+    /// the output to name intersections with
+    ///
+    pub fn dedupe(&self, conn: &Connection, radius: f64) {
+        conn.execute(
+            "
+            UPDATE intersections
+                SET
+                    a_id = b_id,
+                    b_id = a_id,
+                    a_street = b_street,
+                    b_street = a_street
+                WHERE
+                    a_id > b_id
+        ",
+            &[],
+        )
+        .unwrap();
+
+        conn.execute(
+            "
+            DELETE FROM intersections i
+                USING intersections j
+                WHERE
+                    i.a_id = j.a_id
+                    AND i.b_id = j.b_id
+                    AND i.id > j.id
+        ",
+            &[],
+        )
+        .unwrap();
+
+        conn.execute(
+            "
+            UPDATE intersections
+                SET
+                    a_ids = ARRAY[a_id],
+                    b_ids = ARRAY[b_id]
+        ",
+            &[],
+        )
+        .unwrap();
+
+        let rows = conn
+            .query(
+                "
+            SELECT
+                id,
+                a_street,
+                b_street,
+                ST_X(geom),
+                ST_Y(geom),
+                a_id,
+                b_id
+            FROM
+                intersections
+            WHERE
+                geom IS NOT NULL
+            ORDER BY
+                id
+        ",
+                &[],
+            )
+            .unwrap();
+
+        let mut ids: Vec<i32> = Vec::with_capacity(rows.len());
+        let mut streets: Vec<(Vec<String>, Vec<String>)> = Vec::with_capacity(rows.len());
+        let mut coords: Vec<[f64; 2]> = Vec::with_capacity(rows.len());
+        let mut clusters: Vec<(i64, i64)> = Vec::with_capacity(rows.len());
+
+        for row in rows.iter() {
+            let a_street: Option<serde_json::Value> = row.get(1);
+            let b_street: Option<serde_json::Value> = row.get(2);
+
+            ids.push(row.get(0));
+            streets.push((ordered(a_street), ordered(b_street)));
+            coords.push([row.get(3), row.get(4)]);
+            clusters.push((row.get(5), row.get(6)));
+        }
+
+        let keys: Vec<Option<(&String, &String)>> = streets
+            .iter()
+            .map(|(a, b)| match (a.first(), b.first()) {
+                (Some(a), Some(b)) if a <= b => Some((a, b)),
+                (Some(a), Some(b)) => Some((b, a)),
+                _ => None,
+            })
+            .collect();
+
+        // each group of merged intersections is kept as its first intersection, at the
+        // average point of the group
+        let groups = merge(&keys, &coords, radius);
+
+        let mut keep: Vec<i32> = Vec::with_capacity(groups.len());
+        let mut xs: Vec<f64> = Vec::with_capacity(groups.len());
+        let mut ys: Vec<f64> = Vec::with_capacity(groups.len());
+        let mut a_displays: Vec<Option<String>> = Vec::with_capacity(groups.len());
+        let mut b_displays: Vec<Option<String>> = Vec::with_capacity(groups.len());
+        let mut synonyms_list: Vec<String> = Vec::with_capacity(groups.len());
+        let mut a_ids_list: Vec<String> = Vec::with_capacity(groups.len());
+        let mut b_ids_list: Vec<String> = Vec::with_capacity(groups.len());
+        let mut remove: Vec<i32> = Vec::new();
+
+        for group in groups {
+            let first = group[0];
+            let count = group.len() as f64;
+            let (a, b) = &streets[first];
+
+            keep.push(ids[first]);
+            xs.push(group.iter().map(|i| coords[*i][0]).sum::<f64>() / count);
+            ys.push(group.iter().map(|i| coords[*i][1]).sum::<f64>() / count);
+            a_displays.push(a.first().cloned());
+            b_displays.push(b.first().cloned());
+            synonyms_list.push(serde_json::to_string(&synonyms(a, b)).unwrap());
+
+            // merged intersections may have their streets the other way around
+            let mut a_ids: Vec<i64> = Vec::new();
+            let mut b_ids: Vec<i64> = Vec::new();
+            for i in group.iter() {
+                let (a_id, b_id) = if streets[*i].0.first() == a.first() {
+                    clusters[*i]
+                } else {
+                    (clusters[*i].1, clusters[*i].0)
+                };
+
+                if !a_ids.contains(&a_id) {
+                    a_ids.push(a_id);
+                }
+                if !b_ids.contains(&b_id) {
+                    b_ids.push(b_id);
+                }
+            }
+            a_ids_list.push(pg_array(&a_ids));
+            b_ids_list.push(pg_array(&b_ids));
+
+            remove.extend(group[1..].iter().map(|i| ids[*i]));
+        }
+
+        let trans = conn.transaction().unwrap();
+
+        trans
+            .execute(
+                "
+            UPDATE intersections i
+                SET
+                    geom = ST_SetSRID(ST_MakePoint(u.x, u.y), 4326),
+                    a_display = u.a_display,
+                    b_display = u.b_display,
+                    synonyms = u.synonyms::JSONB,
+                    a_ids = u.a_ids::BIGINT[],
+                    b_ids = u.b_ids::BIGINT[]
+                FROM
+                    unnest($1::INT[], $2::FLOAT8[], $3::FLOAT8[], $4::TEXT[], $5::TEXT[], $6::TEXT[], $7::TEXT[], $8::TEXT[])
+                        AS u(id, x, y, a_display, b_display, synonyms, a_ids, b_ids)
+                WHERE
+                    i.id = u.id
+        ",
+                &[
+                    &keep,
+                    &xs,
+                    &ys,
+                    &a_displays,
+                    &b_displays,
+                    &synonyms_list,
+                    &a_ids_list,
+                    &b_ids_list,
+                ],
+            )
+            .unwrap();
+
+        trans
+            .execute(
+                "
+            DELETE FROM intersections
+                WHERE
+                    id = ANY($1::INT[])
+        ",
+                &[&remove],
+            )
+            .unwrap();
+
+        trans.commit().unwrap();
+    }
+}
+
+///
+/// Display names of a JSON list of street names, ordered by priority & frequency,
+/// skipping blank names & names with the same tokenized form, as per dedupe_syn
+///
+fn ordered(names: Option<serde_json::Value>) -> Vec<String> {
+    let names: Vec<Name> = match names {
+        Some(names) => serde_json::from_value(names).unwrap_or(Vec::new()),
+        None => Vec::new(),
+    };
+
+    let mut names = Names { names: names };
+    names.empty();
+    names.sort();
+    names.dedupe();
+
+    names.names.into_iter().map(|name| name.display).collect()
+}
+
+///
+/// Postgres array literal of a list of ids, ie: {1,2}
+///
+fn pg_array(ids: &[i64]) -> String {
+    let ids: Vec<String> = ids.iter().map(|id| id.to_string()).collect();
+
+    format!("{{{}}}", ids.join(","))
+}
+
+///
+/// Every combination of a & b street names, ordered by the priority of the a name,
+/// then the b name, so that the combination of both primary names is first
+///
+fn synonyms(a: &[String], b: &[String]) -> Vec<(String, String)> {
+    let mut combinations = Vec::with_capacity(a.len() * b.len());
+
+    for a_name in a {
+        for b_name in b {
+            combinations.push((a_name.clone(), b_name.clone()));
+        }
+    }
+
+    combinations
+}
+
+///
+/// Group intersections with the same key that are within radius metres of each other,
+/// intersections without a key are never merged
+///
+/// Intersections are bucketed by key so that only intersections of the same streets
+/// are compared with each other. In id order, each intersection that isn't yet grouped
+/// seeds a group of the ungrouped intersections within radius of it. Grouping is not
+/// transitive, so a chain of nearby intersections can't merge into a far away point
+///
+fn merge<K: Eq + Hash>(keys: &[Option<K>], coords: &[[f64; 2]], radius: f64) -> Vec<Vec<usize>> {
+    let mut seeds: Vec<Option<usize>> = vec![None; keys.len()];
+
+    let mut buckets: HashMap<&K, Vec<usize>> = HashMap::new();
+    for (i, key) in keys.iter().enumerate() {
+        if let Some(key) = key {
+            buckets.entry(key).or_insert_with(Vec::new).push(i);
+        }
+    }
+
+    for bucket in buckets.values() {
+        for (pos, &seed) in bucket.iter().enumerate() {
+            if seeds[seed].is_some() {
+                continue;
+            }
+
+            seeds[seed] = Some(seed);
+
+            for &other in &bucket[pos + 1..] {
+                if seeds[other].is_none() && distance::metres(coords[seed], coords[other]) <= radius
+                {
+                    seeds[other] = Some(seed);
+                }
+            }
+        }
+    }
+
+    // seeds always precede the members of their group
+    let mut groups: Vec<Vec<usize>> = Vec::new();
+    let mut lookup: HashMap<usize, usize> = HashMap::new();
+    for (i, seed) in seeds.iter().enumerate() {
+        let seed = seed.unwrap_or(i);
+
+        if seed == i {
+            lookup.insert(i, groups.len());
+            groups.push(vec![i]);
+        } else {
+            groups[lookup[&seed]].push(i);
+        }
+    }
+
+    groups
 }
 
 impl Table for Intersections {
@@ -79,8 +391,13 @@ impl Table for Intersections {
                 id SERIAL,
                 a_id BIGINT,
                 b_id BIGINT,
+                a_ids BIGINT[],
+                b_ids BIGINT[],
                 a_street JSONB,
                 b_street JSONB,
+                a_display TEXT,
+                b_display TEXT,
+                synonyms JSONB,
                 geom GEOMETRY(POINT, 4326)
             )
         "#,
@@ -114,3 +431,101 @@ impl Table for Intersections {
         .unwrap();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ordered() {
+        let names = serde_json::json!([
+            { "display": "Highway 1", "priority": -1, "tokenized": [{ "token": "hwy", "token_type": null }, { "token": "1", "token_type": null }], "freq": 1 },
+            { "display": "Main Street", "priority": 0, "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": null }], "freq": 1 },
+            { "display": "Main St", "priority": 0, "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": null }], "freq": 5 },
+            { "display": " ", "priority": 1, "tokenized": [], "freq": 1 },
+            { "display": "Elm St", "priority": -1, "tokenized": [{ "token": "elm", "token_type": null }, { "token": "st", "token_type": null }], "freq": 1 }
+        ]);
+
+        // the same tokenized name keeps the longest display, with the priority of the first
+        assert_eq!(
+            ordered(Some(names)),
+            vec![
+                String::from("Main Street"),
+                String::from("Highway 1"),
+                String::from("Elm St")
+            ]
+        );
+
+        assert_eq!(ordered(None), Vec::<String>::new());
+        assert_eq!(
+            ordered(Some(serde_json::json!("bogus"))),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn test_synonyms() {
+        let a = vec![String::from("Main St"), String::from("Highway 1")];
+        let b = vec![String::from("Elm St")];
+
+        assert_eq!(
+            synonyms(&a, &b),
+            vec![
+                (String::from("Main St"), String::from("Elm St")),
+                (String::from("Highway 1"), String::from("Elm St"))
+            ]
+        );
+
+        assert_eq!(synonyms(&a, &[]), Vec::<(String, String)>::new());
+    }
+
+    #[test]
+    fn test_merge() {
+        let keys = vec![
+            Some(("elm", "main")),
+            Some(("elm", "main")),
+            Some(("elm", "oak")),
+            None,
+            None,
+        ];
+        let coords = vec![
+            [-77.0, 38.0],
+            // ~20m away, ie: the other carriageway
+            [-77.0002, 38.0001],
+            [-77.0, 38.0],
+            [-77.0, 38.0],
+            [-77.0, 38.0],
+        ];
+
+        assert_eq!(
+            merge(&keys, &coords, 50.0),
+            vec![vec![0, 1], vec![2], vec![3], vec![4]]
+        );
+        assert_eq!(
+            merge(&keys, &coords, 10.0),
+            vec![vec![0], vec![1], vec![2], vec![3], vec![4]]
+        );
+    }
+
+    #[test]
+    fn test_merge_chain() {
+        let keys = vec![Some(("elm", "main")); 4];
+
+        // each intersection is ~35m east of the last, ~105m from the first to the last
+        let coords = vec![
+            [-77.0, 38.0],
+            [-77.0004, 38.0],
+            [-77.0008, 38.0],
+            [-77.0012, 38.0],
+        ];
+
+        assert_eq!(merge(&keys, &coords, 50.0), vec![vec![0, 1], vec![2, 3]]);
+        assert_eq!(merge(&keys, &coords, 80.0), vec![vec![0, 1, 2], vec![3]]);
+    }
+
+    #[test]
+    fn test_pg_array() {
+        assert_eq!(pg_array(&[1, 2]), "{1,2}");
+        assert_eq!(pg_array(&[]), "{}");
+    }
+}
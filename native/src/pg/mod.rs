@@ -1,15 +1,22 @@
 use postgres::Connection;
+use serde::de::DeserializeOwned;
 use serde_json::Value;
+use std::collections::VecDeque;
 use std::io::Read;
 use std::iter::Iterator;
-use std::mem;
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
 pub mod address;
 pub mod addresscluster;
+pub mod copy;
 pub mod intersections;
 pub mod network;
 pub mod networkcluster;
+pub mod pipeline;
 pub mod polygon;
+pub mod pool;
+pub mod reach;
 
 pub use self::address::Address;
 pub use self::addresscluster::AddressCluster;
@@ -20,6 +27,11 @@ pub use self::networkcluster::NetworkCluster;
 pub use self::intersections::Intersections;
 pub use self::polygon::Polygon;
 
+pub use self::pipeline::{Pipeline, StageState};
+pub use self::pool::Pool;
+
+pub use self::copy::CopyFormat;
+
 pub trait Table {
     fn create(&self, conn: &Connection);
     fn count(&self, conn: &Connection) -> i64;
@@ -36,94 +48,260 @@ pub trait InputTable {
 }
 
 ///
-/// Relatively limited cursor wrapper that will allow a cursor to be
-/// created that returns a single Serde_Json::Value field
+/// Tables which can also accept input in the PostgreSQL binary COPY
+/// format via a Read trait will implement the BinaryInputTable Property
+///
+pub trait BinaryInputTable {
+    fn input_binary(&self, conn: &Connection, data: impl Read);
+}
+
+///
+/// Used to give each cursor a unique name so that multiple
+/// cursors can be open at once
+///
+static CURSOR_ID: AtomicUsize = AtomicUsize::new(0);
+
+///
+/// Streaming cursor wrapper that decodes the first column of each row
+/// (a JSON value) into any type that implements serde's Deserialize
 ///
-pub struct Cursor {
+/// The cursor owns its connection and declares a uniquely named cursor
+/// within its own transaction, which is closed when the cursor is dropped
+///
+pub struct Cursor<T = Value> {
     pub fetch: i64,
     pub query: String,
-    trans: postgres::transaction::Transaction<'static>,
-    #[allow(dead_code)]
-    conn: Box<postgres::Connection>,
-    cache: Vec<Value>,
+    name: String,
+    conn: Connection,
+    cache: VecDeque<Value>,
+    done: bool,
+    kind: PhantomData<T>,
 }
 
-impl Cursor {
+impl<T: DeserializeOwned> Cursor<T> {
     pub fn new(conn: Connection, query: String) -> Result<Self, String> {
         let fetch = 1000;
 
-        let pg_conn = Box::new(conn);
+        let name = format!("pt2itp_cursor_{}", CURSOR_ID.fetch_add(1, Ordering::SeqCst));
 
-        let trans: postgres::transaction::Transaction = unsafe {
-            mem::transmute(match pg_conn.transaction() {
-                Ok(trans) => trans,
-                Err(err) => {
-                    return Err(err.to_string());
-                }
-            })
-        };
+        if let Err(err) = conn.batch_execute("BEGIN") {
+            return Err(err.to_string());
+        }
 
-        match trans.execute(
+        if let Err(err) = conn.execute(
             format!(
                 r#"
-            DECLARE next_cursor CURSOR FOR {}
+            DECLARE {name} NO SCROLL CURSOR FOR {query}
         "#,
-                &query
+                name = &name,
+                query = &query
             )
             .as_str(),
             &[],
         ) {
-            Err(err) => {
-                return Err(err.to_string());
-            }
-            _ => (),
-        };
+            return Err(err.to_string());
+        }
 
         Ok(Cursor {
             fetch: fetch,
-            conn: pg_conn,
-            trans: trans,
             query: query,
-            cache: Vec::with_capacity(fetch as usize),
+            name: name,
+            conn: conn,
+            cache: VecDeque::with_capacity(fetch as usize),
+            done: false,
+            kind: PhantomData,
         })
     }
-}
 
-impl Iterator for Cursor {
-    type Item = Value;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if !self.cache.is_empty() {
-            return self.cache.pop();
-        }
+    ///
+    /// Builder style setter for the number of rows
+    /// retrieved from the database per FETCH
+    ///
+    pub fn set_fetch(mut self, fetch: i64) -> Self {
+        self.fetch = fetch;
+        self
+    }
 
-        let rows = match self.trans.query(
+    fn more(&mut self) -> Result<(), String> {
+        let rows = match self.conn.query(
             format!(
                 r#"
-            FETCH {} FROM next_cursor
+            FETCH {fetch} FROM {name}
         "#,
-                &self.fetch
+                fetch = &self.fetch,
+                name = &self.name
             )
             .as_str(),
             &[],
         ) {
             Ok(rows) => rows,
-            Err(err) => panic!("Fetch Error: {}", err.to_string()),
+            Err(err) => {
+                return Err(format!("Fetch Error: {}", err.to_string()));
+            }
         };
 
-        // Cursor is finished
-        if rows.is_empty() {
+        let mut cache = VecDeque::with_capacity(rows.len());
+        for row in rows.iter() {
+            match row.get_opt(0) {
+                Some(Ok(value)) => cache.push_back(value),
+                Some(Err(err)) => {
+                    return Err(format!("Row Error: {}", err.to_string()));
+                }
+                None => {
+                    return Err(String::from("Row Error: cursor query returned no columns"));
+                }
+            };
+        }
+
+        self.cache = cache;
+
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for Cursor<T> {
+    type Item = Result<T, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
             return None;
-        } else {
-            self.cache = rows
-                .iter()
-                .map(|row| {
-                    let json: Value = row.get(0);
-                    json
-                })
-                .collect();
-
-            return self.cache.pop();
         }
+
+        if self.cache.is_empty() {
+            if let Err(err) = self.more() {
+                self.done = true;
+                return Some(Err(err));
+            }
+
+            // Cursor is finished
+            if self.cache.is_empty() {
+                self.done = true;
+                return None;
+            }
+        }
+
+        let value = self.cache.pop_front()?;
+
+        Some(match serde_json::from_value::<T>(value) {
+            Ok(value) => Ok(value),
+            Err(err) => Err(format!("Decode Error: {}", err.to_string())),
+        })
+    }
+}
+
+impl<T> Drop for Cursor<T> {
+    fn drop(&mut self) {
+        // Connection errors have either already been surfaced
+        // or the connection is going away, either way ignore them
+        let _ = self
+            .conn
+            .batch_execute(format!("CLOSE {}; COMMIT;", &self.name).as_str());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use postgres::TlsMode;
+    use serde_json::json;
+
+    fn connect() -> Connection {
+        Connection::connect("postgres://postgres@localhost:5432/pt_test", TlsMode::None).unwrap()
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Row {
+        id: i64,
+        name: String,
+    }
+
+    #[test]
+    fn test_cursor_names() {
+        let query = String::from("SELECT to_json(g) FROM generate_series(1, 3) g");
+
+        let mut a: Cursor = Cursor::new(connect(), query.clone()).unwrap();
+        let mut b: Cursor = Cursor::new(connect(), query).unwrap();
+
+        assert_ne!(a.name, b.name);
+
+        // Both cursors are open at the same time & are read independently
+        assert_eq!(a.next().unwrap().unwrap(), json!(1));
+        assert_eq!(b.next().unwrap().unwrap(), json!(1));
+        assert_eq!(a.next().unwrap().unwrap(), json!(2));
+
+        assert_eq!(
+            a.map(|v| v.unwrap()).collect::<Vec<Value>>(),
+            vec![json!(3)]
+        );
+        assert_eq!(
+            b.map(|v| v.unwrap()).collect::<Vec<Value>>(),
+            vec![json!(2), json!(3)]
+        );
+    }
+
+    #[test]
+    fn test_cursor_fetch() {
+        let mut cursor: Cursor<i64> = Cursor::new(
+            connect(),
+            String::from("SELECT to_json(g) FROM generate_series(1, 25) g"),
+        )
+        .unwrap()
+        .set_fetch(10);
+
+        assert_eq!(cursor.fetch, 10);
+
+        // Only a single batch is held at a time
+        assert_eq!(cursor.next().unwrap().unwrap(), 1);
+        assert_eq!(cursor.cache.len(), 9);
+
+        let mut values: Vec<i64> = vec![1];
+        while let Some(value) = cursor.next() {
+            assert!(cursor.cache.len() < 10);
+            values.push(value.unwrap());
+        }
+
+        assert_eq!(values, (1..=25).collect::<Vec<i64>>());
+        assert!(cursor.next().is_none());
+    }
+
+    #[test]
+    fn test_cursor_typed() {
+        let cursor: Cursor<Row> = Cursor::new(
+            connect(),
+            String::from(
+                "SELECT JSON_Build_Object('id', g, 'name', 'Main St ' || g) FROM generate_series(1, 2) g",
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            cursor.map(|row| row.unwrap()).collect::<Vec<Row>>(),
+            vec![
+                Row {
+                    id: 1,
+                    name: String::from("Main St 1")
+                },
+                Row {
+                    id: 2,
+                    name: String::from("Main St 2")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cursor_decode_error() {
+        let mut cursor: Cursor<Row> = Cursor::new(
+            connect(),
+            String::from("SELECT JSON_Build_Object('id', 'one')"),
+        )
+        .unwrap();
+
+        match cursor.next() {
+            Some(Err(err)) => assert!(err.starts_with("Decode Error: ")),
+            other => panic!("Expected a Decode Error, got {:?}", other),
+        };
+
+        assert!(cursor.next().is_none());
     }
 }
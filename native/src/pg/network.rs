@@ -1,4 +1,4 @@
-use super::{InputTable, Table};
+use super::{BinaryInputTable, InputTable, Table};
 use postgres::Connection;
 use std::io::Read;
 
@@ -35,6 +35,7 @@ impl Table for Network {
                 names JSONB,
                 source TEXT,
                 props JSONB,
+                grade JSONB,
                 geom GEOMETRY(MultiLineString, 4326)
             )
         "#,
@@ -110,6 +111,7 @@ impl InputTable for Network {
                 names,
                 source,
                 props,
+                grade,
                 geom
             )
             FROM STDIN
@@ -155,3 +157,27 @@ impl InputTable for Network {
         .unwrap();
     }
 }
+
+impl BinaryInputTable for Network {
+    fn input_binary(&self, conn: &Connection, mut data: impl Read) {
+        let stmt = conn
+            .prepare(
+                r#"
+            COPY network (
+                names,
+                source,
+                props,
+                grade,
+                geom
+            )
+            FROM STDIN
+            WITH (
+                FORMAT BINARY
+            )
+        "#,
+            )
+            .unwrap();
+
+        stmt.copy_in(&[], &mut data).unwrap();
+    }
+}
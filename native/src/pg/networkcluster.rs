@@ -1,53 +1,170 @@
-use super::Table;
+use super::{reach, Table};
+use crate::util::{distance, topology};
 use postgres::Connection;
 
 pub struct NetworkCluster {
     orphan: bool,
+    radius: f64,
+    gap: f64,
+    angle: f64,
 }
 
 impl NetworkCluster {
     pub fn new(orphan: bool) -> Self {
-        NetworkCluster { orphan: orphan }
+        NetworkCluster {
+            orphan: orphan,
+            radius: distance::CLUSTER_RADIUS,
+            gap: topology::CLUSTER_GAP,
+            angle: topology::CLUSTER_ANGLE,
+        }
+    }
+
+    ///
+    /// Builder style cluster gap setter (metres)
+    ///
+    /// Parts of a cluster that do not share an endpoint & are further apart than
+    /// this distance are split into separate clusters
+    ///
+    pub fn set_gap(mut self, gap: f64) -> Self {
+        self.gap = gap;
+        self
+    }
+
+    ///
+    /// Builder style cluster angle setter (degrees)
+    ///
+    /// Parts of a cluster that do not share an endpoint & differ in orientation
+    /// by more than this angle are split into separate clusters
+    ///
+    pub fn set_angle(mut self, angle: f64) -> Self {
+        self.angle = angle;
+        self
+    }
+
+    ///
+    /// Builder style cluster radius setter (metres)
+    ///
+    /// Networks with the same name within this distance of each other are clustered
+    ///
+    pub fn set_radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
     }
 
     ///
     /// Cluster network linestrings
     ///
+    /// Orphan clustering must be run after addresses have been clustered, grouping the
+    /// network clusters that were not matched to an address cluster by name & proximity
+    ///
     pub fn generate(&self, conn: &postgres::Connection) {
         if self.orphan {
+            reach::cluster(
+                conn,
+                r#"
+                SELECT
+                    id,
+                    name,
+                    geom
+                FROM (
+                    SELECT
+                        nc.id,
+                        nc.geom,
+                        (
+                            SELECT
+                                name->>'tokenized'
+                            FROM
+                                jsonb_array_elements(nc.names) AS name
+                            ORDER BY
+                                (name->>'priority')::INT DESC NULLS LAST,
+                                name->>'tokenized'
+                            LIMIT 1
+                        ) AS name
+                    FROM
+                        network_cluster nc
+                    WHERE
+                        nc.address IS NULL
+                        AND nc.geom IS NOT NULL
+                ) named
+                WHERE
+                    name IS NOT NULL
+                    AND name != '[]'
+            "#,
+                &[],
+                self.radius,
+            );
+
             conn.execute(
                 r#"
-                // TODO
+                INSERT INTO network_orphan_cluster (geom, cluster_ids)
+                    SELECT
+                        ST_Multi(ST_CollectionExtract(ST_Collect(nc.geom), 2)),
+                        Array_Agg(nc.id ORDER BY nc.id)
+                    FROM
+                        network_cluster nc
+                        INNER JOIN reach r
+                        ON r.id = nc.id
+                    GROUP BY
+                        r.cluster
+                    ORDER BY
+                        r.cluster;
             "#,
                 &[],
             )
             .unwrap();
+
+            conn.execute(
+                r#"
+                UPDATE network_orphan_cluster o
+                    SET source_ids = ARRAY(
+                        SELECT DISTINCT
+                            UNNEST(nc.source_ids)
+                        FROM
+                            network_cluster nc
+                        WHERE
+                            nc.id = ANY(o.cluster_ids)
+                        ORDER BY 1
+                    );
+            "#,
+                &[],
+            )
+            .unwrap();
+
+            self.names(conn);
         } else {
-            conn.execute(r#"
+            reach::cluster(
+                conn,
+                r#"
+                SELECT
+                    id,
+                    (names->0)::TEXT,
+                    geom
+                FROM
+                    network
+                WHERE
+                    names->0->>'tokenized' != ''
+            "#,
+                &[],
+                self.radius,
+            );
+
+            conn.execute(
+                r#"
                 INSERT INTO network_cluster(geom)
                     SELECT
-                        geom
-                    FROM (
-                        SELECT
-                            names,
-                            ST_Multi(ST_CollectionExtract(netw.geom, 2)) AS geom
-                        FROM (
-                            SELECT
-                                json_array_elements(json_array_elements(JSON_AGG(names)))::JSONB AS names,
-                                unnest(ST_ClusterWithin(geom, 0.005)) AS geom
-                            FROM network
-                            WHERE names->0->>'tokenized' != ''
-                            GROUP BY names->0
-                        ) netw
-                        GROUP BY
-                            geom,
-                            names
-                        ORDER BY
-                            names->>'priority'
-                    ) final
-                    WHERE geom IS NOT NULL
-                    GROUP BY geom;
-            "#, &[]).unwrap();
+                        ST_Multi(ST_CollectionExtract(ST_Collect(netw.geom ORDER BY netw.id), 2)) AS geom
+                    FROM
+                        network netw
+                        INNER JOIN reach r
+                        ON r.id = netw.id
+                    GROUP BY
+                        r.cluster
+                    ORDER BY
+                        r.cluster;
+            "#,
+                &[],
+            )
+            .unwrap();
 
             conn.execute(
                 r#"
@@ -90,37 +207,9 @@ impl NetworkCluster {
             )
             .unwrap();
 
-            conn.execute(
-                r#"
-            UPDATE network_cluster
-                SET names = final.names
-                FROM (
-                    SELECT
-                        joined.id,
-                        json_agg(joined.names) AS names
-                    FROM (
-                        SELECT DISTINCT
-                            nc.id,
-                            jsonb_array_elements(n.names) AS names
-                        FROM
-                            (
-                                SELECT
-                                    id,
-                                    unnest(source_ids) AS sources
-                                FROM
-                                    network_cluster
-                            ) nc,
-                            network n
-                        WHERE n.id = sources
-                    ) joined
-                    GROUP BY joined.id
-                ) final
-                WHERE
-                    final.id = network_cluster.id;
-            "#,
-                &[],
-            )
-            .unwrap();
+            self.split(conn);
+
+            self.names(conn);
 
             conn.execute(
                 r#"
@@ -159,6 +248,150 @@ impl NetworkCluster {
             .unwrap();
         }
     }
+
+    ///
+    /// Clustering merges every like named network within the cluster radius, which
+    /// can join unrelated streets, ie: two "Main Street"s in adjacent villages.
+    ///
+    /// Split each cluster into groups of topologically connected networks, only keeping
+    /// groups together if they are within the cluster gap & of a similar orientation
+    ///
+    fn split(&self, conn: &postgres::Connection) {
+        let clusters = conn
+            .query(
+                r#"
+            SELECT
+                id,
+                source_ids
+            FROM
+                network_cluster
+            WHERE
+                array_length(source_ids, 1) > 1
+        "#,
+                &[],
+            )
+            .unwrap();
+
+        for cluster in clusters.iter() {
+            let id: i32 = cluster.get(0);
+            let source_ids: Vec<i64> = cluster.get(1);
+
+            let rows = conn
+                .query(
+                    r#"
+                SELECT
+                    id,
+                    ST_AsGeoJSON(ST_Force2D(geom))::JSON
+                FROM
+                    network
+                WHERE
+                    id = ANY($1)
+                ORDER BY
+                    id
+            "#,
+                    &[&source_ids],
+                )
+                .unwrap();
+
+            let mut ids: Vec<i64> = Vec::with_capacity(rows.len());
+            let mut items: Vec<Vec<topology::Line>> = Vec::with_capacity(rows.len());
+
+            for row in rows.iter() {
+                let geom: serde_json::Value = row.get(1);
+
+                ids.push(row.get(0));
+                items.push(topology::lines(&geom));
+            }
+
+            let groups = topology::split(&topology::project(&items), self.gap, self.angle);
+
+            if groups.len() < 2 {
+                continue;
+            }
+
+            let trans = conn.transaction().unwrap();
+
+            trans
+                .execute(
+                    r#"
+                DELETE FROM network_cluster WHERE id = $1
+            "#,
+                    &[&id],
+                )
+                .unwrap();
+
+            for group in groups {
+                let group: Vec<i64> = group.into_iter().map(|i| ids[i]).collect();
+
+                trans
+                    .execute(
+                        r#"
+                    INSERT INTO network_cluster (geom, source_ids)
+                        SELECT
+                            ST_Multi(ST_CollectionExtract(ST_Collect(geom), 2)),
+                            $1
+                        FROM
+                            network
+                        WHERE
+                            id = ANY($1)
+                "#,
+                        &[&group],
+                    )
+                    .unwrap();
+            }
+
+            trans.commit().unwrap();
+        }
+    }
+
+    ///
+    /// Set the names of each cluster to the distinct names of the networks it is made up of
+    ///
+    fn names(&self, conn: &postgres::Connection) {
+        conn.execute(
+            format!(
+                r#"
+            UPDATE {table}
+                SET names = final.names
+                FROM (
+                    SELECT
+                        joined.id,
+                        json_agg(joined.names) AS names
+                    FROM (
+                        SELECT DISTINCT
+                            nc.id,
+                            jsonb_array_elements(n.names) AS names
+                        FROM
+                            (
+                                SELECT
+                                    id,
+                                    unnest(source_ids) AS sources
+                                FROM
+                                    {table}
+                            ) nc,
+                            network n
+                        WHERE n.id = sources
+                    ) joined
+                    GROUP BY joined.id
+                ) final
+                WHERE
+                    final.id = {table}.id;
+            "#,
+                table = self.table()
+            )
+            .as_str(),
+            &[],
+        )
+        .unwrap();
+    }
+
+    fn table(&self) -> &'static str {
+        if self.orphan {
+            "network_orphan_cluster"
+        } else {
+            "network_cluster"
+        }
+    }
 }
 
 impl Table for NetworkCluster {
@@ -183,10 +416,12 @@ impl Table for NetworkCluster {
             conn.execute(
                 r#"
                 CREATE UNLOGGED TABLE network_orphan_cluster (
-                    ID SERIAL,
+                    id SERIAL,
                     names JSONB,
-                    geom GEOMETRY(MULTILINESTRINGZ, 4326),
-                    props JSONB
+                    geom GEOMETRY(MULTILINESTRING, 4326),
+                    props JSONB,
+                    source_ids BIGINT[],
+                    cluster_ids BIGINT[]
                 )
             "#,
                 &[],
@@ -218,10 +453,7 @@ impl Table for NetworkCluster {
     }
 
     fn count(&self, conn: &Connection) -> i64 {
-        let table = match self.orphan {
-            true => String::from("network_orphan_cluster"),
-            false => String::from("network_cluster"),
-        };
+        let table = self.table();
 
         match conn.query(
             format!(
@@ -242,17 +474,14 @@ impl Table for NetworkCluster {
     }
 
     fn index(&self, conn: &Connection) {
-        let table = match self.orphan {
-            true => String::from("network_orphan_cluster"),
-            false => String::from("network_cluster"),
-        };
+        let table = self.table();
 
         conn.execute(
             format!(
                 "
             CREATE INDEX IF NOT EXISTS {table}_idx ON {table} (id);
         ",
-                table = &table
+                table = table
             )
             .as_str(),
             &[],
@@ -271,11 +500,17 @@ impl Table for NetworkCluster {
         )
         .unwrap();
 
-        if !self.orphan {
-            conn.execute(format!("
-                CREATE INDEX network_cluster_source_ids_idx ON network_cluster USING GIN (source_ids);
-            ").as_str(), &[]).unwrap();
-        }
+        conn.execute(
+            format!(
+                "
+            CREATE INDEX IF NOT EXISTS {table}_source_ids_idx ON {table} USING GIN (source_ids);
+        ",
+                table = table
+            )
+            .as_str(),
+            &[],
+        )
+        .unwrap();
 
         conn.execute(
             format!(
@@ -0,0 +1,335 @@
+use super::Table;
+use postgres::{Connection, GenericConnection};
+
+///
+/// Ordered list of map stages. When a stage is (re)run from scratch, the
+/// recorded state of every stage after it is discarded as it can no longer be trusted
+///
+pub const STAGES: &[&str] = &[
+    "import_addr",
+    "import_net",
+    "cluster_net",
+    "intersections",
+    "link_addr",
+    "cluster_addr",
+    "cluster_addr_orphan",
+    "cluster_net_orphan",
+];
+
+///
+/// State of a stage as returned by Pipeline::begin
+///
+#[derive(Debug, PartialEq)]
+pub enum StageState {
+    /// The stage has not been run with the given input & parameters
+    Fresh,
+
+    /// The stage was started with the given input & parameters but did not complete
+    Resume,
+
+    /// The stage was completed with the given input & parameters & can be skipped
+    Complete,
+}
+
+///
+/// The pipeline table records which map stages have completed, along with the
+/// input checksum & parameters that they were run with. The pipeline_checkpoint
+/// table records id ranges of a stage that have been committed
+///
+/// Unlike the other tables, these are never dropped so that a failed run can be resumed
+///
+pub struct Pipeline();
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Pipeline()
+    }
+
+    ///
+    /// Remove all recorded stage state
+    ///
+    pub fn reset(&self, conn: &impl GenericConnection) {
+        conn.batch_execute(
+            r#"
+            DELETE FROM pipeline_checkpoint;
+            DELETE FROM pipeline;
+        "#,
+        )
+        .unwrap();
+    }
+
+    ///
+    /// Determine if a stage can be skipped or resumed. If resume is false or the stage was
+    /// last run with a different checksum or params, the stage and all stages after it
+    /// are reset and StageState::Fresh is returned
+    ///
+    pub fn begin(
+        &self,
+        conn: &impl GenericConnection,
+        stage: &str,
+        checksum: Option<&str>,
+        params: &serde_json::Value,
+        resume: bool,
+    ) -> StageState {
+        if resume {
+            let rows = conn
+                .query(
+                    r#"
+                SELECT
+                    completed
+                FROM
+                    pipeline
+                WHERE
+                    stage = $1
+                    AND checksum IS NOT DISTINCT FROM $2
+                    AND params = $3::JSONB
+            "#,
+                    &[&stage, &checksum, &params],
+                )
+                .unwrap();
+
+            if !rows.is_empty() {
+                let completed: bool = rows.get(0).get(0);
+
+                if completed {
+                    return StageState::Complete;
+                } else {
+                    return StageState::Resume;
+                }
+            }
+        }
+
+        let trans = conn.transaction().unwrap();
+
+        for reset in self.downstream(stage) {
+            trans
+                .execute(
+                    r#"
+                DELETE FROM pipeline_checkpoint WHERE stage = $1
+            "#,
+                    &[&reset],
+                )
+                .unwrap();
+
+            trans
+                .execute(
+                    r#"
+                DELETE FROM pipeline WHERE stage = $1
+            "#,
+                    &[&reset],
+                )
+                .unwrap();
+        }
+
+        trans
+            .execute(
+                r#"
+            INSERT INTO pipeline (stage, checksum, params, completed, updated)
+                VALUES ($1, $2, $3::JSONB, false, NOW())
+        "#,
+                &[&stage, &checksum, &params],
+            )
+            .unwrap();
+
+        trans.commit().unwrap();
+
+        StageState::Fresh
+    }
+
+    ///
+    /// Mark a stage as successfully completed
+    ///
+    pub fn complete(&self, conn: &impl GenericConnection, stage: &str) {
+        conn.execute(
+            r#"
+            UPDATE pipeline
+                SET
+                    completed = true,
+                    updated = NOW()
+                WHERE
+                    stage = $1
+        "#,
+            &[&stage],
+        )
+        .unwrap();
+    }
+
+    ///
+    /// Record that the inclusive id range min => max of a stage has been committed.
+    /// This should be called in the same transaction as the work it records
+    ///
+    pub fn checkpoint(&self, conn: &impl GenericConnection, stage: &str, min: i64, max: i64) {
+        conn.execute(
+            r#"
+            INSERT INTO pipeline_checkpoint (stage, min, max)
+                VALUES ($1, $2, $3)
+        "#,
+            &[&stage, &min, &max],
+        )
+        .unwrap();
+    }
+
+    ///
+    /// Return all committed id ranges of a given stage
+    ///
+    pub fn checkpoints(&self, conn: &impl GenericConnection, stage: &str) -> Checkpoints {
+        let rows = conn
+            .query(
+                r#"
+            SELECT
+                min,
+                max
+            FROM
+                pipeline_checkpoint
+            WHERE
+                stage = $1
+        "#,
+                &[&stage],
+            )
+            .unwrap();
+
+        Checkpoints {
+            ranges: rows.iter().map(|row| (row.get(0), row.get(1))).collect(),
+        }
+    }
+
+    ///
+    /// The given stage along with every stage that comes after it
+    ///
+    fn downstream(&self, stage: &str) -> Vec<String> {
+        match STAGES.iter().position(|s| *s == stage) {
+            Some(pos) => STAGES[pos..].iter().map(|s| s.to_string()).collect(),
+            None => vec![stage.to_string()],
+        }
+    }
+}
+
+impl Table for Pipeline {
+    fn create(&self, conn: &Connection) {
+        conn.batch_execute(
+            r#"
+            CREATE TABLE IF NOT EXISTS pipeline (
+                stage TEXT PRIMARY KEY,
+                checksum TEXT,
+                params JSONB,
+                completed BOOLEAN NOT NULL DEFAULT false,
+                updated TIMESTAMP
+            );
+
+            CREATE TABLE IF NOT EXISTS pipeline_checkpoint (
+                stage TEXT NOT NULL,
+                min BIGINT NOT NULL,
+                max BIGINT NOT NULL
+            );
+        "#,
+        )
+        .unwrap();
+    }
+
+    fn count(&self, conn: &Connection) -> i64 {
+        match conn.query(
+            r#"
+            SELECT count(*) FROM pipeline
+        "#,
+            &[],
+        ) {
+            Ok(res) => {
+                let cnt: i64 = res.get(0).get(0);
+                cnt
+            }
+            _ => 0,
+        }
+    }
+
+    fn index(&self, conn: &Connection) {
+        conn.execute(
+            r#"
+            CREATE INDEX IF NOT EXISTS pipeline_checkpoint_stage_idx ON pipeline_checkpoint (stage);
+        "#,
+            &[],
+        )
+        .unwrap();
+    }
+}
+
+///
+/// Committed id ranges of a stage
+///
+pub struct Checkpoints {
+    ranges: Vec<(i64, i64)>,
+}
+
+impl Checkpoints {
+    ///
+    /// Is the inclusive id range min => max entirely covered by committed ranges
+    ///
+    pub fn covers(&self, min: i64, max: i64) -> bool {
+        let mut ranges = self.ranges.clone();
+        ranges.sort();
+
+        let mut next = min;
+        for (rmin, rmax) in ranges {
+            if rmin > next {
+                break;
+            }
+
+            if rmax >= next {
+                next = rmax + 1;
+            }
+
+            if next > max {
+                return true;
+            }
+        }
+
+        false
+    }
+
+    pub fn len(&self) -> usize {
+        self.ranges.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoints_covers() {
+        let checkpoints = Checkpoints {
+            ranges: vec![(10, 19), (0, 9), (25, 30)],
+        };
+
+        assert!(checkpoints.covers(0, 9));
+        assert!(checkpoints.covers(0, 19));
+        assert!(checkpoints.covers(5, 15));
+        assert!(checkpoints.covers(26, 30));
+        assert!(!checkpoints.covers(15, 25));
+        assert!(!checkpoints.covers(20, 24));
+        assert!(!checkpoints.covers(31, 40));
+
+        let empty = Checkpoints { ranges: Vec::new() };
+        assert!(empty.is_empty());
+        assert!(!empty.covers(0, 0));
+    }
+
+    #[test]
+    fn test_downstream() {
+        let pipeline = Pipeline::new();
+
+        assert_eq!(
+            pipeline.downstream("link_addr"),
+            vec![
+                "link_addr",
+                "cluster_addr",
+                "cluster_addr_orphan",
+                "cluster_net_orphan"
+            ]
+        );
+        assert_eq!(pipeline.downstream("unknown"), vec!["unknown"]);
+    }
+}
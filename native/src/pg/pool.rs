@@ -0,0 +1,99 @@
+use crossbeam::channel::{Receiver, Sender};
+use postgres::{Connection, TlsMode};
+use std::ops::Deref;
+
+///
+/// Minimal fixed size connection pool. Worker threads check a connection out
+/// per unit of work so that the number of connections a mode opens is bounded,
+/// regardless of how many workers it starts
+///
+/// Each slot of the pool holds either an open connection, or None if its
+/// connection had to be discarded & a new one should be opened on checkout
+///
+#[derive(Clone)]
+pub struct Pool {
+    db: String,
+    tx: Sender<Option<Connection>>,
+    rx: Receiver<Option<Connection>>,
+}
+
+impl Pool {
+    pub fn new(db: &str, size: usize) -> Result<Self, String> {
+        let size = size.max(1);
+        let (tx, rx) = crossbeam::channel::bounded(size);
+
+        for _ in 0..size {
+            tx.send(Some(connect(db)?)).unwrap();
+        }
+
+        Ok(Pool {
+            db: String::from(db),
+            tx: tx,
+            rx: rx,
+        })
+    }
+
+    ///
+    /// Check out a connection, blocking until one is available
+    ///
+    pub fn get(&self) -> PooledConnection {
+        let conn = match self.rx.recv().unwrap() {
+            Some(conn) => conn,
+            None => match connect(&self.db) {
+                Ok(conn) => conn,
+                Err(err) => {
+                    // Keep the slot so that other workers aren't left waiting on it
+                    self.tx.send(None).ok();
+
+                    println!("{}", err);
+                    panic!("{}", err);
+                }
+            },
+        };
+
+        PooledConnection {
+            conn: Some(conn),
+            tx: self.tx.clone(),
+        }
+    }
+}
+
+fn connect(db: &str) -> Result<Connection, String> {
+    match Connection::connect(
+        format!("postgres://postgres@localhost:5432/{}", db).as_str(),
+        TlsMode::None,
+    ) {
+        Ok(conn) => Ok(conn),
+        Err(err) => Err(format!("Connection Error: {}", err.to_string())),
+    }
+}
+
+///
+/// Connection that is returned to its Pool when dropped
+///
+pub struct PooledConnection {
+    conn: Option<Connection>,
+    tx: Sender<Option<Connection>>,
+}
+
+impl Deref for PooledConnection {
+    type Target = Connection;
+
+    fn deref(&self) -> &Connection {
+        self.conn.as_ref().unwrap()
+    }
+}
+
+impl Drop for PooledConnection {
+    fn drop(&mut self) {
+        if let Some(conn) = self.conn.take() {
+            // A connection left mid transaction or desynchronized (ie: its thread panicked)
+            // can't be reused, close it & free its slot for a replacement
+            if conn.is_active() || conn.is_desynchronized() {
+                self.tx.send(None).ok();
+            } else {
+                self.tx.send(Some(conn)).ok();
+            }
+        }
+    }
+}
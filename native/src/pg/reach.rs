@@ -0,0 +1,128 @@
+use crate::util::distance;
+use crate::util::topology::DisjointSet;
+use postgres::types::ToSql;
+use postgres::Connection;
+use std::collections::HashMap;
+
+///
+/// Cluster the features returned by the source query, of the form SELECT id, key, geom,
+/// into groups of features with the same key that are connected by gaps of at most radius
+/// metres, ie: ST_ClusterDBSCAN(minpoints := 1) on the true distance between features
+///
+/// Candidate pairs are found on the raw geometries with a latitude scaled radius in
+/// degrees, which can use the geometry index, and are then refined on geography,
+/// in the same fashion as map::link_process
+///
+/// The cluster of each feature is written to the temporary reach (id, cluster) table,
+/// which lasts until the next call on the same connection. Clusters are identified by
+/// the lowest feature id that they contain
+///
+pub fn cluster(conn: &Connection, source: &str, params: &[&dyn ToSql], radius: f64) {
+    conn.batch_execute(
+        r#"
+        DROP TABLE IF EXISTS reach_source;
+        DROP TABLE IF EXISTS reach;
+
+        CREATE TEMP TABLE reach_source (
+            id BIGINT,
+            key TEXT,
+            geom GEOMETRY
+        );
+
+        CREATE TEMP TABLE reach (
+            id BIGINT,
+            cluster BIGINT
+        );
+    "#,
+    )
+    .unwrap();
+
+    conn.execute(
+        format!(
+            r#"
+        INSERT INTO reach_source (id, key, geom) {source}
+    "#,
+            source = source
+        )
+        .as_str(),
+        params,
+    )
+    .unwrap();
+
+    conn.batch_execute(
+        r#"
+        CREATE INDEX reach_source_gix ON reach_source USING GIST (geom);
+        ANALYZE reach_source;
+    "#,
+    )
+    .unwrap();
+
+    let ids: Vec<i64> = conn
+        .query(
+            r#"
+        SELECT id FROM reach_source ORDER BY id
+    "#,
+            &[],
+        )
+        .unwrap()
+        .iter()
+        .map(|row| row.get(0))
+        .collect();
+
+    let index: HashMap<i64, usize> = ids.iter().enumerate().map(|(i, id)| (*id, i)).collect();
+
+    let pairs = conn
+        .query(
+            format!(
+                r#"
+            SELECT
+                a.id,
+                b.id
+            FROM
+                reach_source a
+                INNER JOIN reach_source b
+                ON a.key = b.key
+                AND a.id < b.id
+                AND ST_DWithin(a.geom, b.geom, {degrees})
+                AND ST_DWithin(ST_Force2D(a.geom)::GEOGRAPHY, ST_Force2D(b.geom)::GEOGRAPHY, $1)
+        "#,
+                degrees = distance::sql_extent_degrees("$1", "a.geom")
+            )
+            .as_str(),
+            &[&radius],
+        )
+        .unwrap();
+
+    // Ids are sorted & the lower index of a union becomes its root,
+    // so the root of each set is its lowest id
+    let mut sets = DisjointSet::new(ids.len());
+    for pair in pairs.iter() {
+        let a: i64 = pair.get(0);
+        let b: i64 = pair.get(1);
+
+        sets.union(index[&a], index[&b]);
+    }
+
+    let clusters: Vec<i64> = (0..ids.len()).map(|i| ids[sets.find(i)]).collect();
+
+    conn.execute(
+        r#"
+        INSERT INTO reach (id, cluster)
+            SELECT
+                id,
+                cluster
+            FROM
+                unnest($1::BIGINT[], $2::BIGINT[]) AS r(id, cluster)
+    "#,
+        &[&ids, &clusters],
+    )
+    .unwrap();
+
+    conn.batch_execute(
+        r#"
+        CREATE INDEX reach_idx ON reach (id);
+        DROP TABLE reach_source;
+    "#,
+    )
+    .unwrap();
+}
@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 mod count;
 mod explode;
-mod tree;
+pub(crate) mod tree;
 
 #[derive(Serialize, Deserialize, Debug)]
 struct StatsArgs {
@@ -43,6 +43,21 @@ pub fn create(
     bound: Option<String>,
     boundmap: &mut HashMap<String, StatsBound>,
 ) -> rstar::RTree<Rect> {
+    let tree = load(bound);
+
+    for rect in tree.iter() {
+        boundmap.insert(rect.name.clone(), StatsBound::new());
+    }
+
+    println!("ok - {} bounds loaded into rtree", tree.size());
+
+    tree
+}
+
+///
+/// Load named (Multi)Polygon features into an rtree of their bounding boxes
+///
+pub fn load(bound: Option<String>) -> rstar::RTree<Rect> {
     let bounds_stream = GeoStream::new(bound);
 
     let mut tree_contents = Vec::new();
@@ -69,14 +84,10 @@ pub fn create(
             _ => panic!("Bound must be (Multi)Polygon Features"),
         };
 
-        boundmap.insert(name.clone(), StatsBound::new());
-
         let rect = Rect::new(geom, name);
 
         tree_contents.push(rect);
     }
 
-    println!("ok - {} bounds loaded into rtree", tree_contents.len());
-
     rstar::RTree::bulk_load(tree_contents)
 }
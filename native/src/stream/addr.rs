@@ -3,6 +3,7 @@ use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::iter::Iterator;
 
+use crate::pg::copy::{self, CopyFormat};
 use crate::{stream::geo::GeoStream, Address, Context};
 
 pub struct AddrStream {
@@ -10,6 +11,9 @@ pub struct AddrStream {
     input: GeoStream,
     buffer: Option<Vec<u8>>, //Used by Read impl for storing partial features
     errors: Option<BufWriter<File>>,
+    format: CopyFormat,
+    header: bool,  //Has the binary COPY header been written
+    trailer: bool, //Has the binary COPY trailer been written
 }
 
 impl AddrStream {
@@ -22,8 +26,46 @@ impl AddrStream {
                 None => None,
                 Some(path) => Some(BufWriter::new(File::create(path).unwrap())),
             },
+            format: CopyFormat::Text,
+            header: false,
+            trailer: false,
         }
     }
+
+    ///
+    /// Builder style setter for the format the Read impl will output
+    ///
+    pub fn set_format(mut self, format: CopyFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    ///
+    /// Bytes of the next feature in the stream, in the stream's COPY format
+    ///
+    fn next_bytes(&mut self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+
+        if self.format == CopyFormat::Binary && !self.header {
+            self.header = true;
+            bytes.extend_from_slice(copy::HEADER);
+        }
+
+        match self.next() {
+            Some(feat) => match self.format {
+                CopyFormat::Text => bytes.append(&mut feat.to_tsv().into_bytes()),
+                CopyFormat::Binary => bytes.append(&mut feat.to_binary()),
+            },
+            None => {
+                if self.format == CopyFormat::Binary && !self.trailer {
+                    self.trailer = true;
+                    bytes.extend_from_slice(copy::TRAILER);
+                }
+            }
+        };
+
+        bytes
+    }
 }
 
 impl std::io::Read for AddrStream {
@@ -36,12 +78,7 @@ impl std::io::Read for AddrStream {
             if self.buffer.is_some() {
                 write = self.buffer.take().unwrap();
             } else {
-                let feat = match self.next() {
-                    Some(feat) => feat.to_tsv(),
-                    None => String::from(""),
-                };
-
-                let mut bytes = feat.into_bytes();
+                let mut bytes = self.next_bytes();
                 if bytes.len() == 0 {
                     end = true;
                 } else {
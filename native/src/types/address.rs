@@ -2,10 +2,13 @@ use postgis::ewkb::AsEwkbPoint;
 use postgis::ewkb::EwkbWrite;
 use regex::{Regex, RegexSet};
 
-use crate::{hecate, types::name::InputName, Context, Name, Names, Source};
+use crate::{
+    hecate, pg::copy::BinaryRow, types::name::InputName, types::HouseNumber, Context, Name, Names,
+    Source,
+};
 
 /// A representation of a single Address
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Address {
     /// An optional identifier for the address
     pub id: Option<i64>,
@@ -271,6 +274,13 @@ impl Address {
         Ok(())
     }
 
+    ///
+    /// The standardized number split into a numeric key & suffix for fuzzy matching
+    ///
+    pub fn house_number(&self) -> HouseNumber {
+        HouseNumber::new(&self.number)
+    }
+
     ///
     ///Return a PG Copyable String of the feature
     ///
@@ -298,6 +308,32 @@ impl Address {
         )
     }
 
+    ///
+    ///Return a PG binary COPY tuple of the feature
+    ///
+    ///id, version, names, number, source, output, interpolate, props, geom
+    ///
+    pub fn to_binary(self) -> Vec<u8> {
+        let mut geom: Vec<u8> = Vec::new();
+        postgis::ewkb::Point::new(self.geom[0], self.geom[1], Some(4326))
+            .as_ewkb()
+            .write_ewkb(&mut geom)
+            .unwrap();
+
+        let mut row = BinaryRow::new(9);
+        row.int8(self.id);
+        row.int8(Some(self.version));
+        row.jsonb(&serde_json::to_value(&self.names.names).unwrap_or(serde_json::Value::Null));
+        row.text(&self.number);
+        row.text(&self.source);
+        row.bool(self.output);
+        row.bool(self.interpolate);
+        row.jsonb(&serde_json::value::Value::from(self.props));
+        row.bytes(&geom);
+
+        row.finish()
+    }
+
     ///
     ///Insert an address into a given database
     ///
@@ -576,6 +612,34 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_address_binary() {
+        let feat: geojson::GeoJson = String::from(r#"{"id":80614173,"type":"Feature","version":3,"geometry":{"type":"Point","coordinates":[-84.7395102,39.1618162]},"properties":{"number":"726","source":"hamilton","street":"Rosewynne Ct"}}"#).parse().unwrap();
+
+        let context = Context::new(
+            String::from("us"),
+            Some(String::from("mn")),
+            Tokens::generate(vec![String::from("en")]),
+        );
+
+        let binary = Address::new(feat, &context).unwrap().to_binary();
+
+        // field count, followed by the id & version as int8
+        assert_eq!(
+            &binary[0..26],
+            &[0, 9, 0, 0, 0, 8, 0, 0, 0, 0, 4, 206, 19, 29, 0, 0, 0, 8, 0, 0, 0, 0, 0, 0, 0, 3]
+        );
+
+        // geometry is sent as raw EWKB
+        assert_eq!(
+            &binary[binary.len() - 29..],
+            &[
+                0, 0, 0, 25, 1, 1, 0, 0, 32, 230, 16, 0, 0, 189, 3, 151, 34, 84, 47, 85, 192, 67,
+                123, 171, 100, 182, 148, 67, 64
+            ]
+        );
+    }
+
     #[test]
     fn test_address_simple_geom_fail() {
         // US street value is has a `/`
@@ -1,4 +1,4 @@
-use crate::{text, Context, Names, Source};
+use crate::{pg::copy::BinaryRow, text, Context, Names, Source};
 use postgis::ewkb::EwkbWrite;
 
 #[derive(Debug)]
@@ -18,6 +18,9 @@ pub struct Network {
     /// JSON representation of properties
     pub props: serde_json::Map<String, serde_json::Value>,
 
+    /// Vertical position of the network, derived from its properties
+    pub grade: Grade,
+
     /// Simple representation of MultiLineString
     pub geom: Vec<geojson::LineStringType>,
 }
@@ -85,6 +88,7 @@ impl Network {
             },
             names: names,
             source: source,
+            grade: Grade::from_props(&props),
             props: props,
             geom: geom,
         };
@@ -106,15 +110,61 @@ impl Network {
 
     ///
     /// Return a PG Copyable String of the feature
-    /// names, source, props, geom
+    /// names, source, props, grade, geom
     ///
     pub fn to_tsv(self) -> String {
+        let twkb = self.to_twkb();
+
+        let geom = postgis::ewkb::EwkbMultiLineString {
+            geom: &twkb,
+            srid: Some(4326),
+            point_type: postgis::ewkb::PointType::Point,
+        }
+        .to_hex_ewkb();
+
+        format!(
+            "{names}\t{source}\t{props}\t{grade}\t{geom}\n",
+            names = serde_json::to_string(&self.names.names).unwrap_or(String::from("")),
+            source = self.source,
+            props = serde_json::value::Value::from(self.props),
+            grade = serde_json::to_string(&self.grade).unwrap_or(String::from("")),
+            geom = geom
+        )
+    }
+
+    ///
+    /// Return a PG binary COPY tuple of the feature
+    /// names, source, props, grade, geom
+    ///
+    pub fn to_binary(self) -> Vec<u8> {
+        let twkb = self.to_twkb();
+
+        let mut geom: Vec<u8> = Vec::new();
+        postgis::ewkb::EwkbMultiLineString {
+            geom: &twkb,
+            srid: Some(4326),
+            point_type: postgis::ewkb::PointType::Point,
+        }
+        .write_ewkb(&mut geom)
+        .unwrap();
+
+        let mut row = BinaryRow::new(5);
+        row.jsonb(&serde_json::to_value(&self.names.names).unwrap_or(serde_json::Value::Null));
+        row.text(&self.source);
+        row.jsonb(&serde_json::value::Value::from(self.props));
+        row.jsonb(&serde_json::to_value(&self.grade).unwrap_or(serde_json::Value::Null));
+        row.bytes(&geom);
+
+        row.finish()
+    }
+
+    fn to_twkb(&self) -> postgis::twkb::MultiLineString {
         let mut twkb = postgis::twkb::MultiLineString {
             lines: Vec::with_capacity(self.geom.len()),
             ids: None,
         };
 
-        for ln in self.geom {
+        for ln in self.geom.iter() {
             let mut line = postgis::twkb::LineString {
                 points: Vec::with_capacity(ln.len()),
             };
@@ -127,20 +177,85 @@ impl Network {
             twkb.lines.push(line);
         }
 
-        let geom = postgis::ewkb::EwkbMultiLineString {
-            geom: &twkb,
-            srid: Some(4326),
-            point_type: postgis::ewkb::PointType::Point,
+        twkb
+    }
+}
+
+///
+/// Normalized grade separation of a network, used to avoid generating intersections
+/// between networks that cross without connecting, ie: bridges, tunnels & overpasses
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct Grade {
+    /// Relative vertical layer, 0 being at ground level
+    pub layer: i64,
+
+    pub bridge: bool,
+
+    pub tunnel: bool,
+
+    /// Limited access highways are only reachable via their own ramps
+    pub limited: bool,
+}
+
+impl Grade {
+    ///
+    /// Derive the grade from OSM style layer, bridge, tunnel & highway properties
+    ///
+    /// Rendering ranks such as the osm2pgsql z_order are not vertical layers, they
+    /// differ by highway class & would separate roads that cross at grade
+    ///
+    pub fn from_props(props: &serde_json::Map<String, serde_json::Value>) -> Self {
+        let bridge = is_truthy(props.get("bridge"));
+        let tunnel = is_truthy(props.get("tunnel"));
+
+        let layer = as_layer(props.get("layer")).unwrap_or(if bridge {
+            1
+        } else if tunnel {
+            -1
+        } else {
+            0
+        });
+
+        let limited = is_truthy(props.get("limited_access"))
+            || is_truthy(props.get("controlled_access"))
+            || match props.get("highway") {
+                Some(serde_json::Value::String(highway)) => highway == "motorway",
+                _ => false,
+            };
+
+        Grade {
+            layer: layer,
+            bridge: bridge,
+            tunnel: tunnel,
+            limited: limited,
         }
-        .to_hex_ewkb();
+    }
+}
 
-        format!(
-            "{names}\t{source}\t{props}\t{geom}\n",
-            names = serde_json::to_string(&self.names.names).unwrap_or(String::from("")),
-            source = self.source,
-            props = serde_json::value::Value::from(self.props),
-            geom = geom
-        )
+fn as_layer(value: Option<&serde_json::Value>) -> Option<i64> {
+    let layer = match value? {
+        serde_json::Value::Number(num) => num.as_f64()?,
+        serde_json::Value::String(string) => string.trim().parse::<f64>().ok()?,
+        _ => return None,
+    };
+
+    if layer.is_finite() {
+        Some(layer.round() as i64)
+    } else {
+        None
+    }
+}
+
+fn is_truthy(value: Option<&serde_json::Value>) -> bool {
+    match value {
+        Some(serde_json::Value::Bool(b)) => *b,
+        Some(serde_json::Value::Number(num)) => num.as_f64().map_or(false, |num| num != 0.0),
+        Some(serde_json::Value::String(string)) => match string.trim().to_lowercase().as_str() {
+            "" | "no" | "false" | "0" => false,
+            _ => true,
+        },
+        _ => false,
     }
 }
 
@@ -175,7 +290,105 @@ mod tests {
 
         let net = Network::new(feat, &context).unwrap();
 
-        assert_eq!(net.to_tsv(), "[{\"display\":\"Poremba Court Southwest\",\"priority\":0,\"source\":\"Network\",\"tokenized\":[{\"token\":\"poremba\",\"token_type\":null},{\"token\":\"court\",\"token_type\":null},{\"token\":\"southwest\",\"token_type\":null}],\"freq\":1}]\t\t{\"id\":6052094,\"street\":[{\"display\":\"Poremba Court Southwest\",\"priority\":0}]}\t0105000020E610000001000000010200000003000000FCA5457D924053C09128B4ACFB6D4340F52F49658A4053C0CBA145B6F36D434009826CFE844053C0F7D676C9EE6D4340\n");
+        assert_eq!(net.to_tsv(), "[{\"display\":\"Poremba Court Southwest\",\"priority\":0,\"source\":\"Network\",\"tokenized\":[{\"token\":\"poremba\",\"token_type\":null},{\"token\":\"court\",\"token_type\":null},{\"token\":\"southwest\",\"token_type\":null}],\"freq\":1}]\t\t{\"id\":6052094,\"street\":[{\"display\":\"Poremba Court Southwest\",\"priority\":0}]}\t{\"layer\":0,\"bridge\":false,\"tunnel\":false,\"limited\":false}\t0105000020E610000001000000010200000003000000FCA5457D924053C09128B4ACFB6D4340F52F49658A4053C0CBA145B6F36D434009826CFE844053C0F7D676C9EE6D4340\n");
+    }
+
+    #[test]
+    fn test_network_binary() {
+        let feat: geojson::GeoJson = String::from(
+            r#"{
+            "type":"Feature",
+            "properties":{
+                "street":[{"display":"Poremba Court Southwest","priority":0}]},
+            "geometry":{
+                "type":"LineString",
+                "coordinates":[[-77.008941,38.859243],[-77.008447,38.859]]
+            }
+        }"#,
+        )
+        .parse()
+        .unwrap();
+
+        let context = Context::new(
+            String::from("us"),
+            Some(String::from("dc")),
+            Tokens::new(HashMap::new(), HashMap::new(), HashMap::new()),
+        );
+
+        let binary = Network::new(feat, &context).unwrap().to_binary();
+
+        // field count, followed by the names jsonb version byte
+        assert_eq!(&binary[0..2], &[0, 5]);
+        assert_eq!(binary[6], 1);
+
+        // geometry is sent as raw EWKB
+        assert_eq!(
+            &binary[binary.len() - 58..binary.len() - 54],
+            &[0, 0, 0, 54]
+        );
+        assert_eq!(
+            &binary[binary.len() - 54..binary.len() - 45],
+            &[1, 5, 0, 0, 32, 230, 16, 0, 0]
+        );
+    }
+
+    #[test]
+    fn test_network_grade() {
+        let grade = |props: serde_json::Value| match props {
+            serde_json::Value::Object(props) => Grade::from_props(&props),
+            _ => panic!("props must be an object"),
+        };
+
+        assert_eq!(grade(serde_json::json!({})), Grade::default());
+
+        assert_eq!(
+            grade(serde_json::json!({ "bridge": "yes" })),
+            Grade {
+                layer: 1,
+                bridge: true,
+                tunnel: false,
+                limited: false,
+            }
+        );
+
+        assert_eq!(
+            grade(serde_json::json!({ "tunnel": "culvert", "layer": "-2" })),
+            Grade {
+                layer: -2,
+                bridge: false,
+                tunnel: true,
+                limited: false,
+            }
+        );
+
+        assert_eq!(
+            grade(serde_json::json!({ "bridge": "no", "z_order": 3 })),
+            Grade {
+                layer: 0,
+                bridge: false,
+                tunnel: false,
+                limited: false,
+            }
+        );
+
+        assert_eq!(
+            grade(serde_json::json!({ "highway": "motorway", "bridge": true, "layer": "bogus" })),
+            Grade {
+                layer: 1,
+                bridge: true,
+                tunnel: false,
+                limited: true,
+            }
+        );
+
+        assert_eq!(
+            grade(serde_json::json!({ "highway": "motorway_link" })).limited,
+            false
+        );
+        assert_eq!(
+            grade(serde_json::json!({ "controlled_access": 1 })).limited,
+            true
+        );
     }
 
     #[test]
@@ -0,0 +1,154 @@
+use regex::Regex;
+
+///
+/// A house number split into a numeric key & letter suffix, so that the same address
+/// written in different ways, ie: '12A', '12 a', '012a' can be found & compared
+///
+/// Numbers are expected to have been standardized by Address::std
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct HouseNumber {
+    /// Numeric part without leading zeros, or the entire number if it isn't a simple
+    /// numeric number, such as grid (12n34) or slash (123/89) numbers
+    pub key: String,
+
+    /// Letter suffix, ie: the 'a' of '12a'
+    pub suffix: String,
+
+    /// End of a number range, ie: the 14 of '12-14'
+    pub end: Option<u64>,
+}
+
+impl HouseNumber {
+    pub fn new(number: &str) -> Self {
+        lazy_static! {
+            static ref NUMBER: Regex =
+                Regex::new(r"^0*(?P<num>\d+)(-0*(?P<end>\d+))?-?(?P<suffix>[a-z]?)$").unwrap();
+        }
+
+        let number: String = number
+            .to_lowercase()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+
+        let caps = match NUMBER.captures(&number) {
+            Some(caps) => caps,
+            None => {
+                return HouseNumber {
+                    key: number,
+                    suffix: String::from(""),
+                    end: None,
+                };
+            }
+        };
+
+        let num = &caps["num"];
+        let suffix = String::from(&caps["suffix"]);
+
+        match caps.name("end") {
+            None => HouseNumber {
+                key: String::from(num),
+                suffix: suffix,
+                end: None,
+            },
+            Some(end) => {
+                let start: u64 = num.parse().unwrap_or(0);
+                let end: u64 = end.as_str().parse().unwrap_or(0);
+
+                // Only increasing numbers are a range, hyphenated numbers such as those
+                // in Queens (123-45) are distinct addresses & must match exactly
+                if end > start {
+                    HouseNumber {
+                        key: String::from(num),
+                        suffix: suffix,
+                        end: Some(end),
+                    }
+                } else {
+                    HouseNumber {
+                        key: number,
+                        suffix: String::from(""),
+                        end: None,
+                    }
+                }
+            }
+        }
+    }
+
+    ///
+    /// Is this number the same address as another.
+    ///
+    /// Numbers must have the same key & suffix, a different suffix (12a vs 12b) or a missing
+    /// suffix (12 vs 12a) is a different address. A range is the same address as the
+    /// number it starts with (12-14 vs 12), but not another range (12-14 vs 12-16)
+    ///
+    pub fn same(&self, other: &HouseNumber) -> bool {
+        if self.key != other.key || self.suffix != other.suffix {
+            return false;
+        }
+
+        match (self.end, other.end) {
+            (Some(end), Some(other_end)) => end == other_end,
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_house_number() {
+        assert_eq!(
+            HouseNumber::new("012 A"),
+            HouseNumber {
+                key: String::from("12"),
+                suffix: String::from("a"),
+                end: None,
+            }
+        );
+
+        assert_eq!(
+            HouseNumber::new("12-14"),
+            HouseNumber {
+                key: String::from("12"),
+                suffix: String::from(""),
+                end: Some(14),
+            }
+        );
+
+        assert_eq!(
+            HouseNumber::new("12-b"),
+            HouseNumber {
+                key: String::from("12"),
+                suffix: String::from("b"),
+                end: None,
+            }
+        );
+
+        assert_eq!(HouseNumber::new("0").key, String::from("0"));
+        assert_eq!(HouseNumber::new("123-45").key, String::from("123-45"));
+        assert_eq!(HouseNumber::new("12n34").key, String::from("12n34"));
+        assert_eq!(HouseNumber::new("123/89").key, String::from("123/89"));
+    }
+
+    #[test]
+    fn test_house_number_same() {
+        let same = |a: &str, b: &str| HouseNumber::new(a).same(&HouseNumber::new(b));
+
+        assert!(same("12a", "12 A"));
+        assert!(same("012", "12"));
+        assert!(same("12-14", "12"));
+        assert!(same("12", "12-14"));
+        assert!(same("12-14", "12-14"));
+        assert!(same("123-45", "123-45"));
+
+        assert!(!same("12a", "12b"));
+        assert!(!same("12", "12a"));
+        assert!(!same("12-14", "14"));
+        assert!(!same("12-14", "12-16"));
+        assert!(!same("123-45", "123"));
+        assert!(!same("12", "13"));
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs::File;
+use std::io::{BufReader, Read};
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+///
+/// Streaming FNV-1a 64 bit hash. Unlike std's DefaultHasher the output is
+/// stable across builds, so it can be persisted & compared between runs
+///
+pub struct Checksum {
+    hash: u64,
+}
+
+impl Checksum {
+    pub fn new() -> Self {
+        Checksum { hash: FNV_OFFSET }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.hash ^= u64::from(*byte);
+            self.hash = self.hash.wrapping_mul(FNV_PRIME);
+        }
+    }
+
+    pub fn finish(&self) -> String {
+        format!("{:016x}", self.hash)
+    }
+}
+
+///
+/// Checksum the contents of a given file
+///
+pub fn file(path: &str) -> Result<String, String> {
+    let mut reader = match File::open(path) {
+        Ok(file) => BufReader::new(file),
+        Err(err) => {
+            return Err(format!("Failed to open {}: {}", path, err));
+        }
+    };
+
+    let mut checksum = Checksum::new();
+    let mut buf = vec![0; 64 * 1024];
+
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(len) => checksum.update(&buf[..len]),
+            Err(err) => {
+                return Err(format!("Failed to read {}: {}", path, err));
+            }
+        };
+    }
+
+    Ok(checksum.finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checksum() {
+        assert_eq!(Checksum::new().finish(), "cbf29ce484222325");
+
+        let mut checksum = Checksum::new();
+        checksum.update(b"a");
+        assert_eq!(checksum.finish(), "af63dc4c8601ec8c");
+
+        let mut split = Checksum::new();
+        split.update(b"foo");
+        split.update(b"bar");
+
+        let mut whole = Checksum::new();
+        whole.update(b"foobar");
+
+        assert_eq!(split.finish(), whole.finish());
+    }
+}
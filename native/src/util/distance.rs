@@ -0,0 +1,125 @@
+///
+/// Metres in a degree of latitude, or in a degree of longitude at the equator
+///
+pub const METRES_PER_DEGREE: f64 = 111_320.0;
+
+///
+/// Default radius (metres) within which like named addresses or networks are clustered
+///
+pub const CLUSTER_RADIUS: f64 = 500.0;
+
+///
+/// Default radius (metres) within which a network is considered a potential link for an address
+///
+pub const LINK_RADIUS: f64 = 2000.0;
+
+///
+/// Default radius (metres) within which existing addresses are compared to a new address
+/// by conflate & consensus
+///
+pub const SEARCH_RADIUS: f64 = 1000.0;
+
+///
+/// Default radius (metres) within which intersections of the same streets are merged,
+/// ie: where dual carriageways cross
+///
+pub const INTERSECTION_RADIUS: f64 = 50.0;
+
+///
+/// Convert a distance in metres to decimal degrees at a given latitude.
+///
+/// A degree of longitude shrinks towards the poles while a degree of latitude does not,
+/// converting using the length of a degree of longitude gives a radius in degrees that
+/// covers at least the given distance in every direction
+///
+pub fn degrees(metres: f64, lat: f64) -> f64 {
+    // Clamp so that the conversion doesn't explode at the poles
+    let lat = lat.abs().min(89.0).to_radians();
+
+    metres / (METRES_PER_DEGREE * lat.cos())
+}
+
+///
+/// Approximate distance in metres between two (lon, lat) coordinates,
+/// accurate for the short distances used when snapping, merging & measuring
+///
+pub fn metres(a: [f64; 2], b: [f64; 2]) -> f64 {
+    let scale = ((a[1] + b[1]) / 2.0).to_radians().cos();
+
+    let x = (a[0] - b[0]) * METRES_PER_DEGREE * scale;
+    let y = (a[1] - b[1]) * METRES_PER_DEGREE;
+
+    (x * x + y * y).sqrt()
+}
+
+///
+/// SQL equivalent of degrees(), converting the metres expression to decimal
+/// degrees at the latitude of the given point geometry expression
+///
+pub fn sql_degrees(metres: &str, point: &str) -> String {
+    format!(
+        "({metres} / ({mpd} * cos(radians(LEAST(abs(ST_Y({point})), 89)))))",
+        metres = metres,
+        mpd = METRES_PER_DEGREE,
+        point = point
+    )
+}
+
+///
+/// SQL equivalent of degrees() for any geometry expression, converting at the most
+/// poleward latitude that is within the metres expression of the geometry's bounding
+/// box, so that the radius covers the given distance from every part of the geometry
+///
+pub fn sql_extent_degrees(metres: &str, geom: &str) -> String {
+    sql_degrees(
+        metres,
+        format!(
+            "ST_MakePoint(0, GREATEST(abs(ST_YMin({geom})), abs(ST_YMax({geom}))) + ({metres}) / {mpd})",
+            metres = metres,
+            mpd = METRES_PER_DEGREE,
+            geom = geom
+        )
+        .as_str(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_degrees() {
+        assert_eq!(degrees(METRES_PER_DEGREE, 0.0), 1.0);
+        assert_eq!(degrees(1000.0, 0.0), degrees(1000.0, -0.0));
+
+        // A degree of longitude is half as long at 60 degrees
+        assert!((degrees(METRES_PER_DEGREE, 60.0) - 2.0).abs() < 1e-9);
+        assert!((degrees(METRES_PER_DEGREE, -60.0) - 2.0).abs() < 1e-9);
+
+        // Clamped near the poles
+        assert!(degrees(1000.0, 90.0).is_finite());
+        assert_eq!(degrees(1000.0, 90.0), degrees(1000.0, 89.0));
+    }
+
+    #[test]
+    fn test_metres() {
+        assert!((metres([0.0, 0.0], [1.0, 0.0]) - METRES_PER_DEGREE).abs() < 1e-6);
+        assert!((metres([0.0, 60.0], [1.0, 60.0]) - METRES_PER_DEGREE / 2.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_sql_degrees() {
+        assert_eq!(
+            sql_degrees("$3", "a.geom"),
+            "($3 / (111320 * cos(radians(LEAST(abs(ST_Y(a.geom)), 89)))))"
+        );
+    }
+
+    #[test]
+    fn test_sql_extent_degrees() {
+        assert_eq!(
+            sql_extent_degrees("$1", "a.geom"),
+            "($1 / (111320 * cos(radians(LEAST(abs(ST_Y(ST_MakePoint(0, GREATEST(abs(ST_YMin(a.geom)), abs(ST_YMax(a.geom))) + ($1) / 111320))), 89)))))"
+        );
+    }
+}
@@ -0,0 +1,640 @@
+use postgres::GenericConnection;
+
+use crate::util::distance;
+use crate::util::topology::{self, DisjointSet, Line};
+use crate::Network;
+
+///
+/// A snapped line end in the network graph
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct Node {
+    pub coord: [f64; 2],
+    pub edges: Vec<usize>,
+}
+
+///
+/// A single LineString of a network feature, connecting two nodes
+///
+#[derive(Debug, PartialEq, Clone)]
+pub struct Edge {
+    pub id: i64,
+    pub from: usize,
+    pub to: usize,
+    pub length: f64,
+}
+
+///
+/// Counts describing the shape of a graph, used for QA output
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Summary {
+    pub nodes: usize,
+    pub edges: usize,
+    pub components: usize,
+    pub dangling: usize,
+    pub junctions: usize,
+}
+
+///
+/// Node coordinates as stored in the Graph RTree
+///
+struct NodePoint {
+    coord: [f64; 2],
+    node: usize,
+}
+
+impl rstar::RTreeObject for NodePoint {
+    type Envelope = rstar::AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        rstar::AABB::from_point(self.coord)
+    }
+}
+
+impl rstar::PointDistance for NodePoint {
+    fn distance_2(&self, point: &[f64; 2]) -> f64 {
+        let x = self.coord[0] - point[0];
+        let y = self.coord[1] - point[1];
+
+        x * x + y * y
+    }
+}
+
+///
+/// A line vertex as stored in the RTrees used to split lines
+///
+struct Vertex {
+    line: usize,
+    coord: [f64; 2],
+}
+
+impl rstar::RTreeObject for Vertex {
+    type Envelope = rstar::AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        rstar::AABB::from_point(self.coord)
+    }
+}
+
+///
+/// Node/Edge graph of a road network.
+///
+/// Lines are split into edges wherever they connect to another line, that is at their
+/// ends, at vertices shared with another line & where the end of another line meets them
+/// partway along a segment (a T-junction). Points within tolerance metres of an existing
+/// node are snapped to it, so that slightly misaligned features are still considered
+/// connected, as per topology::components
+///
+pub struct Graph {
+    tolerance: f64,
+    lines: Vec<(i64, Line)>,
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+    tree: rstar::RTree<NodePoint>,
+}
+
+impl Graph {
+    pub fn new() -> Self {
+        Graph {
+            tolerance: topology::SNAP_TOLERANCE,
+            lines: Vec::new(),
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            tree: rstar::RTree::new(),
+        }
+    }
+
+    ///
+    /// Builder style snapping tolerance setter (metres)
+    ///
+    pub fn set_tolerance(mut self, tolerance: f64) -> Self {
+        self.tolerance = tolerance;
+        self
+    }
+
+    ///
+    /// Build a graph from a set of networks. Networks without an id are given
+    /// a sequential id, in the same fashion as pg::Network::seq_id
+    ///
+    pub fn from_networks(mut self, networks: impl IntoIterator<Item = Network>) -> Self {
+        for (i, network) in networks.into_iter().enumerate() {
+            let id = match network.id {
+                Some(id) => id,
+                None => i as i64 + 1,
+            };
+
+            for line in network.geom {
+                self.add(id, line.iter().map(|coord| [coord[0], coord[1]]).collect());
+            }
+        }
+
+        self.build()
+    }
+
+    ///
+    /// Build a graph from the imported network table, ordered by id so that
+    /// snapping is deterministic
+    ///
+    pub fn from_db(mut self, conn: &impl GenericConnection) -> Self {
+        let rows = conn
+            .query(
+                r#"
+            SELECT
+                id,
+                ST_AsGeoJSON(ST_Force2D(geom))::JSON
+            FROM
+                network
+            ORDER BY
+                id
+        "#,
+                &[],
+            )
+            .unwrap();
+
+        for row in rows.iter() {
+            let id: i64 = row.get(0);
+            let geom: serde_json::Value = row.get(1);
+
+            for line in topology::lines(&geom) {
+                self.add(id, line);
+            }
+        }
+
+        self.build()
+    }
+
+    ///
+    /// Add a single line of the network with the given id, returning the index of the
+    /// line, or None if the line has fewer than two coordinates
+    ///
+    /// Lines are only split into nodes & edges once the graph is built
+    ///
+    pub fn add(&mut self, id: i64, line: Line) -> Option<usize> {
+        if line.len() < 2 {
+            return None;
+        }
+
+        self.lines.push((id, line));
+
+        Some(self.lines.len() - 1)
+    }
+
+    ///
+    /// Split the added lines into the nodes & edges of the graph
+    ///
+    pub fn build(mut self) -> Self {
+        let lines = std::mem::replace(&mut self.lines, Vec::new());
+
+        let vertices: rstar::RTree<Vertex> = rstar::RTree::bulk_load(
+            lines
+                .iter()
+                .enumerate()
+                .flat_map(|(i, (_, line))| {
+                    line.iter().map(move |coord| Vertex {
+                        line: i,
+                        coord: *coord,
+                    })
+                })
+                .collect(),
+        );
+
+        let ends: rstar::RTree<Vertex> = rstar::RTree::bulk_load(
+            lines
+                .iter()
+                .enumerate()
+                .flat_map(|(i, (_, line))| {
+                    vec![line[0], line[line.len() - 1]]
+                        .into_iter()
+                        .map(move |coord| Vertex {
+                            line: i,
+                            coord: coord,
+                        })
+                })
+                .collect(),
+        );
+
+        for (i, (id, line)) in lines.iter().enumerate() {
+            let splits = self.splits(i, line, &vertices, &ends);
+
+            for piece in splits.windows(2) {
+                let coords = piece_of(line, &piece[0], &piece[1]);
+
+                let length: f64 = coords
+                    .windows(2)
+                    .map(|seg| distance::metres(seg[0], seg[1]))
+                    .sum();
+
+                // Split points within tolerance of each other snap to the same node,
+                // skip the piece between them rather than adding a degenerate loop
+                if splits.len() > 2 && length <= self.tolerance {
+                    continue;
+                }
+
+                let from = self.snap(coords[0]);
+                let to = self.snap(coords[coords.len() - 1]);
+
+                self.edge(*id, from, to, length);
+            }
+        }
+
+        self
+    }
+
+    ///
+    /// Points along a line at which it connects to another line, as (vertex, fraction of
+    /// the following segment) positions & their coordinates, ordered along the line
+    ///
+    fn splits(
+        &self,
+        i: usize,
+        line: &[[f64; 2]],
+        vertices: &rstar::RTree<Vertex>,
+        ends: &rstar::RTree<Vertex>,
+    ) -> Vec<((usize, f64), [f64; 2])> {
+        let last = line.len() - 1;
+        let mut splits: Vec<((usize, f64), [f64; 2])> =
+            vec![((0, 0.0), line[0]), ((last, 0.0), line[last])];
+
+        // interior vertices shared with another line
+        for (index, coord) in line.iter().enumerate().take(last).skip(1) {
+            let shared = vertices
+                .locate_in_envelope(&self.envelope(*coord, *coord))
+                .any(|other| {
+                    other.line != i && distance::metres(other.coord, *coord) <= self.tolerance
+                });
+
+            if shared {
+                splits.push(((index, 0.0), *coord));
+            }
+        }
+
+        // ends of other lines that meet a segment of this line
+        for (index, seg) in line.windows(2).enumerate() {
+            for end in ends.locate_in_envelope(&self.envelope(seg[0], seg[1])) {
+                if end.line == i {
+                    continue;
+                }
+
+                let (t, closest) = nearest(end.coord, seg[0], seg[1]);
+
+                if distance::metres(end.coord, closest) > self.tolerance {
+                    continue;
+                }
+
+                // ends meeting a vertex are split at the vertex itself
+                if distance::metres(seg[0], closest) <= self.tolerance {
+                    splits.push(((index, 0.0), seg[0]));
+                } else if distance::metres(seg[1], closest) <= self.tolerance {
+                    splits.push(((index + 1, 0.0), seg[1]));
+                } else {
+                    splits.push(((index, t), closest));
+                }
+            }
+        }
+
+        splits.sort_by(|a, b| (a.0).partial_cmp(&b.0).unwrap());
+        splits.dedup_by(|a, b| a.0 == b.0);
+
+        splits
+    }
+
+    ///
+    /// Bounding box of the coordinates within tolerance of a segment
+    ///
+    fn envelope(&self, a: [f64; 2], b: [f64; 2]) -> rstar::AABB<[f64; 2]> {
+        // Degrees are only used to narrow down candidates, the final check is in metres
+        let radius = distance::degrees(self.tolerance, a[1].abs().max(b[1].abs()));
+
+        rstar::AABB::from_corners(
+            [a[0].min(b[0]) - radius, a[1].min(b[1]) - radius],
+            [a[0].max(b[0]) + radius, a[1].max(b[1]) + radius],
+        )
+    }
+
+    fn edge(&mut self, id: i64, from: usize, to: usize, length: f64) -> usize {
+        let edge = self.edges.len();
+        self.edges.push(Edge {
+            id: id,
+            from: from,
+            to: to,
+            length: length,
+        });
+
+        self.nodes[from].edges.push(edge);
+        self.nodes[to].edges.push(edge);
+
+        edge
+    }
+
+    ///
+    /// Return the node within tolerance of a coordinate, creating one if there is none
+    ///
+    fn snap(&mut self, coord: [f64; 2]) -> usize {
+        use rstar::PointDistance;
+
+        // Degrees are only used to narrow down candidates, the final check is in metres
+        let radius = distance::degrees(self.tolerance, coord[1]);
+        let radius_2 = radius * radius;
+
+        let closest = self
+            .tree
+            .nearest_neighbor_iter(&coord)
+            .take_while(|point| point.distance_2(&coord) <= radius_2)
+            .map(|point| (point.node, distance::metres(point.coord, coord)))
+            .filter(|(_, dist)| *dist <= self.tolerance)
+            .fold(
+                None,
+                |closest: Option<(usize, f64)>, (node, dist)| match closest {
+                    Some((_, min)) if min <= dist => closest,
+                    _ => Some((node, dist)),
+                },
+            );
+
+        if let Some((node, _)) = closest {
+            return node;
+        }
+
+        let node = self.nodes.len();
+        self.nodes.push(Node {
+            coord: coord,
+            edges: Vec::new(),
+        });
+        self.tree.insert(NodePoint {
+            coord: coord,
+            node: node,
+        });
+
+        node
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn edges(&self) -> &[Edge] {
+        &self.edges
+    }
+
+    ///
+    /// Number of edge ends at a node, a loop counts twice
+    ///
+    pub fn degree(&self, node: usize) -> usize {
+        self.nodes[node].edges.len()
+    }
+
+    pub fn degrees(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .map(|node| self.degree(node))
+            .collect()
+    }
+
+    ///
+    /// Nodes at the end of a single edge, such as dead ends or
+    /// gaps in the network that were too large to snap
+    ///
+    pub fn dangling(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|node| self.degree(*node) == 1)
+            .collect()
+    }
+
+    ///
+    /// Nodes where edges of more than one network feature meet
+    ///
+    pub fn junctions(&self) -> Vec<usize> {
+        (0..self.nodes.len())
+            .filter(|node| {
+                let edges = &self.nodes[*node].edges;
+                let first = self.edges[edges[0]].id;
+
+                edges.iter().any(|edge| self.edges[*edge].id != first)
+            })
+            .collect()
+    }
+
+    ///
+    /// Network ids of the edges at a node, sorted & deduplicated
+    ///
+    pub fn ids(&self, node: usize) -> Vec<i64> {
+        let mut ids: Vec<i64> = self.nodes[node]
+            .edges
+            .iter()
+            .map(|edge| self.edges[*edge].id)
+            .collect();
+
+        ids.sort();
+        ids.dedup();
+        ids
+    }
+
+    ///
+    /// Groups of connected edges, each a sorted list of edge indexes,
+    /// ordered by their smallest edge index
+    ///
+    pub fn components(&self) -> Vec<Vec<usize>> {
+        let mut sets = DisjointSet::new(self.nodes.len());
+
+        for edge in self.edges.iter() {
+            sets.union(edge.from, edge.to);
+        }
+
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut roots: Vec<Option<usize>> = vec![None; self.nodes.len()];
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            let root = sets.find(edge.from);
+
+            match roots[root] {
+                Some(group) => groups[group].push(i),
+                None => {
+                    roots[root] = Some(groups.len());
+                    groups.push(vec![i]);
+                }
+            }
+        }
+
+        groups
+    }
+
+    pub fn summary(&self) -> Summary {
+        Summary {
+            nodes: self.nodes.len(),
+            edges: self.edges.len(),
+            components: self.components().len(),
+            dangling: self.dangling().len(),
+            junctions: self.junctions().len(),
+        }
+    }
+}
+
+///
+/// Coordinates of a line between two split points
+///
+fn piece_of(
+    line: &[[f64; 2]],
+    from: &((usize, f64), [f64; 2]),
+    to: &((usize, f64), [f64; 2]),
+) -> Line {
+    let ((from_index, _), from_coord) = from;
+    let ((to_index, to_t), to_coord) = to;
+
+    let mut coords = vec![*from_coord];
+    coords.extend(line[from_index + 1..=*to_index].iter().cloned());
+
+    // ends partway along a segment
+    if *to_t > 0.0 {
+        coords.push(*to_coord);
+    }
+
+    coords.dedup();
+    coords
+}
+
+///
+/// Fraction along a segment & coordinate of the point on it nearest a coordinate,
+/// using a local planar projection of the segment
+///
+fn nearest(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> (f64, [f64; 2]) {
+    let scale = a[1].to_radians().cos();
+
+    let dx = (b[0] - a[0]) * scale;
+    let dy = b[1] - a[1];
+    let len_2 = dx * dx + dy * dy;
+
+    if len_2 == 0.0 {
+        return (0.0, a);
+    }
+
+    let t = (((p[0] - a[0]) * scale * dx + (p[1] - a[1]) * dy) / len_2)
+        .max(0.0)
+        .min(1.0);
+
+    (t, [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Roughly 1 metre of longitude at 38 degrees latitude
+    const METRE: f64 = 0.0000114;
+
+    fn graph(tolerance: f64) -> Graph {
+        let mut graph = Graph::new().set_tolerance(tolerance);
+
+        // Main St made up of two features meeting end to end
+        graph.add(1, vec![[-77.0, 38.0], [-77.001, 38.0]]);
+        graph.add(2, vec![[-77.001, 38.0], [-77.002, 38.0]]);
+
+        // Elm St ends just over half a metre short of Main St
+        graph.add(3, vec![[-77.001, 38.0 + METRE / 2.0], [-77.001, 38.001]]);
+
+        // Oak St is isolated
+        graph.add(4, vec![[-78.0, 38.0], [-78.0, 38.001]]);
+
+        graph.build()
+    }
+
+    #[test]
+    fn test_graph() {
+        let graph = graph(1.0);
+
+        assert_eq!(graph.nodes().len(), 6);
+        assert_eq!(graph.edges().len(), 4);
+        assert_eq!(graph.degrees(), vec![1, 3, 1, 1, 1, 1]);
+        assert_eq!(graph.dangling(), vec![0, 2, 3, 4, 5]);
+        assert_eq!(graph.junctions(), vec![1]);
+        assert_eq!(graph.ids(1), vec![1, 2, 3]);
+        assert_eq!(graph.components(), vec![vec![0, 1, 2], vec![3]]);
+
+        assert_eq!(
+            graph.summary(),
+            Summary {
+                nodes: 6,
+                edges: 4,
+                components: 2,
+                dangling: 5,
+                junctions: 1,
+            }
+        );
+
+        let length = graph.edges()[0].length;
+        assert!((length - 87.7).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_graph_tolerance() {
+        // Elm St is no longer close enough to snap to Main St
+        let graph = graph(0.1);
+
+        assert_eq!(graph.nodes().len(), 7);
+        assert_eq!(graph.junctions(), vec![1]);
+        assert_eq!(graph.ids(1), vec![1, 2]);
+        assert_eq!(graph.components(), vec![vec![0, 1], vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn test_graph_loop() {
+        let mut graph = Graph::new();
+
+        assert_eq!(graph.add(1, vec![[0.0, 0.0]]), None);
+        assert_eq!(
+            graph.add(1, vec![[0.0, 0.0], [0.001, 0.0], [0.0, 0.001], [0.0, 0.0]]),
+            Some(0)
+        );
+
+        let graph = graph.build();
+
+        assert_eq!(graph.nodes().len(), 1);
+        assert_eq!(graph.degree(0), 2);
+        assert_eq!(graph.dangling(), Vec::<usize>::new());
+        assert_eq!(graph.junctions(), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_graph_t_junction() {
+        let mut graph = Graph::new();
+
+        // Main St is a single feature
+        graph.add(1, vec![[-77.0, 38.0], [-77.002, 38.0]]);
+
+        // Elm St ends partway along Main St, just under half a metre north of it
+        graph.add(2, vec![[-77.001, 38.001], [-77.001, 38.0 + METRE / 3.0]]);
+
+        let graph = graph.build();
+
+        assert_eq!(graph.nodes().len(), 4);
+        assert_eq!(graph.edges().len(), 3);
+        assert_eq!(graph.degrees(), vec![1, 3, 1, 1]);
+        assert_eq!(graph.dangling(), vec![0, 2, 3]);
+        assert_eq!(graph.junctions(), vec![1]);
+        assert_eq!(graph.ids(1), vec![1, 2]);
+        assert_eq!(graph.components(), vec![vec![0, 1, 2]]);
+
+        // Main St is split in half at the junction
+        let half = graph.edges()[0].length;
+        assert!((half - 87.7).abs() < 0.1);
+        assert!((graph.edges()[1].length - half).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_graph_shared_vertex() {
+        let mut graph = Graph::new();
+
+        // Main St & Elm St cross at a vertex they share
+        graph.add(1, vec![[-77.0, 38.0], [-77.001, 38.0], [-77.002, 38.0]]);
+        graph.add(
+            2,
+            vec![[-77.001, 38.001], [-77.001, 38.0], [-77.001, 37.999]],
+        );
+
+        let graph = graph.build();
+
+        assert_eq!(graph.nodes().len(), 5);
+        assert_eq!(graph.edges().len(), 4);
+        assert_eq!(graph.junctions(), vec![1]);
+        assert_eq!(graph.degree(1), 4);
+        assert_eq!(graph.components(), vec![vec![0, 1, 2, 3]]);
+    }
+}
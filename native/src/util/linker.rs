@@ -8,6 +8,12 @@ pub struct Link<'a> {
     pub id: i64,
     pub maxscore: f64,
     pub names: &'a Names,
+
+    /// Match path that produced the maxscore
+    pub maxpath: Option<MatchPath>,
+
+    /// (primary name index, potential name index) that produced the maxscore
+    pub maxname: Option<(usize, usize)>,
 }
 
 impl<'a> Link<'a> {
@@ -16,6 +22,8 @@ impl<'a> Link<'a> {
             id: id,
             maxscore: 0.0,
             names: names,
+            maxpath: None,
+            maxname: None,
         }
     }
 }
@@ -35,6 +43,56 @@ impl LinkResult {
     }
 }
 
+///
+/// The linker rule that resulted in a given match
+///
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy)]
+pub enum MatchPath {
+    /// Tokenized names are identical
+    Exact,
+
+    /// Tokenized names are identical once the primary cardinal has been removed
+    CardinalStrip,
+
+    /// Weighted levenshtein distance score
+    Levenshtein,
+
+    /// All tokens of the shorter name are found in the longer name
+    Subset,
+}
+
+impl MatchPath {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MatchPath::Exact => "exact",
+            MatchPath::CardinalStrip => "cardinal-strip",
+            MatchPath::Levenshtein => "levenshtein",
+            MatchPath::Subset => "subset",
+        }
+    }
+}
+
+///
+/// A LinkResult along with the provenance of how the match was made
+///
+#[derive(Debug, PartialEq)]
+pub struct LinkDetail {
+    pub id: i64,
+    pub score: f64,
+
+    /// Rule that produced the match
+    pub path: MatchPath,
+
+    /// Display value of the primary name that matched
+    pub name: String,
+
+    /// Display value of the potential name that was matched
+    pub matched: String,
+
+    /// Number of potentials that were considered
+    pub candidates: i64,
+}
+
 // check if characters are consecutive in string; ie. "ntra" in "nuestra"
 fn pattern_match(pattern: &str, full: &str) -> bool {
     let mut pattern_chars = pattern.chars();
@@ -118,18 +176,36 @@ fn check_substring(token_list_one: Vec<String>, mut token_list_two: Vec<String>)
 /// being matched with a slightly less desirable match, usually due to data
 /// reasons.
 ///
-pub fn linker(primary: Link, mut potentials: Vec<Link>, strict: bool) -> Option<LinkResult> {
-    for name in &primary.names.names {
+pub fn linker(primary: Link, potentials: Vec<Link>, strict: bool) -> Option<LinkResult> {
+    linker_detail(primary, potentials, strict)
+        .map(|detail| LinkResult::new(detail.id, detail.score))
+}
+
+///
+/// Identical to linker() but returns the provenance of the match
+/// (match path, matched names & candidate count) along with the id & score
+///
+pub fn linker_detail(primary: Link, mut potentials: Vec<Link>, strict: bool) -> Option<LinkDetail> {
+    let candidates = potentials.len() as i64;
+
+    for (name_i, name) in primary.names.names.iter().enumerate() {
         let tokenized = name.tokenized_string();
         let tokenless = name.tokenless_string();
 
         for potential in potentials.iter_mut() {
-            'outer: for potential_name in &potential.names.names {
+            'outer: for (potential_i, potential_name) in potential.names.names.iter().enumerate() {
                 // Ensure exact matches are always returned before potential short-circuits
                 //
                 // N Main St == N Main St
                 if name.tokenized == potential_name.tokenized {
-                    return Some(LinkResult::new(potential.id, 100.0));
+                    return Some(LinkDetail {
+                        id: potential.id,
+                        score: 100.0,
+                        path: MatchPath::Exact,
+                        name: name.display.clone(),
+                        matched: potential_name.display.clone(),
+                        candidates: candidates,
+                    });
                 }
 
                 let potential_tokenized = potential_name.tokenized_string();
@@ -163,7 +239,14 @@ pub fn linker(primary: Link, mut potentials: Vec<Link>, strict: bool) -> Option<
                         && !potential_name.has_type(Some(TokenType::Cardinal))
                         && name.remove_type_string(Some(TokenType::Cardinal)) == potential_tokenized
                     {
-                        return Some(LinkResult::new(potential.id, 100.0));
+                        return Some(LinkDetail {
+                            id: potential.id,
+                            score: 100.0,
+                            path: MatchPath::CardinalStrip,
+                            name: name.display.clone(),
+                            matched: potential_name.display.clone(),
+                            candidates: candidates,
+                        });
                     }
                 }
 
@@ -225,6 +308,7 @@ pub fn linker(primary: Link, mut potentials: Vec<Link>, strict: bool) -> Option<
                     }
                 }
 
+                let mut path = MatchPath::Levenshtein;
                 let mut score = 100.0
                     - (((2.0 * lev_score.unwrap())
                         / (potential_tokenized.len() as f64 + tokenized.len() as f64))
@@ -257,11 +341,14 @@ pub fn linker(primary: Link, mut potentials: Vec<Link>, strict: bool) -> Option<
                     if subset_match {
                         // subset match successful
                         score = 70.01;
+                        path = MatchPath::Subset;
                     };
                 }
 
                 if score > potential.maxscore {
                     potential.maxscore = score;
+                    potential.maxpath = Some(path);
+                    potential.maxname = Some((name_i, potential_i));
                 }
             }
         }
@@ -285,10 +372,16 @@ pub fn linker(primary: Link, mut potentials: Vec<Link>, strict: bool) -> Option<
     match max {
         Some(max) => {
             if max.maxscore > 70.0 {
-                Some(LinkResult::new(
-                    max.id,
-                    (max.maxscore * 100.0).round() / 100.0,
-                ))
+                let (name_i, potential_i) = max.maxname.unwrap_or((0, 0));
+
+                Some(LinkDetail {
+                    id: max.id,
+                    score: (max.maxscore * 100.0).round() / 100.0,
+                    path: max.maxpath.unwrap_or(MatchPath::Levenshtein),
+                    name: primary.names.names[name_i].display.clone(),
+                    matched: max.names.names[potential_i].display.clone(),
+                    candidates: candidates,
+                })
             } else {
                 None
             }
@@ -1290,4 +1383,44 @@ mod tests {
             assert_linker_eq!("fr", "grand'place", "grand place", false, 70.01);
         }
     }
+
+    #[test]
+    fn test_linker_detail() {
+        let detail = |language: &str, name_a: &str, name_b: &str| {
+            let context = build_lang_context!(language);
+            let a_name = Names::new(vec![Name::new(name_a, 0, None, &context)], &context);
+            let b_name = Names::new(vec![Name::new(name_b, 0, None, &context)], &context);
+            let c_name = Names::new(vec![Name::new("Nowhere Ln", 0, None, &context)], &context);
+            let a = Link::new(1, &a_name);
+            let b = vec![Link::new(3, &c_name), Link::new(2, &b_name)];
+
+            let detail = linker_detail(a, b, false).unwrap();
+
+            assert_eq!(detail.id, 2);
+            assert_eq!(detail.candidates, 2);
+            assert_eq!(detail.name, a_name.names[0].display);
+            assert_eq!(detail.matched, b_name.names[0].display);
+
+            (detail.path, detail.score)
+        };
+
+        assert_eq!(
+            detail("en", "Main St", "Main St"),
+            (MatchPath::Exact, 100.0)
+        );
+        assert_eq!(
+            detail("en", "N Main St", "Main St"),
+            (MatchPath::CardinalStrip, 100.0)
+        );
+        assert_eq!(
+            detail("fr", "rue de la reine astrid", "rue reine astrid"),
+            (MatchPath::Levenshtein, 91.18)
+        );
+        assert_eq!(
+            detail("fr", "grand'place", "grand place"),
+            (MatchPath::Subset, 70.01)
+        );
+
+        assert_eq!(MatchPath::CardinalStrip.as_str(), "cardinal-strip");
+    }
 }
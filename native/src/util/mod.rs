@@ -1 +1,9 @@
+pub mod checksum;
+pub mod distance;
+pub mod graph;
 pub mod linker;
+pub mod partition;
+pub mod progress;
+pub mod task;
+pub mod topology;
+pub mod treelinker;
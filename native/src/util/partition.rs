@@ -0,0 +1,470 @@
+use std::sync::Mutex;
+
+use crate::pg::Pool;
+
+///
+/// Default number of ids in a single window
+///
+pub const WINDOW: i64 = 5000;
+
+///
+/// How the id space of a table is split into windows
+///
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Partition {
+    /// Fixed width windows between min(id) & max(id). Cheap, but windows
+    /// over sparse ids may contain few or no rows
+    Range,
+
+    /// Windows of a fixed number of rows, found by seeking past the end of the
+    /// last window. Suited to sparse ids such as those from Hecate
+    Keyset,
+}
+
+///
+/// Parallelism options shared by modes that process ids in windows across worker threads
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Parallel {
+    /// Number of worker threads, defaults to the number of cpus
+    pub workers: Option<usize>,
+
+    /// Number of ids (Range) or rows (Keyset) in a single window, defaults to WINDOW
+    pub window: Option<i64>,
+
+    /// Maximum number of database connections shared by the workers,
+    /// defaults to one per worker
+    pub pool: Option<usize>,
+
+    /// Partitioning strategy, defaults to Range
+    pub partition: Option<Partition>,
+}
+
+impl Parallel {
+    pub fn new() -> Self {
+        Parallel {
+            workers: None,
+            window: None,
+            pool: None,
+            partition: None,
+        }
+    }
+
+    pub fn workers(&self) -> usize {
+        self.workers.unwrap_or_else(num_cpus::get).max(1)
+    }
+
+    pub fn window(&self) -> i64 {
+        self.window.unwrap_or(WINDOW).max(1)
+    }
+
+    ///
+    /// Number of connections to open, there is never a need for more connections than workers
+    ///
+    pub fn pool(&self) -> usize {
+        let workers = self.workers();
+
+        self.pool.unwrap_or(workers).max(1).min(workers)
+    }
+
+    pub fn partition(&self) -> Partition {
+        self.partition.unwrap_or(Partition::Range)
+    }
+
+    ///
+    /// Partition the id column of the given table, returning the min & max id
+    /// along with the windows, or None if the table is empty
+    ///
+    pub fn windows(&self, pool: &Pool, table: &str) -> Option<(i64, i64, IdWindows)> {
+        let (min, max) = bounds(pool, table)?;
+
+        let windows = match self.partition() {
+            Partition::Range => IdWindows::range(min, max, self.window()),
+            Partition::Keyset => {
+                let pool = pool.clone();
+                let query = format!(
+                    r#"
+                    SELECT
+                        max(id)
+                    FROM (
+                        SELECT
+                            id
+                        FROM
+                            {table}
+                        WHERE
+                            id >= $1
+                        ORDER BY
+                            id
+                        LIMIT $2
+                    ) w
+                "#,
+                    table = table
+                );
+
+                IdWindows::keyset(min, self.window(), move |start, size| {
+                    let rows = pool.get().query(&query, &[&start, &size]).unwrap();
+
+                    rows.get(0).get(0)
+                })
+            }
+        };
+
+        Some((min, max, windows))
+    }
+}
+
+///
+/// Return the min & max id of a table, or None if it is empty
+///
+pub fn bounds(pool: &Pool, table: &str) -> Option<(i64, i64)> {
+    let rows = pool
+        .get()
+        .query(
+            format!(
+                r#"
+            SELECT
+                min(id),
+                max(id)
+            FROM
+                {table}
+        "#,
+                table = table
+            )
+            .as_str(),
+            &[],
+        )
+        .unwrap();
+
+    let row = rows.get(0);
+    let min: Option<i64> = row.get(0);
+    let max: Option<i64> = row.get(1);
+
+    match (min, max) {
+        (Some(min), Some(max)) => Some((min, max)),
+        _ => None,
+    }
+}
+
+///
+/// Number of ids in the inclusive range min => max
+///
+pub fn span(min: i64, max: i64) -> u64 {
+    if max < min {
+        0
+    } else {
+        (i128::from(max) - i128::from(min) + 1) as u64
+    }
+}
+
+type Seek = Box<dyn Fn(i64, i64) -> Option<i64> + Send + Sync>;
+
+enum Strategy {
+    Range { max: i64 },
+    Keyset { seek: Seek },
+}
+
+///
+/// Hands out contiguous, non overlapping, inclusive id windows to any number of
+/// worker threads. Every id between the starting id & the end of the range falls
+/// in exactly one window.
+///
+/// Rather than statically splitting the id range between workers, each worker
+/// takes the next window when it finishes its last, so that skewed id density
+/// doesn't leave most workers idle
+///
+pub struct IdWindows {
+    next: Mutex<Option<i64>>,
+    size: i64,
+    strategy: Strategy,
+}
+
+impl IdWindows {
+    ///
+    /// Windows of `size` ids between min & max
+    ///
+    pub fn range(min: i64, max: i64, size: i64) -> Self {
+        IdWindows {
+            next: Mutex::new(Some(min)),
+            size: size.max(1),
+            strategy: Strategy::Range { max: max },
+        }
+    }
+
+    ///
+    /// Windows of `size` rows starting at min. Given the start of a window and its size,
+    /// seek must return the greatest id of the first `size` rows with an id >= start,
+    /// or None if there are no such rows
+    ///
+    pub fn keyset(
+        min: i64,
+        size: i64,
+        seek: impl Fn(i64, i64) -> Option<i64> + Send + Sync + 'static,
+    ) -> Self {
+        IdWindows {
+            next: Mutex::new(Some(min)),
+            size: size.max(1),
+            strategy: Strategy::Keyset {
+                seek: Box::new(seek),
+            },
+        }
+    }
+
+    ///
+    /// Take the next unprocessed window, returns None once the range is exhausted
+    ///
+    pub fn next(&self) -> Option<(i64, i64)> {
+        let mut next = self.next.lock().unwrap();
+
+        let start = (*next)?;
+
+        let end = match self.strategy {
+            Strategy::Range { max } => {
+                if start > max {
+                    None
+                } else {
+                    Some(start.saturating_add(self.size - 1).min(max))
+                }
+            }
+            Strategy::Keyset { ref seek } => match seek(start, self.size) {
+                Some(end) if end >= start => Some(end),
+                _ => None,
+            },
+        };
+
+        match end {
+            Some(end) => {
+                *next = end.checked_add(1);
+                Some((start, end))
+            }
+            None => {
+                *next = None;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use std::sync::Arc;
+
+    ///
+    /// Minimal xorshift generator so that property tests are reproducible
+    ///
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn between(&mut self, min: i64, max: i64) -> i64 {
+            min + (self.next() % (max - min + 1) as u64) as i64
+        }
+
+        ///
+        /// Sorted set of unique ids, both negative & positive, with gaps of random size
+        ///
+        fn ids(&mut self) -> Vec<i64> {
+            let len = self.between(0, 200);
+            let mut id = self.between(-10_000, 10_000);
+            let mut ids = BTreeSet::new();
+
+            for _ in 0..len {
+                ids.insert(id);
+                id += match self.between(0, 3) {
+                    0 => 1,
+                    1 => self.between(1, 5),
+                    2 => self.between(1, 100),
+                    _ => self.between(1, 5000),
+                };
+            }
+
+            ids.into_iter().collect()
+        }
+    }
+
+    ///
+    /// In memory equivalent of the keyset seek query
+    ///
+    fn seek(ids: Arc<Vec<i64>>) -> impl Fn(i64, i64) -> Option<i64> + Send + Sync {
+        move |start, size| {
+            ids.iter()
+                .filter(|id| **id >= start)
+                .take(size as usize)
+                .last()
+                .cloned()
+        }
+    }
+
+    ///
+    /// Drain windows from multiple threads, returning them sorted
+    ///
+    fn drain(windows: IdWindows, threads: usize) -> Vec<(i64, i64)> {
+        let windows = Arc::new(windows);
+
+        let strands: Vec<_> = (0..threads)
+            .map(|_| {
+                let windows = windows.clone();
+
+                std::thread::spawn(move || {
+                    let mut taken = Vec::new();
+                    while let Some(window) = windows.next() {
+                        taken.push(window);
+                    }
+                    taken
+                })
+            })
+            .collect();
+
+        let mut taken: Vec<(i64, i64)> = strands
+            .into_iter()
+            .flat_map(|strand| strand.join().unwrap())
+            .collect();
+        taken.sort();
+        taken
+    }
+
+    ///
+    /// Assert that every id falls into exactly one window
+    ///
+    fn assert_exactly_once(ids: &[i64], windows: &[(i64, i64)]) {
+        for pair in windows.windows(2) {
+            assert!(pair[0].1 < pair[1].0, "windows overlap: {:?}", pair);
+        }
+
+        // As windows are sorted & disjoint, an id can only be in the last window starting at or before it
+        for id in ids {
+            let contained = match windows.binary_search_by(|window| window.0.cmp(id)) {
+                Ok(_) => true,
+                Err(0) => false,
+                Err(i) => windows[i - 1].1 >= *id,
+            };
+
+            assert!(contained, "id {} is in no window", id);
+        }
+    }
+
+    #[test]
+    fn test_parallel() {
+        let parallel = Parallel {
+            workers: Some(4),
+            window: Some(0),
+            pool: Some(8),
+            partition: None,
+        };
+
+        assert_eq!(parallel.workers(), 4);
+        assert_eq!(parallel.window(), 1);
+        assert_eq!(parallel.pool(), 4);
+        assert_eq!(parallel.partition(), Partition::Range);
+
+        let parallel: Parallel = serde_json::from_value(serde_json::json!({
+            "workers": 4,
+            "pool": 2,
+            "partition": "keyset"
+        }))
+        .unwrap();
+
+        assert_eq!(parallel.window(), WINDOW);
+        assert_eq!(parallel.pool(), 2);
+        assert_eq!(parallel.partition(), Partition::Keyset);
+    }
+
+    #[test]
+    fn test_span() {
+        assert_eq!(span(1, 10), 10);
+        assert_eq!(span(-5, 5), 11);
+        assert_eq!(span(5, 4), 0);
+        assert_eq!(span(std::i64::MIN, std::i64::MAX), std::u64::MAX);
+    }
+
+    #[test]
+    fn test_range_windows() {
+        let windows = IdWindows::range(1, 10, 4);
+
+        assert_eq!(windows.next(), Some((1, 4)));
+        assert_eq!(windows.next(), Some((5, 8)));
+        assert_eq!(windows.next(), Some((9, 10)));
+        assert_eq!(windows.next(), None);
+        assert_eq!(windows.next(), None);
+
+        let empty = IdWindows::range(1, 0, 4);
+        assert_eq!(empty.next(), None);
+
+        // Must not overflow at the end of the id space
+        let end = IdWindows::range(std::i64::MAX - 5, std::i64::MAX, 4);
+        assert_eq!(end.next(), Some((std::i64::MAX - 5, std::i64::MAX - 2)));
+        assert_eq!(end.next(), Some((std::i64::MAX - 1, std::i64::MAX)));
+        assert_eq!(end.next(), None);
+    }
+
+    #[test]
+    fn test_keyset_windows() {
+        let ids = Arc::new(vec![-7, -3, 2, 3, 900, 901, 5000]);
+        let windows = IdWindows::keyset(-7, 3, seek(ids));
+
+        assert_eq!(windows.next(), Some((-7, 2)));
+        assert_eq!(windows.next(), Some((3, 901)));
+        assert_eq!(windows.next(), Some((902, 5000)));
+        assert_eq!(windows.next(), None);
+        assert_eq!(windows.next(), None);
+    }
+
+    #[test]
+    fn test_range_property() {
+        let mut rng = Rng(0x2545_F491_4F6C_DD1D);
+
+        for _ in 0..200 {
+            let ids = rng.ids();
+            let size = rng.between(50, 5000);
+            let threads = rng.between(1, 8) as usize;
+
+            let windows = match (ids.first(), ids.last()) {
+                (Some(min), Some(max)) => drain(IdWindows::range(*min, *max, size), threads),
+                _ => Vec::new(),
+            };
+
+            assert_exactly_once(&ids, &windows);
+
+            // Every window other than the last is exactly size ids wide
+            for window in windows.iter().rev().skip(1) {
+                assert_eq!(span(window.0, window.1), size as u64);
+            }
+        }
+    }
+
+    #[test]
+    fn test_keyset_property() {
+        let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+
+        for _ in 0..200 {
+            let ids = Arc::new(rng.ids());
+            let size = rng.between(1, 50);
+            let threads = rng.between(1, 8) as usize;
+
+            let windows = match ids.first() {
+                Some(min) => drain(IdWindows::keyset(*min, size, seek(ids.clone())), threads),
+                None => Vec::new(),
+            };
+
+            assert_exactly_once(&ids, &windows);
+
+            // Every window other than the last holds exactly size rows
+            for window in windows.iter().rev().skip(1) {
+                let rows = ids
+                    .iter()
+                    .filter(|id| window.0 <= **id && **id <= window.1)
+                    .count();
+
+                assert_eq!(rows, size as usize);
+            }
+        }
+    }
+}
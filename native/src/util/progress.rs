@@ -0,0 +1,390 @@
+use crossbeam::channel::{Receiver, Sender};
+use neon::prelude::*;
+use std::fs::OpenOptions;
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+///
+/// Minimum time between two progress events of the same stage
+///
+pub const INTERVAL: Duration = Duration::from_secs(1);
+
+///
+/// A single progress report, serialized as a JSON line or passed to a JS callback
+///
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ProgressEvent {
+    /// Name of the stage that is reporting
+    pub stage: String,
+
+    /// Number of items that have been processed
+    pub done: u64,
+
+    /// Total number of items, if known ahead of time
+    pub total: Option<u64>,
+
+    /// Items processed per second since the stage started
+    pub rate: f64,
+
+    /// Seconds since the stage started
+    pub elapsed: f64,
+
+    /// Is this the final event of the stage
+    pub finished: bool,
+}
+
+enum Sink {
+    Silent,
+    Lines(Mutex<Box<dyn Write + Send>>),
+    Channel(Sender<ProgressEvent>),
+}
+
+///
+/// Destination for progress events. Cheap to clone & safe to share between threads
+///
+#[derive(Clone)]
+pub struct Progress {
+    sink: Arc<Sink>,
+}
+
+impl Progress {
+    ///
+    /// Discard all events
+    ///
+    pub fn silent() -> Self {
+        Progress {
+            sink: Arc::new(Sink::Silent),
+        }
+    }
+
+    ///
+    /// Write each event as a line of JSON
+    ///
+    pub fn lines(writer: impl Write + Send + 'static) -> Self {
+        Progress {
+            sink: Arc::new(Sink::Lines(Mutex::new(Box::new(writer)))),
+        }
+    }
+
+    ///
+    /// Send each event over a channel, the channel is closed once
+    /// every clone of the Progress & its Stages has been dropped
+    ///
+    pub fn channel() -> (Self, Receiver<ProgressEvent>) {
+        let (tx, rx) = crossbeam::channel::unbounded();
+
+        (
+            Progress {
+                sink: Arc::new(Sink::Channel(tx)),
+            },
+            rx,
+        )
+    }
+
+    ///
+    /// Start reporting a new stage
+    ///
+    pub fn stage(&self, name: &str, total: Option<u64>) -> Stage {
+        Stage {
+            inner: Arc::new(StageInner {
+                progress: self.clone(),
+                name: name.to_string(),
+                total: total,
+                done: AtomicU64::new(0),
+                start: Instant::now(),
+                last: Mutex::new(Instant::now()),
+            }),
+        }
+    }
+
+    fn emit(&self, event: &ProgressEvent) {
+        // Failing to report progress should never fail the job itself
+        match *self.sink {
+            Sink::Silent => (),
+            Sink::Lines(ref writer) => {
+                if let Ok(mut writer) = writer.lock() {
+                    if let Ok(line) = serde_json::to_string(event) {
+                        writer.write_all(format!("{}\n", line).as_bytes()).ok();
+                        writer.flush().ok();
+                    }
+                }
+            }
+            Sink::Channel(ref tx) => {
+                tx.send(event.clone()).ok();
+            }
+        };
+    }
+}
+
+///
+/// Progress of a single stage. Clones share the same counter so that a stage can
+/// be ticked from multiple threads. The final event is emitted when the last clone
+/// is dropped
+///
+#[derive(Clone)]
+pub struct Stage {
+    inner: Arc<StageInner>,
+}
+
+struct StageInner {
+    progress: Progress,
+    name: String,
+    total: Option<u64>,
+    done: AtomicU64,
+    start: Instant,
+    last: Mutex<Instant>,
+}
+
+impl Stage {
+    ///
+    /// Record that n more items have been processed, emitting an
+    /// event if one hasn't been emitted within the last INTERVAL
+    ///
+    pub fn tick(&self, n: u64) {
+        self.inner.done.fetch_add(n, Ordering::Relaxed);
+
+        // If another thread holds the lock it is already emitting
+        let mut last = match self.inner.last.try_lock() {
+            Ok(last) => last,
+            Err(_) => return,
+        };
+
+        if last.elapsed() >= INTERVAL {
+            *last = Instant::now();
+            self.inner.progress.emit(&self.inner.event(false));
+        }
+    }
+
+    pub fn done(&self) -> u64 {
+        self.inner.done.load(Ordering::Relaxed)
+    }
+}
+
+impl StageInner {
+    fn event(&self, finished: bool) -> ProgressEvent {
+        let done = self.done.load(Ordering::Relaxed);
+        let elapsed = self.start.elapsed();
+        let elapsed = elapsed.as_secs() as f64 + f64::from(elapsed.subsec_millis()) / 1000.0;
+
+        ProgressEvent {
+            stage: self.name.clone(),
+            done: done,
+            total: self.total,
+            rate: if elapsed > 0.0 {
+                done as f64 / elapsed
+            } else {
+                0.0
+            },
+            elapsed: elapsed,
+            finished: finished,
+        }
+    }
+}
+
+impl Drop for StageInner {
+    fn drop(&mut self) {
+        self.progress.emit(&self.event(true));
+    }
+}
+
+///
+/// Delivers events from a Progress channel to a JS callback. As JS can only be
+/// called from the main thread, events are queued until listen is called
+///
+pub struct Callback<'a> {
+    func: Handle<'a, JsFunction>,
+    events: Receiver<ProgressEvent>,
+}
+
+impl<'a> Callback<'a> {
+    ///
+    /// Block, delivering events as they arrive, until the Progress & all of
+    /// its Stages have been dropped
+    ///
+    pub fn listen(&self, cx: &mut FunctionContext<'a>) -> NeonResult<()> {
+        for event in self.events.iter() {
+            self.call(cx, &event)?;
+        }
+
+        Ok(())
+    }
+
+    ///
+    /// Take the events of the callback so that they can be polled for instead,
+    /// ie: by a mode running as a background task
+    ///
+    pub fn into_events(self) -> Receiver<ProgressEvent> {
+        self.events
+    }
+
+    fn call(&self, cx: &mut FunctionContext<'a>, event: &ProgressEvent) -> NeonResult<()> {
+        let event = neon_serde::to_value(cx, event)?;
+        let this = cx.undefined();
+
+        self.func.call(cx, this, vec![event])?;
+
+        Ok(())
+    }
+}
+
+///
+/// Parse the optional `progress` property of the argument object at the given index.
+///
+/// The property can either be a JS function, which will be called with each ProgressEvent,
+/// or a path that events will be appended to as JSON lines, where "-" is stderr.
+///
+/// The argument object is left untouched, use args() to read the remaining arguments
+///
+pub fn from_args<'a>(
+    cx: &mut FunctionContext<'a>,
+    index: i32,
+) -> NeonResult<(Progress, Option<Callback<'a>>)> {
+    let obj = match cx.argument_opt(index) {
+        Some(arg) => {
+            if arg.is_a::<JsObject>() {
+                arg.downcast::<JsObject>().or_throw(cx)?
+            } else {
+                return Ok((Progress::silent(), None));
+            }
+        }
+        None => return Ok((Progress::silent(), None)),
+    };
+
+    let arg = obj.get(cx, "progress")?;
+
+    if arg.is_a::<JsFunction>() {
+        let func = arg.downcast::<JsFunction>().or_throw(cx)?;
+        let (progress, events) = Progress::channel();
+
+        Ok((
+            progress,
+            Some(Callback {
+                func: func,
+                events: events,
+            }),
+        ))
+    } else if arg.is_a::<JsString>() {
+        let path = arg.downcast::<JsString>().or_throw(cx)?.value();
+
+        if path == "-" {
+            Ok((Progress::lines(std::io::stderr()), None))
+        } else {
+            match OpenOptions::new().create(true).append(true).open(&path) {
+                Ok(file) => Ok((Progress::lines(BufWriter::new(file)), None)),
+                Err(err) => cx.throw_error(format!("Unable to write to progress file: {}", err)),
+            }
+        }
+    } else {
+        Ok((Progress::silent(), None))
+    }
+}
+
+///
+/// Return the argument at the given index for deserialization, or None if it is missing,
+/// undefined or null.
+///
+/// Objects are shallow copied without their `progress` property, as a JS callback can't be
+/// deserialized, so that the caller's object is never modified
+///
+pub fn args<'a>(
+    cx: &mut FunctionContext<'a>,
+    index: i32,
+) -> NeonResult<Option<Handle<'a, JsValue>>> {
+    let arg = match cx.argument_opt(index) {
+        Some(arg) => arg,
+        None => return Ok(None),
+    };
+
+    if arg.is_a::<JsUndefined>() || arg.is_a::<JsNull>() {
+        return Ok(None);
+    } else if !arg.is_a::<JsObject>() || arg.is_a::<JsArray>() || arg.is_a::<JsFunction>() {
+        return Ok(Some(arg));
+    }
+
+    let obj = arg.downcast::<JsObject>().or_throw(cx)?;
+    let copy = JsObject::new(cx);
+
+    let keys = obj.get_own_property_names(cx)?;
+    for i in 0..keys.len() {
+        let key = keys.get(cx, i)?;
+
+        if let Ok(name) = key.downcast::<JsString>() {
+            if name.value() == "progress" {
+                continue;
+            }
+        }
+
+        let value = obj.get(cx, key)?;
+        copy.set(cx, key, value)?;
+    }
+
+    Ok(Some(copy.upcast()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_progress_channel() {
+        let (progress, events) = Progress::channel();
+
+        let stage = progress.stage("test", Some(10));
+        drop(progress);
+
+        let worker = stage.clone();
+        std::thread::spawn(move || {
+            worker.tick(4);
+            worker.tick(6);
+        })
+        .join()
+        .unwrap();
+
+        assert_eq!(stage.done(), 10);
+        drop(stage);
+
+        // Ticks are within INTERVAL of the stage starting, so only the final event is sent
+        let events: Vec<ProgressEvent> = events.iter().collect();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].stage, "test");
+        assert_eq!(events[0].done, 10);
+        assert_eq!(events[0].total, Some(10));
+        assert!(events[0].finished);
+    }
+
+    #[test]
+    fn test_progress_lines() {
+        #[derive(Clone)]
+        struct Shared(Arc<Mutex<Vec<u8>>>);
+
+        impl Write for Shared {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let buf = Shared(Arc::new(Mutex::new(Vec::new())));
+
+        let progress = Progress::lines(buf.clone());
+        progress.stage("first", None).tick(1);
+        progress.stage("second", Some(2)).tick(2);
+
+        let output = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let lines: Vec<ProgressEvent> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0].stage, "first");
+        assert_eq!(lines[0].total, None);
+        assert_eq!(lines[1].stage, "second");
+        assert_eq!(lines[1].done, 2);
+    }
+}
@@ -0,0 +1,327 @@
+use crossbeam::channel::{Receiver, TryRecvError};
+use neon::prelude::*;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::util::progress;
+
+lazy_static! {
+    ///
+    /// Cancel tokens of all currently running background tasks
+    ///
+    static ref TOKENS: Mutex<HashMap<u32, CancelToken>> = Mutex::new(HashMap::new());
+
+    ///
+    /// Progress events of background tasks that were given a JS progress callback,
+    /// queued until they are polled for with events()
+    ///
+    static ref EVENTS: Mutex<HashMap<u32, Receiver<progress::ProgressEvent>>> = Mutex::new(HashMap::new());
+}
+
+static TOKEN_ID: AtomicUsize = AtomicUsize::new(1);
+
+///
+/// Shared flag that long running loops check between batches to
+/// determine if they should stop early
+///
+#[derive(Clone)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    ///
+    /// Return an Err if the token has been cancelled, allowing loops to use `?`
+    ///
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(String::from("Cancelled"))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+///
+/// Register a new token, returning the id that JS can pass to cancel
+///
+fn register() -> (u32, CancelToken) {
+    let id = TOKEN_ID.fetch_add(1, Ordering::SeqCst) as u32;
+    let token = CancelToken::new();
+
+    TOKENS.lock().unwrap().insert(id, token.clone());
+
+    (id, token)
+}
+
+fn release(id: u32) {
+    TOKENS.lock().unwrap().remove(&id);
+}
+
+///
+/// Cancel a running background task given the id returned when it was scheduled.
+/// Returns false if there is no task running with the given id
+///
+pub fn cancel(mut cx: FunctionContext) -> JsResult<JsBoolean> {
+    let id = cx.argument::<JsNumber>(0)?.value() as u32;
+
+    match TOKENS.lock().unwrap().get(&id) {
+        Some(token) => {
+            token.cancel();
+            Ok(cx.boolean(true))
+        }
+        None => Ok(cx.boolean(false)),
+    }
+}
+
+///
+/// Return the progress events that a background task has queued since the last call.
+///
+/// Once the task has finished & every event has been returned its queue is removed,
+/// tasks without a JS progress callback always return an empty array
+///
+pub fn events(mut cx: FunctionContext) -> JsResult<JsValue> {
+    let id = cx.argument::<JsNumber>(0)?.value() as u32;
+
+    let events = drain(id);
+
+    Ok(neon_serde::to_value(&mut cx, &events)?)
+}
+
+fn drain(id: u32) -> Vec<progress::ProgressEvent> {
+    let mut queues = EVENTS.lock().unwrap();
+
+    let mut events = Vec::new();
+
+    let finished = match queues.get(&id) {
+        None => return events,
+        Some(queue) => loop {
+            match queue.try_recv() {
+                Ok(event) => events.push(event),
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        },
+    };
+
+    if finished {
+        queues.remove(&id);
+    }
+
+    events
+}
+
+type Job = Box<dyn FnOnce(CancelToken) -> Result<serde_json::Value, String> + Send>;
+
+///
+/// A mode running on the libuv thread pool
+///
+struct ModeTask {
+    id: u32,
+    token: CancelToken,
+    job: Mutex<Option<Job>>,
+}
+
+impl Task for ModeTask {
+    type Output = serde_json::Value;
+    type Error = String;
+    type JsEvent = JsValue;
+
+    fn perform(&self) -> Result<Self::Output, Self::Error> {
+        let job = match self.job.lock().unwrap().take() {
+            Some(job) => job,
+            None => return Err(String::from("Task has already been performed")),
+        };
+
+        let token = self.token.clone();
+
+        // A panic must not unwind into libuv, instead pass it to the callback as an error
+        match catch_unwind(AssertUnwindSafe(move || job(token))) {
+            Ok(result) => result,
+            Err(err) => Err(panic_message(err)),
+        }
+    }
+
+    fn complete(
+        self,
+        mut cx: TaskContext,
+        result: Result<Self::Output, Self::Error>,
+    ) -> JsResult<Self::JsEvent> {
+        release(self.id);
+
+        match result {
+            Ok(output) => Ok(neon_serde::to_value(&mut cx, &output)?),
+            Err(err) => cx.throw_error(err),
+        }
+    }
+}
+
+fn panic_message(err: Box<dyn std::any::Any + Send>) -> String {
+    if let Some(string) = err.downcast_ref::<String>() {
+        string.clone()
+    } else if let Some(string) = err.downcast_ref::<&str>() {
+        string.to_string()
+    } else {
+        String::from("Unknown Panic")
+    }
+}
+
+///
+/// Run a mode. If the argument at `index` is a function the mode is scheduled as a background
+/// task, the function is called with (err, result) on completion, and the id of the task's
+/// cancel token is returned. Events for a JS progress callback are queued under the same id
+/// & delivered by polling events()
+///
+/// Otherwise the mode is run synchronously & its result returned. If a JS progress callback was
+/// given, the mode runs on its own thread so that the main thread can deliver progress events.
+///
+/// The job should own the Progress of the mode so that the callback stops listening once it returns
+///
+pub fn run<'a>(
+    cx: &mut FunctionContext<'a>,
+    index: i32,
+    callback: Option<progress::Callback<'a>>,
+    job: impl FnOnce(CancelToken) -> Result<serde_json::Value, String> + Send + 'static,
+) -> JsResult<'a, JsValue> {
+    let done = match cx.argument_opt(index) {
+        Some(arg) => {
+            if arg.is_a::<JsFunction>() {
+                Some(arg.downcast::<JsFunction>().or_throw(cx)?)
+            } else {
+                None
+            }
+        }
+        None => None,
+    };
+
+    if let Some(done) = done {
+        let (id, token) = register();
+
+        // JS can't be called from the thread pool, queue events for events() instead
+        if let Some(callback) = callback {
+            EVENTS.lock().unwrap().insert(id, callback.into_events());
+        }
+
+        let task = ModeTask {
+            id: id,
+            token: token,
+            job: Mutex::new(Some(Box::new(job))),
+        };
+        task.schedule(done);
+
+        return Ok(cx.number(f64::from(id)).upcast());
+    }
+
+    let result = match callback {
+        None => job(CancelToken::new()),
+        Some(callback) => {
+            let strand = match thread::Builder::new()
+                .name(String::from("Mode"))
+                .spawn(move || job(CancelToken::new()))
+            {
+                Ok(strand) => strand,
+                Err(err) => {
+                    return cx.throw_error(format!("Thread Creation Error: {}", err));
+                }
+            };
+
+            callback.listen(cx)?;
+
+            match strand.join() {
+                Ok(result) => result,
+                Err(err) => Err(panic_message(err)),
+            }
+        }
+    };
+
+    match result {
+        Ok(output) => Ok(neon_serde::to_value(cx, &output)?),
+        Err(err) => cx.throw_error(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancel_token() {
+        let token = CancelToken::new();
+        let shared = token.clone();
+
+        assert!(!token.is_cancelled());
+        assert_eq!(token.check(), Ok(()));
+
+        shared.cancel();
+
+        assert!(token.is_cancelled());
+        assert_eq!(token.check(), Err(String::from("Cancelled")));
+    }
+
+    #[test]
+    fn test_register() {
+        let (first, token) = register();
+        let (second, _) = register();
+
+        assert_ne!(first, second);
+        assert!(TOKENS.lock().unwrap().contains_key(&first));
+
+        TOKENS.lock().unwrap().get(&first).unwrap().cancel();
+        assert!(token.is_cancelled());
+
+        release(first);
+        release(second);
+        assert!(!TOKENS.lock().unwrap().contains_key(&first));
+    }
+
+    #[test]
+    fn test_drain() {
+        let (progress, events) = progress::Progress::channel();
+        EVENTS.lock().unwrap().insert(u32::max_value(), events);
+
+        let stage = progress.stage("test", Some(2));
+        stage.tick(2);
+
+        // The stage is still running, no event has been sent within INTERVAL
+        assert_eq!(drain(u32::max_value()), Vec::new());
+        assert!(EVENTS.lock().unwrap().contains_key(&u32::max_value()));
+
+        drop(stage);
+        drop(progress);
+
+        let events = drain(u32::max_value());
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].done, 2);
+        assert!(events[0].finished);
+
+        // The channel is closed & empty so the queue has been removed
+        assert!(!EVENTS.lock().unwrap().contains_key(&u32::max_value()));
+        assert_eq!(drain(u32::max_value()), Vec::new());
+    }
+
+    #[test]
+    fn test_panic_message() {
+        let err = catch_unwind(|| panic!("Thread Error: {}", 1)).unwrap_err();
+        assert_eq!(panic_message(err), "Thread Error: 1");
+
+        let err = catch_unwind(|| panic!("static")).unwrap_err();
+        assert_eq!(panic_message(err), "static");
+    }
+}
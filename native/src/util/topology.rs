@@ -0,0 +1,467 @@
+use crate::util::distance::METRES_PER_DEGREE;
+
+///
+/// Default distance (metres) between disconnected parts of a network cluster beyond which
+/// they are split into separate clusters
+///
+pub const CLUSTER_GAP: f64 = 250.0;
+
+///
+/// Default difference in orientation (degrees) beyond which disconnected parts of
+/// a network cluster are split into separate clusters
+///
+pub const CLUSTER_ANGLE: f64 = 45.0;
+
+///
+/// Distance (metres) within which the end of one line is considered connected to another
+///
+pub const SNAP_TOLERANCE: f64 = 1.0;
+
+///
+/// Parts shorter than this (metres) don't have a meaningful orientation
+/// and are never split from a cluster by orientation alone
+///
+pub const MIN_ORIENTED_LENGTH: f64 = 100.0;
+
+///
+/// A single LineString as a list of coordinates
+///
+pub type Line = Vec<[f64; 2]>;
+
+///
+/// Parse the coordinates of a GeoJSON (Multi)LineString geometry
+///
+pub fn lines(geom: &serde_json::Value) -> Vec<Line> {
+    let coords = match geom.get("type").and_then(|t| t.as_str()) {
+        Some("LineString") => vec![&geom["coordinates"]],
+        Some("MultiLineString") => match geom["coordinates"].as_array() {
+            Some(lines) => lines.iter().collect(),
+            None => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+
+    coords
+        .into_iter()
+        .filter_map(|line| line.as_array())
+        .map(|line| {
+            line.iter()
+                .filter_map(|coord| match (coord[0].as_f64(), coord[1].as_f64()) {
+                    (Some(x), Some(y)) => Some([x, y]),
+                    _ => None,
+                })
+                .collect()
+        })
+        .collect()
+}
+
+///
+/// Project (lon, lat) items onto a planar grid in metres, centred on their mean latitude.
+/// Suitable for the distances involved within a single cluster
+///
+pub fn project(items: &[Vec<Line>]) -> Vec<Vec<Line>> {
+    let mut lat_sum = 0.0;
+    let mut lat_count = 0.0;
+
+    for line in items.iter().flatten() {
+        for coord in line {
+            lat_sum += coord[1];
+            lat_count += 1.0;
+        }
+    }
+
+    let lat = if lat_count > 0.0 {
+        lat_sum / lat_count
+    } else {
+        0.0
+    };
+    let scale = lat.to_radians().cos();
+
+    items
+        .iter()
+        .map(|lines| {
+            lines
+                .iter()
+                .map(|line| {
+                    line.iter()
+                        .map(|coord| {
+                            [
+                                coord[0] * METRES_PER_DEGREE * scale,
+                                coord[1] * METRES_PER_DEGREE,
+                            ]
+                        })
+                        .collect()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+///
+/// Group projected items (each made up of one or more lines) that are topologically
+/// connected, that is the end of a line in one item lies within tolerance of a line in another
+///
+pub fn components(items: &[Vec<Line>], tolerance: f64) -> Vec<Vec<usize>> {
+    let mut sets = DisjointSet::new(items.len());
+
+    for a in 0..items.len() {
+        for b in (a + 1)..items.len() {
+            if touches(&items[a], &items[b], tolerance) || touches(&items[b], &items[a], tolerance)
+            {
+                sets.union(a, b);
+            }
+        }
+    }
+
+    sets.groups()
+}
+
+///
+/// Split projected items into groups that should be clustered together.
+///
+/// Topologically connected items are always kept together, disconnected components
+/// are only grouped if they are within gap metres of each other and, where both are
+/// long enough to have an orientation, differ in orientation by no more than angle degrees
+///
+pub fn split(items: &[Vec<Line>], gap: f64, angle: f64) -> Vec<Vec<usize>> {
+    let comps = components(items, SNAP_TOLERANCE);
+
+    let segments: Vec<Vec<([f64; 2], [f64; 2])>> = comps
+        .iter()
+        .map(|comp| {
+            comp.iter()
+                .flat_map(|i| items[*i].iter())
+                .flat_map(|line| line.windows(2).map(|seg| (seg[0], seg[1])))
+                .collect()
+        })
+        .collect();
+
+    let orientations: Vec<Option<f64>> = segments.iter().map(|segs| orientation(segs)).collect();
+
+    let mut sets = DisjointSet::new(comps.len());
+
+    for a in 0..comps.len() {
+        for b in (a + 1)..comps.len() {
+            if let (Some(oa), Some(ob)) = (orientations[a], orientations[b]) {
+                if axial_difference(oa, ob) > angle {
+                    continue;
+                }
+            }
+
+            if min_distance(&segments[a], &segments[b]) <= gap {
+                sets.union(a, b);
+            }
+        }
+    }
+
+    let mut groups: Vec<Vec<usize>> = sets
+        .groups()
+        .into_iter()
+        .map(|group| {
+            let mut ids: Vec<usize> = group
+                .into_iter()
+                .flat_map(|comp| comps[comp].iter().cloned())
+                .collect();
+            ids.sort();
+            ids
+        })
+        .collect();
+
+    groups.sort();
+    groups
+}
+
+///
+/// Does the end of any line in a lie within tolerance of any line in b
+///
+fn touches(a: &[Line], b: &[Line], tolerance: f64) -> bool {
+    for line in a {
+        let ends = match (line.first(), line.last()) {
+            (Some(first), Some(last)) => [*first, *last],
+            _ => continue,
+        };
+
+        for end in ends.iter() {
+            for other in b {
+                if other.len() == 1 && dist(*end, other[0]) <= tolerance {
+                    return true;
+                }
+
+                for seg in other.windows(2) {
+                    if point_segment(*end, seg[0], seg[1]) <= tolerance {
+                        return true;
+                    }
+                }
+            }
+        }
+    }
+
+    false
+}
+
+///
+/// Length weighted mean orientation of a set of segments in degrees [0, 180),
+/// or None if the segments are too short to have a meaningful orientation
+///
+fn orientation(segments: &[([f64; 2], [f64; 2])]) -> Option<f64> {
+    let mut length = 0.0;
+    let mut cos = 0.0;
+    let mut sin = 0.0;
+
+    // Orientation is axial (a line heading north is the same as one heading south)
+    // so angles are doubled before averaging
+    for (a, b) in segments {
+        let len = dist(*a, *b);
+        let theta = (b[1] - a[1]).atan2(b[0] - a[0]);
+
+        length += len;
+        cos += len * (2.0 * theta).cos();
+        sin += len * (2.0 * theta).sin();
+    }
+
+    if length < MIN_ORIENTED_LENGTH {
+        return None;
+    }
+
+    let theta = sin.atan2(cos).to_degrees() / 2.0;
+
+    Some((theta + 180.0) % 180.0)
+}
+
+///
+/// Difference between two axial orientations in degrees [0, 90]
+///
+fn axial_difference(a: f64, b: f64) -> f64 {
+    let diff = (a - b).abs() % 180.0;
+
+    diff.min(180.0 - diff)
+}
+
+fn min_distance(a: &[([f64; 2], [f64; 2])], b: &[([f64; 2], [f64; 2])]) -> f64 {
+    let mut min = std::f64::MAX;
+
+    for sa in a {
+        for sb in b {
+            let d = segment_segment(*sa, *sb);
+
+            if d < min {
+                min = d;
+            }
+        }
+    }
+
+    min
+}
+
+fn dist(a: [f64; 2], b: [f64; 2]) -> f64 {
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)).sqrt()
+}
+
+fn point_segment(p: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+    let dx = b[0] - a[0];
+    let dy = b[1] - a[1];
+    let len_2 = dx * dx + dy * dy;
+
+    if len_2 == 0.0 {
+        return dist(p, a);
+    }
+
+    let t = (((p[0] - a[0]) * dx + (p[1] - a[1]) * dy) / len_2)
+        .max(0.0)
+        .min(1.0);
+
+    dist(p, [a[0] + t * dx, a[1] + t * dy])
+}
+
+fn segment_segment(a: ([f64; 2], [f64; 2]), b: ([f64; 2], [f64; 2])) -> f64 {
+    if intersects(a, b) {
+        return 0.0;
+    }
+
+    point_segment(a.0, b.0, b.1)
+        .min(point_segment(a.1, b.0, b.1))
+        .min(point_segment(b.0, a.0, a.1))
+        .min(point_segment(b.1, a.0, a.1))
+}
+
+fn intersects(a: ([f64; 2], [f64; 2]), b: ([f64; 2], [f64; 2])) -> bool {
+    fn cross(o: [f64; 2], a: [f64; 2], b: [f64; 2]) -> f64 {
+        (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0])
+    }
+
+    let d1 = cross(b.0, b.1, a.0);
+    let d2 = cross(b.0, b.1, a.1);
+    let d3 = cross(a.0, a.1, b.0);
+    let d4 = cross(a.0, a.1, b.1);
+
+    ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+        && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+}
+
+///
+/// Union-Find over the indexes 0..n
+///
+pub(crate) struct DisjointSet {
+    parents: Vec<usize>,
+}
+
+impl DisjointSet {
+    pub(crate) fn new(n: usize) -> Self {
+        DisjointSet {
+            parents: (0..n).collect(),
+        }
+    }
+
+    pub(crate) fn find(&mut self, i: usize) -> usize {
+        let mut root = i;
+        while self.parents[root] != root {
+            root = self.parents[root];
+        }
+
+        // Path compression
+        let mut i = i;
+        while self.parents[i] != root {
+            let next = self.parents[i];
+            self.parents[i] = root;
+            i = next;
+        }
+
+        root
+    }
+
+    pub(crate) fn union(&mut self, a: usize, b: usize) {
+        let a = self.find(a);
+        let b = self.find(b);
+
+        if a != b {
+            self.parents[a.max(b)] = a.min(b);
+        }
+    }
+
+    ///
+    /// Return each set as a sorted list of indexes, ordered by their smallest index
+    ///
+    pub(crate) fn groups(&mut self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+        let mut roots: Vec<Option<usize>> = vec![None; self.parents.len()];
+
+        for i in 0..self.parents.len() {
+            let root = self.find(i);
+
+            match roots[root] {
+                Some(group) => groups[group].push(i),
+                None => {
+                    roots[root] = Some(groups.len());
+                    groups.push(vec![i]);
+                }
+            }
+        }
+
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_components() {
+        let items = vec![
+            vec![vec![[0.0, 0.0], [100.0, 0.0]]],
+            vec![vec![[100.0, 0.0], [200.0, 0.0]]],
+            vec![vec![[500.0, 0.0], [600.0, 0.0]]],
+            // T junction onto the middle of the first line
+            vec![vec![[50.0, 0.5], [50.0, 100.0]]],
+        ];
+
+        assert_eq!(components(&items, 1.0), vec![vec![0, 1, 3], vec![2]]);
+        assert_eq!(components(&items, 0.1), vec![vec![0, 1], vec![2], vec![3]]);
+    }
+
+    #[test]
+    fn test_split_gap() {
+        let items = vec![
+            vec![vec![[0.0, 0.0], [200.0, 0.0]]],
+            vec![vec![[400.0, 0.0], [600.0, 0.0]]],
+            vec![vec![[2000.0, 0.0], [2200.0, 0.0]]],
+        ];
+
+        assert_eq!(split(&items, 250.0, 45.0), vec![vec![0, 1], vec![2]]);
+        assert_eq!(split(&items, 100.0, 45.0), vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(split(&items, 2000.0, 45.0), vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_split_orientation() {
+        let items = vec![
+            vec![vec![[0.0, 0.0], [200.0, 0.0]]],
+            // Perpendicular & disconnected
+            vec![vec![[210.0, 10.0], [210.0, 210.0]]],
+            // Too short to have an orientation
+            vec![vec![[-60.0, 0.0], [-60.0, 20.0]]],
+        ];
+
+        assert_eq!(split(&items, 250.0, 45.0), vec![vec![0, 2], vec![1]]);
+        assert_eq!(split(&items, 250.0, 90.0), vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn test_split_connected() {
+        // Connected items are never split, regardless of orientation
+        let items = vec![
+            vec![vec![[0.0, 0.0], [200.0, 0.0]]],
+            vec![vec![[200.0, 0.0], [200.0, 200.0]]],
+        ];
+
+        assert_eq!(split(&items, 0.0, 0.0), vec![vec![0, 1]]);
+    }
+
+    #[test]
+    fn test_orientation() {
+        assert_eq!(axial_difference(10.0, 170.0), 20.0);
+        assert_eq!(axial_difference(0.0, 90.0), 90.0);
+
+        let north = orientation(&[([0.0, 0.0], [0.0, 200.0])]).unwrap();
+        let south = orientation(&[([0.0, 200.0], [0.0, 0.0])]).unwrap();
+        assert!(axial_difference(north, south) < 1e-9);
+
+        assert_eq!(orientation(&[([0.0, 0.0], [0.0, 10.0])]), None);
+    }
+
+    #[test]
+    fn test_lines() {
+        let geom = serde_json::json!({
+            "type": "MultiLineString",
+            "coordinates": [[[1.0, 2.0], [3.0, 4.0]], [[5.0, 6.0], [7.0, 8.0]]]
+        });
+
+        assert_eq!(
+            lines(&geom),
+            vec![vec![[1.0, 2.0], [3.0, 4.0]], vec![[5.0, 6.0], [7.0, 8.0]]]
+        );
+
+        let geom = serde_json::json!({
+            "type": "LineString",
+            "coordinates": [[1.0, 2.0], [3.0, 4.0]]
+        });
+
+        assert_eq!(lines(&geom), vec![vec![[1.0, 2.0], [3.0, 4.0]]]);
+
+        let geom = serde_json::json!({
+            "type": "Point",
+            "coordinates": [1.0, 2.0]
+        });
+
+        assert_eq!(lines(&geom), Vec::<Line>::new());
+    }
+
+    #[test]
+    fn test_project() {
+        let items = vec![vec![vec![[1.0, 60.0], [2.0, 60.0]]]];
+        let projected = project(&items);
+
+        let width = projected[0][0][1][0] - projected[0][0][0][0];
+        assert!((width - METRES_PER_DEGREE / 2.0).abs() < 1e-6);
+    }
+}
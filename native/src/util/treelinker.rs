@@ -0,0 +1,464 @@
+use crate::util::{distance, linker};
+use crate::{Address, Name, Names, Network};
+use postgres::GenericConnection;
+
+///
+/// Maximum number of proximal networks that are passed to the linker,
+/// ordered from most to least proximal
+///
+pub const LINK_CANDIDATES: usize = 10;
+
+///
+/// A single network feature as stored in the TreeLinker RTree
+///
+pub struct NetworkLine {
+    pub id: i64,
+    pub names: Names,
+    pub geom: Vec<geojson::LineStringType>,
+    envelope: rstar::AABB<[f64; 2]>,
+}
+
+impl NetworkLine {
+    pub fn new(id: i64, names: Names, geom: Vec<geojson::LineStringType>) -> Option<Self> {
+        let mut min = [std::f64::MAX, std::f64::MAX];
+        let mut max = [std::f64::MIN, std::f64::MIN];
+
+        for ln in geom.iter() {
+            for pt in ln.iter() {
+                min[0] = min[0].min(pt[0]);
+                min[1] = min[1].min(pt[1]);
+                max[0] = max[0].max(pt[0]);
+                max[1] = max[1].max(pt[1]);
+            }
+        }
+
+        // A network without any coordinates can never be linked to
+        if min[0] > max[0] {
+            return None;
+        }
+
+        Some(NetworkLine {
+            id: id,
+            names: names,
+            geom: geom,
+            envelope: rstar::AABB::from_corners(min, max),
+        })
+    }
+}
+
+impl rstar::RTreeObject for NetworkLine {
+    type Envelope = rstar::AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+impl NetworkLine {
+    ///
+    /// Squared distance between a point and the network, with longitudes
+    /// multiplied by the given scale before measuring
+    ///
+    fn scaled_distance_2(&self, point: &[f64; 2], scale: f64) -> f64 {
+        let mut min = std::f64::MAX;
+
+        for ln in self.geom.iter() {
+            if ln.len() == 1 {
+                min = min.min(segment_distance_2(point, &ln[0], &ln[0], scale));
+            }
+
+            for seg in ln.windows(2) {
+                min = min.min(segment_distance_2(point, &seg[0], &seg[1], scale));
+            }
+        }
+
+        min
+    }
+
+    ///
+    /// Approximate distance in metres between a (lon, lat) point and the network
+    ///
+    pub fn metres(&self, point: &[f64; 2]) -> f64 {
+        let scale = point[1].to_radians().cos();
+
+        self.scaled_distance_2(point, scale).sqrt() * distance::METRES_PER_DEGREE
+    }
+}
+
+impl rstar::PointDistance for NetworkLine {
+    fn distance_2(&self, point: &[f64; 2]) -> f64 {
+        self.scaled_distance_2(point, 1.0)
+    }
+}
+
+///
+/// Squared cartesian distance between a point and the segment a => b,
+/// with x coordinates multiplied by the given scale
+///
+fn segment_distance_2(point: &[f64; 2], a: &[f64], b: &[f64], scale: f64) -> f64 {
+    let dx = (b[0] - a[0]) * scale;
+    let dy = b[1] - a[1];
+    let px = (point[0] - a[0]) * scale;
+    let py = point[1] - a[1];
+    let len = dx * dx + dy * dy;
+
+    let t = if len == 0.0 {
+        0.0
+    } else {
+        ((px * dx + py * dy) / len).max(0.0).min(1.0)
+    };
+
+    let x = t * dx - px;
+    let y = t * dy - py;
+
+    x * x + y * y
+}
+
+///
+/// Pure rust alternative to map::link_process that does not require PostGIS
+///
+/// Networks are loaded into an in memory RTree and each address is linked against the
+/// closest LINK_CANDIDATES networks within distance::LINK_RADIUS metres. To assign the
+/// same netids as link_process the tree must be built from the network clusters, with
+/// their cluster ids & merged names, ie: with from_db
+///
+pub struct TreeLinker {
+    tree: rstar::RTree<NetworkLine>,
+    radius: f64,
+}
+
+impl TreeLinker {
+    ///
+    /// Build a TreeLinker from a set of network lines
+    ///
+    pub fn new(lines: impl IntoIterator<Item = NetworkLine>) -> Self {
+        TreeLinker {
+            tree: rstar::RTree::bulk_load(lines.into_iter().collect()),
+            radius: distance::LINK_RADIUS,
+        }
+    }
+
+    ///
+    /// Build a TreeLinker from a set of networks, linking to their own ids. Every network
+    /// must have an id, as generated ids could collide with the explicit ids of others
+    ///
+    pub fn from_networks(networks: impl IntoIterator<Item = Network>) -> Result<Self, String> {
+        let mut lines: Vec<NetworkLine> = Vec::new();
+
+        for network in networks {
+            let id = match network.id {
+                Some(id) => id,
+                None => {
+                    return Err(String::from(
+                        "Every network must have an id to be linked to",
+                    ))
+                }
+            };
+
+            if let Some(line) = NetworkLine::new(id, network.names, network.geom) {
+                lines.push(line);
+            }
+        }
+
+        Ok(TreeLinker::new(lines))
+    }
+
+    ///
+    /// Build a TreeLinker from the network_cluster table, linking to the same
+    /// cluster ids & merged names as link_process
+    ///
+    pub fn from_db(conn: &impl GenericConnection) -> Self {
+        let rows = conn
+            .query(
+                "
+            SELECT
+                id::BIGINT,
+                names::JSON,
+                ST_AsGeoJSON(ST_Force2D(geom))::JSON
+            FROM
+                network_cluster
+            WHERE
+                geom IS NOT NULL
+        ",
+                &[],
+            )
+            .unwrap();
+
+        let mut lines: Vec<NetworkLine> = Vec::with_capacity(rows.len());
+
+        for row in rows.iter() {
+            let id: i64 = row.get(0);
+
+            let names: serde_json::Value = row.get(1);
+            let names: Vec<Name> = match serde_json::from_value(names) {
+                Err(err) => {
+                    println!("JSON Failure: {}", err.to_string());
+                    panic!("JSON Failure: {}", err.to_string());
+                }
+                Ok(names) => names,
+            };
+
+            let geom: serde_json::Value = row.get(2);
+            let geom = match serde_json::from_value::<geojson::Geometry>(geom) {
+                Ok(geom) => match geom.value {
+                    geojson::Value::MultiLineString(lns) => lns,
+                    geojson::Value::LineString(ln) => vec![ln],
+                    _ => continue,
+                },
+                Err(_) => continue,
+            };
+
+            if let Some(line) = NetworkLine::new(id, Names { names: names }, geom) {
+                lines.push(line);
+            }
+        }
+
+        TreeLinker::new(lines)
+    }
+
+    ///
+    /// Builder style link radius setter (metres)
+    ///
+    pub fn set_radius(mut self, radius: f64) -> Self {
+        self.radius = radius;
+        self
+    }
+
+    ///
+    /// Number of networks loaded into the tree
+    ///
+    pub fn size(&self) -> usize {
+        self.tree.size()
+    }
+
+    ///
+    /// Return the networks within the link radius of a given point, ordered from most
+    /// to least proximal
+    ///
+    /// As with link_process, networks are filtered on their distance in metres but
+    /// ordered by their distance in degrees
+    ///
+    pub fn proximal(&self, point: [f64; 2]) -> Vec<&NetworkLine> {
+        use rstar::PointDistance;
+
+        // The radius in degrees covers at least the radius in metres in every direction
+        let degrees = distance::degrees(self.radius, point[1]);
+        let degrees_2 = degrees * degrees;
+
+        self.tree
+            .nearest_neighbor_iter(&point)
+            .take_while(|line| line.distance_2(&point) <= degrees_2)
+            .filter(|line| line.metres(&point) <= self.radius)
+            .take(LINK_CANDIDATES)
+            .collect()
+    }
+
+    ///
+    /// Find the network that a given address should be linked to
+    ///
+    pub fn link(&self, addr: &Address) -> Option<linker::LinkResult> {
+        self.link_detail(
+            addr.id.unwrap_or(0),
+            &addr.names,
+            [addr.geom[0], addr.geom[1]],
+        )
+        .map(|detail| linker::LinkResult::new(detail.id, detail.score))
+    }
+
+    ///
+    /// Find the network that an address with the given id, names & point should
+    /// be linked to, along with the provenance of the match
+    ///
+    pub fn link_detail(
+        &self,
+        id: i64,
+        names: &Names,
+        point: [f64; 2],
+    ) -> Option<linker::LinkDetail> {
+        let potentials: Vec<linker::Link> = self
+            .proximal(point)
+            .into_iter()
+            .map(|line| linker::Link::new(line.id, &line.names))
+            .collect();
+
+        if potentials.is_empty() {
+            return None;
+        }
+
+        let primary = linker::Link::new(id, names);
+
+        linker::linker_detail(primary, potentials, false)
+    }
+
+    ///
+    /// Link a stream of addresses, returning (address id, netid) pairs
+    ///
+    /// As with map::link_process, only addresses that have an id and
+    /// are used for interpolation are considered
+    ///
+    pub fn link_all<'a>(
+        &'a self,
+        addrs: impl Iterator<Item = Address> + 'a,
+    ) -> impl Iterator<Item = (i64, i64)> + 'a {
+        addrs.filter_map(move |addr| {
+            if !addr.interpolate {
+                return None;
+            }
+
+            let id = addr.id?;
+
+            self.link(&addr).map(|link| (id, link.id))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Context, Tokens};
+    use std::collections::HashMap;
+
+    fn context() -> Context {
+        Context::new(
+            String::from("us"),
+            None,
+            Tokens::new(HashMap::new(), HashMap::new(), HashMap::new()),
+        )
+    }
+
+    fn network(id: i64, street: &str, coords: &str) -> Network {
+        let feat: geojson::GeoJson = format!(
+            r#"{{"id":{},"type":"Feature","properties":{{"street":"{}"}},"geometry":{{"type":"LineString","coordinates":{}}}}}"#,
+            id, street, coords
+        )
+        .parse()
+        .unwrap();
+
+        Network::new(feat, &context()).unwrap()
+    }
+
+    fn address(id: i64, street: &str, coords: &str) -> Address {
+        let feat: geojson::GeoJson = format!(
+            r#"{{"id":{},"type":"Feature","properties":{{"street":"{}","number":"1"}},"geometry":{{"type":"Point","coordinates":{}}}}}"#,
+            id, street, coords
+        )
+        .parse()
+        .unwrap();
+
+        Address::new(feat, &context()).unwrap()
+    }
+
+    #[test]
+    fn test_treelinker() {
+        let tree = TreeLinker::from_networks(vec![
+            network(1, "Main St", "[[-77.0,38.0],[-77.0,38.001]]"),
+            network(2, "Elm St", "[[-77.0001,38.0],[-77.0001,38.001]]"),
+            network(3, "Main St", "[[-77.005,38.0],[-77.005,38.001]]"),
+            network(4, "Oak St", "[[-78.0,38.0],[-78.0,38.001]]"),
+        ])
+        .unwrap();
+
+        assert_eq!(tree.size(), 4);
+
+        // Closest network of the same name wins
+        assert_eq!(
+            tree.link(&address(1, "Main St", "[-77.0002,38.0005]")),
+            Some(linker::LinkResult::new(1, 100.0))
+        );
+
+        assert_eq!(
+            tree.link(&address(2, "Elm St", "[-77.0002,38.0005]")),
+            Some(linker::LinkResult::new(2, 100.0))
+        );
+
+        // Oak St exists but is outside of the link radius
+        assert_eq!(tree.link(&address(3, "Oak St", "[-77.0002,38.0005]")), None);
+
+        // No proximal networks at all
+        assert_eq!(tree.link(&address(4, "Main St", "[10.0,10.0]")), None);
+    }
+
+    #[test]
+    fn test_treelinker_link_all() {
+        let tree = TreeLinker::from_networks(vec![
+            network(1, "Main St", "[[-77.0,38.0],[-77.0,38.001]]"),
+            network(2, "Elm St", "[[-77.0001,38.0],[-77.0001,38.001]]"),
+        ])
+        .unwrap();
+
+        let mut no_itp = address(3, "Main St", "[-77.0002,38.0005]");
+        no_itp.interpolate = false;
+
+        let links: Vec<(i64, i64)> = tree
+            .link_all(
+                vec![
+                    address(1, "Main St", "[-77.0002,38.0005]"),
+                    address(2, "Elm St", "[-77.0002,38.0005]"),
+                    no_itp,
+                    address(4, "Oak St", "[-77.0002,38.0005]"),
+                ]
+                .into_iter(),
+            )
+            .collect();
+
+        assert_eq!(links, vec![(1, 1), (2, 2)]);
+    }
+
+    #[test]
+    fn test_treelinker_from_networks_id() {
+        let feat: geojson::GeoJson = r#"{"type":"Feature","properties":{"street":"Main St"},"geometry":{"type":"LineString","coordinates":[[-77.0,38.0],[-77.0,38.001]]}}"#
+            .parse()
+            .unwrap();
+
+        // Generated ids could collide with those of other networks
+        assert!(TreeLinker::from_networks(vec![
+            network(1, "Main St", "[[-77.0,38.0],[-77.0,38.001]]"),
+            Network::new(feat, &context()).unwrap(),
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn test_segment_distance_2() {
+        assert_eq!(
+            segment_distance_2(&[0.0, 1.0], &[0.0, 0.0], &[2.0, 0.0], 1.0),
+            1.0
+        );
+        assert_eq!(
+            segment_distance_2(&[3.0, 0.0], &[0.0, 0.0], &[2.0, 0.0], 1.0),
+            1.0
+        );
+        assert_eq!(
+            segment_distance_2(&[1.0, 1.0], &[0.0, 0.0], &[0.0, 0.0], 1.0),
+            2.0
+        );
+        assert_eq!(
+            segment_distance_2(&[4.0, 0.0], &[0.0, 0.0], &[0.0, 2.0], 0.5),
+            4.0
+        );
+    }
+
+    #[test]
+    fn test_treelinker_metres() {
+        // At 60N a degree of longitude is ~55.7km, these are ~1.67km & ~2.78km east
+        let tree = TreeLinker::from_networks(vec![
+            network(1, "Main St", "[[-76.97,60.0],[-76.97,60.001]]"),
+            network(2, "Elm St", "[[-76.95,60.0],[-76.95,60.001]]"),
+        ])
+        .unwrap();
+
+        let near = tree.proximal([-77.0, 60.0005]);
+        assert_eq!(near.len(), 1);
+        assert_eq!(near[0].id, 1);
+        assert!((near[0].metres(&[-77.0, 60.0005]) - 1670.0).abs() < 1.0);
+
+        assert_eq!(
+            tree.link(&address(1, "Main St", "[-77.0,60.0005]")),
+            Some(linker::LinkResult::new(1, 100.0))
+        );
+        assert_eq!(tree.link(&address(2, "Elm St", "[-77.0,60.0005]")), None);
+
+        let tree = tree.set_radius(3000.0);
+        assert_eq!(tree.proximal([-77.0, 60.0005]).len(), 2);
+    }
+}
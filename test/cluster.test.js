@@ -236,3 +236,80 @@ test('cluster.network', (t) => {
 });
 
 db.init(test);
+
+test('cluster.network - orphan', (t) => {
+    const pool = db.get();
+    const popQ = new Queue(1);
+
+    // POPULATE NETWORK
+    popQ.defer((done) => {
+        pool.query(`
+            BEGIN;
+            INSERT INTO network (names, geom) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Main Street", "freq": 1, "priority": 0 }]', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -66.05390310287476, 45.26961632842303 ], [ -66.05441808700562, 45.271035832768376 ] ] ]}'), 4326));
+            INSERT INTO network (names, geom) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Main Street", "freq": 1, "priority": 0 }]', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -66.05435371398926, 45.27100563091792 ], [ -66.05493307113646, 45.27245530161207 ] ] ]}'), 4326));
+            INSERT INTO network (names, geom) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Main Street", "freq": 1, "priority": 0 }]', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -113.50117206573485, 53.55137413785917 ], [ -113.50112915039062, 53.54836549323335 ] ] ]}'), 4326));
+            INSERT INTO network (names, geom) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Main Street", "freq": 1, "priority": 0 }]', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -113.50100040435791, 53.54836549323335 ], [ -113.50104331970215, 53.54614711825744 ] ] ]}'), 4326));
+            COMMIT;
+        `, (err) => {
+            t.error(err, 'no errors');
+
+            pg_optimize();
+
+            return done();
+        });
+    });
+
+    popQ.defer((done) => {
+        cluster_net('pt_test');
+
+        // Only the first cluster received addresses
+        pool.query(`
+            UPDATE network_cluster SET address = 1 WHERE id = 1;
+        `, (err) => {
+            t.error(err, 'no errors');
+
+            cluster_net('pt_test', true);
+
+            return done();
+        });
+    });
+
+    popQ.defer((done) => {
+        pool.query(`
+            SELECT
+                names,
+                source_ids,
+                cluster_ids
+            FROM
+                network_orphan_cluster
+            ORDER BY
+                id ASC;
+        `, (err, res) => {
+            t.error(err, 'no errors');
+
+            t.equals(res.rows.length, 1, 'ok - one orphan network cluster');
+
+            t.deepEquals(res.rows[0], {
+                names: [{
+                    freq: 1,
+                    tokenized: [{ token: 'main', token_type: null }, { token: 'st', token_type: 'Way' }],
+                    display: 'Main Street',
+                    priority: 0
+                }],
+                source_ids: ['3', '4'],
+                cluster_ids: ['2']
+            });
+
+            return done();
+        });
+    });
+
+    popQ.await((err) => {
+        t.error(err, 'no errors');
+        pool.end(() => {
+            t.end();
+        });
+    });
+});
+
+db.init(test);
@@ -504,3 +504,478 @@ test('conflate - MODIFY existing addresses with output==false are not modified,
     fs.unlinkSync('/tmp/error-persistent');
     t.end();
 });
+
+test('conflate - DELETE requires a scope', (t) => {
+    t.throws(() => worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-delete-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-delete-new.geojson'),
+        output: '/tmp/output.geojson',
+        delete: true,
+        db: 'pt_test'
+    }), /delete requires a scope_source or scope_polygon argument/);
+    t.end();
+});
+
+test('conflate - DELETE refuses to delete more than the delete ratio', (t) => {
+    t.throws(() => worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-delete-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-delete-new.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        delete: true,
+        scope_source: 'dc',
+        db: 'pt_test'
+    }), /Refusing to delete 1 of 2 persistent addresses in scope/);
+
+    t.notOk(fs.existsSync('/tmp/output.geojson'), 'no partial diff is output');
+    t.notOk(fs.existsSync('/tmp/output.geojson.partial'), 'partial diff is removed');
+    t.end();
+});
+
+test('conflate - DELETE unmatched persistent addresses within the source scope', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-delete-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-delete-new.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        delete: true,
+        scope_source: 'dc',
+        delete_ratio: 0.5,
+        db: 'pt_test'
+    });
+
+    const rl = new ReadLine('/tmp/output.geojson');
+
+    const feat = JSON.parse(rl.next());
+    t.equals(feat.action, 'delete', 'delete action');
+    t.equals(feat.id, 2, 'unmatched in scope address is deleted');
+    t.equals(feat.version, 1, 'current version is deleted');
+    t.notOk(rl.next(), 'out of scope address is not deleted');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - DELETE unmatched persistent addresses within, or on the boundary of, the scope polygon', (t) => {
+    for (const backend of ['postgres', 'memory']) {
+        worker({
+            'in_persistent': path.resolve(__dirname, './fixtures/conflate-delete-persistent.geojson'),
+            'in_address': path.resolve(__dirname, './fixtures/conflate-delete-new.geojson'),
+            output: '/tmp/output.geojson',
+            context: {
+                country: 'us',
+                region: 'dc',
+                languages: ['en']
+            },
+            delete: true,
+            scope_polygon: path.resolve(__dirname, './fixtures/conflate-scope-polygon.geojson'),
+            delete_ratio: 0.5,
+            backend: backend,
+            db: 'pt_test'
+        });
+
+        const rl = new ReadLine('/tmp/output.geojson');
+
+        const feat = JSON.parse(rl.next());
+        t.equals(feat.action, 'delete', `${backend} delete action`);
+        t.equals(feat.id, 2, `${backend} unmatched address on the scope boundary is deleted`);
+        t.notOk(rl.next(), `${backend} matched address within & address outside of the scope are not deleted`);
+
+        fs.unlinkSync('/tmp/output.geojson');
+    }
+
+    t.end();
+});
+
+test('conflate - DELETE does not delete the latest version when an older version is matched', (t) => {
+    for (const backend of ['postgres', 'memory']) {
+        worker({
+            'in_persistent': path.resolve(__dirname, './fixtures/conflate-delete-versions-persistent.geojson'),
+            'in_address': path.resolve(__dirname, './fixtures/conflate-delete-new.geojson'),
+            output: '/tmp/output.geojson',
+            context: {
+                country: 'us',
+                region: 'dc',
+                languages: ['en']
+            },
+            delete: true,
+            scope_source: 'dc',
+            backend: backend,
+            db: 'pt_test'
+        });
+
+        const rl = new ReadLine('/tmp/output.geojson');
+
+        t.notOk(rl.next(), `${backend} matched address is not deleted`);
+
+        fs.unlinkSync('/tmp/output.geojson');
+    }
+
+    t.end();
+});
+
+test('conflate - MODIFY moves an existing address beyond the move threshold', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-move-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-move-rooftop.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        move_threshold: 10,
+        prefer_accuracy: true,
+        db: 'pt_test'
+    });
+
+    const rl = new ReadLine('/tmp/output.geojson');
+
+    const feat = JSON.parse(rl.next());
+    t.equals(feat.action, 'modify', 'modify action');
+    t.equals(feat.id, 1, 'existing address is modified');
+    t.equals(feat.version, 2, 'existing version is modified');
+    t.deepEquals(feat.geometry.coordinates, [-77.0008, 38.8917], 'moved to the new geometry');
+    t.equals(feat.properties.accuracy, 'rooftop', 'accuracy of the new geometry');
+    t.notOk(rl.next(), 'no other features');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - MODIFY does not move an existing address within the move threshold', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-move-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-move-rooftop.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        move_threshold: 100,
+        db: 'pt_test'
+    });
+
+    const rl = new ReadLine('/tmp/output.geojson');
+    t.notOk(rl.next(), 'no features output');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - MODIFY does not move an existing address to a less accurate point', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-move-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-move-point.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        move_threshold: 10,
+        prefer_accuracy: true,
+        db: 'pt_test'
+    });
+
+    const rl = new ReadLine('/tmp/output.geojson');
+    t.notOk(rl.next(), 'no features output');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - MODIFY merges properties using their merge strategy', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-move-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-move-rooftop.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        merge: {
+            accuracy: 'new'
+        },
+        db: 'pt_test'
+    });
+
+    const rl = new ReadLine('/tmp/output.geojson');
+
+    const feat = JSON.parse(rl.next());
+    t.equals(feat.action, 'modify', 'modify action');
+    t.equals(feat.id, 1, 'existing address is modified');
+    t.equals(feat.properties.accuracy, 'rooftop', 'new accuracy is preferred');
+    t.deepEquals(feat.geometry.coordinates, [-77.0008, 38.8913], 'geometry is not moved');
+    t.notOk(rl.next(), 'no other features');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - MODIFY merge strategies are not applied to matches without changes', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-move-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-move-rooftop.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        merge: {
+            accuracy: 'persistent'
+        },
+        db: 'pt_test'
+    });
+
+    const rl = new ReadLine('/tmp/output.geojson');
+    t.notOk(rl.next(), 'no features output');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - MODIFY a lower priority source cannot demote the persistent primary name', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-names-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-names-new.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        source_priority: ['county', 'osm'],
+        db: 'pt_test'
+    });
+
+    const rl = new ReadLine('/tmp/output.geojson');
+
+    const feat = JSON.parse(rl.next());
+    t.equals(feat.action, 'modify', 'modify action');
+    t.equals(feat.id, 1, 'existing address is modified');
+    t.deepEquals(feat.properties.street.map((name) => name.display), ['4th ST NE', 'Capitol Ave NE'], 'persistent primary name is kept');
+    t.ok(feat.properties.street[0].priority > feat.properties.street[1].priority, 'new name is ranked below the persistent primary');
+    t.notOk(rl.next(), 'no other features');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - MODIFY a higher priority source demotes the persistent primary name', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/conflate-names-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/conflate-names-new.geojson'),
+        output: '/tmp/output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        source_priority: ['osm', 'county'],
+        db: 'pt_test'
+    });
+
+    const rl = new ReadLine('/tmp/output.geojson');
+
+    const feat = JSON.parse(rl.next());
+    t.equals(feat.action, 'modify', 'modify action');
+    t.deepEquals(feat.properties.street.map((name) => name.display), ['Capitol Ave NE', '4th ST NE'], 'new primary name is preferred');
+    t.ok(feat.properties.street[0].priority > feat.properties.street[1].priority, 'persistent primary is demoted');
+    t.notOk(rl.next(), 'no other features');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - memory backend output matches the postgres backend', (t) => {
+    const output = (backend, input, persistent = './fixtures/dc-persistent.geojson') => {
+        worker({
+            'in_persistent': path.resolve(__dirname, persistent),
+            'in_address': path.resolve(__dirname, input),
+            output: '/tmp/output.geojson',
+            context: {
+                country: 'us',
+                region: 'dc',
+                languages: ['en']
+            },
+            backend: backend,
+            db: 'pt_test'
+        });
+
+        const feats = fs.readFileSync('/tmp/output.geojson', 'utf8').split('\n').filter((line) => line.length);
+        fs.unlinkSync('/tmp/output.geojson');
+
+        return feats.map((line) => JSON.parse(line));
+    };
+
+    for (const input of ['./fixtures/dc-modify-multiple.geojson', './fixtures/dc-new.geojson']) {
+        const feats = output('memory', input);
+
+        t.ok(feats.length > 0, `${input} has output`);
+        t.deepEquals(feats, output('postgres', input), `${input} output is the same`);
+    }
+
+    // several modifies in a single cursor batch must come out in the same order
+    const persistent = './fixtures/conflate-parity-persistent.geojson';
+    const input = './fixtures/conflate-parity-new.geojson';
+    const feats = output('memory', input, persistent);
+
+    t.deepEquals(feats.map((feat) => feat.action), Array(6).fill('modify'), 'each persistent address is modified');
+    t.deepEquals(feats.map((feat) => feat.id), [1, 2, 3, 4, 5, 6], 'modifies are in id order');
+    t.deepEquals(feats, output('postgres', input, persistent), `${input} output is the same`);
+
+    t.end();
+});
+
+test('conflate - batched output matches regardless of window & workers', (t) => {
+    const output = (backend, window, workers) => {
+        worker({
+            'in_persistent': path.resolve(__dirname, './fixtures/dc-persistent.geojson'),
+            'in_address': path.resolve(__dirname, './fixtures/dc-new.geojson'),
+            output: '/tmp/output.geojson',
+            context: {
+                country: 'us',
+                region: 'dc',
+                languages: ['en']
+            },
+            backend: backend,
+            window: window,
+            workers: workers,
+            db: 'pt_test'
+        });
+
+        const feats = fs.readFileSync('/tmp/output.geojson', 'utf8');
+        fs.unlinkSync('/tmp/output.geojson');
+
+        return feats;
+    };
+
+    for (const backend of ['postgres', 'memory']) {
+        const expected = output(backend, undefined, 1);
+
+        t.equals(output(backend, 1, 4), expected, `${backend} output with a window of 1 is identical`);
+        t.equals(output(backend, 2, 3), expected, `${backend} output with a window of 2 is identical`);
+    }
+
+    t.end();
+});
+
+test('conflate - summary reports the actions of the diff', (t) => {
+    worker({
+        'in_persistent': path.resolve(__dirname, './fixtures/dc-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/dc-modify-multiple.geojson'),
+        output: '/tmp/output.geojson',
+        summary: '/tmp/summary.json',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        db: 'pt_test'
+    });
+
+    const summary = JSON.parse(fs.readFileSync('/tmp/summary.json', 'utf8'));
+
+    t.equals(summary.create.total, 0, 'no creates');
+    t.equals(summary.modify.total, 1, '1 modify');
+    t.deepEquals(summary.modify.sources, { '': 1 }, 'modifies by source');
+    t.deepEquals(summary.modify.reasons, { name: 1 }, 'modifies by reason');
+    t.equals(summary.modify.samples.length, 1, 'modify example');
+    t.equals(summary.modify.samples[0].id, 1, 'modify example is the modified feature');
+    t.equals(summary.delete.total, 0, 'no deletes');
+    t.deepEquals(summary.multiple, [1], 'persistent address matched by multiple new addresses');
+
+    fs.unlinkSync('/tmp/output.geojson');
+    fs.unlinkSync('/tmp/summary.json');
+    t.end();
+});
+
+test('conflate - RESTORE a deleted persistent address matched by a new address', (t) => {
+    for (const backend of ['postgres', 'memory']) {
+        worker({
+            'in_persistent': path.resolve(__dirname, './fixtures/conflate-restore-persistent.geojson'),
+            'in_address': path.resolve(__dirname, './fixtures/conflate-move-persistent.geojson'),
+            output: '/tmp/output.geojson',
+            context: {
+                country: 'us',
+                region: 'dc',
+                languages: ['en']
+            },
+            backend: backend,
+            db: 'pt_test'
+        });
+
+        const rl = new ReadLine('/tmp/output.geojson');
+
+        const feat = JSON.parse(rl.next());
+        t.equals(feat.action, 'restore', `${backend} restore action`);
+        t.equals(feat.id, 1, `${backend} deleted address is restored`);
+        t.equals(feat.version, 2, `${backend} restore has the version of the delete`);
+        t.notOk(rl.next(), `${backend} no other features`);
+
+        fs.unlinkSync('/tmp/output.geojson');
+    }
+
+    t.end();
+});
+
+test('conflate - persistent addresses with conflicting versions are an error', (t) => {
+    for (const backend of ['postgres', 'memory']) {
+        t.throws(() => worker({
+            'in_persistent': path.resolve(__dirname, './fixtures/conflate-conflict-persistent.geojson'),
+            'in_address': path.resolve(__dirname, './fixtures/conflate-move-persistent.geojson'),
+            output: '/tmp/output.geojson',
+            context: {
+                country: 'us',
+                region: 'dc',
+                languages: ['en']
+            },
+            backend: backend,
+            db: 'pt_test'
+        }), /Persistent address 1 has 2 conflicting features with version 2/, `${backend} conflict error`);
+    }
+
+    fs.unlinkSync('/tmp/output.geojson');
+    t.end();
+});
+
+test('conflate - house numbers match across unit & suffix variants', (t) => {
+    for (const backend of ['postgres', 'memory']) {
+        worker({
+            'in_persistent': path.resolve(__dirname, './fixtures/conflate-number-persistent.geojson'),
+            'in_address': path.resolve(__dirname, './fixtures/conflate-number-new.geojson'),
+            output: '/tmp/output.geojson',
+            context: {
+                country: 'us',
+                region: 'dc',
+                languages: ['en']
+            },
+            backend: backend,
+            db: 'pt_test'
+        });
+
+        const rl = new ReadLine('/tmp/output.geojson');
+
+        // 12 matches 012, 20 matches 20-22 & 30 A matches 30a without changes
+        const feat = JSON.parse(rl.next());
+        t.equals(feat.action, 'create', `${backend} missing suffix is a different address`);
+        t.equals(feat.properties.number, '30', `${backend} created number`);
+        t.notOk(rl.next(), `${backend} no other features`);
+
+        fs.unlinkSync('/tmp/output.geojson');
+    }
+
+    t.end();
+});
@@ -37,9 +37,27 @@ test('consensus - full agreement', (t) => {
 
     t.deepEqual(results, {
         results: {
-            'source-1': { agreement_count: 1, hit_count: 1 },
-            'source-2': { agreement_count: 1, hit_count: 1 },
-            'source-3': { agreement_count: 1, hit_count: 1 }
+            'source-1': {
+                agreement_count: 1,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: { mean: 6.31, median: 6.31, p90: 6.31 }
+            },
+            'source-2': {
+                agreement_count: 1,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: { mean: 2.47, median: 2.47, p90: 2.47 }
+            },
+            'source-3': {
+                agreement_count: 1,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: { mean: 6.57, median: 6.57, p90: 6.57 }
+            }
         },
         threshold: 25,
         sample_count: 1
@@ -83,9 +101,27 @@ test('consensus - partial agreement', (t) => {
 
     t.deepEqual(results, {
         results: {
-            'source-1': { agreement_count: 1, hit_count: 1 },
-            'source-2': { agreement_count: 1, hit_count: 1 },
-            'source-4': { agreement_count: 0, hit_count: 1 }
+            'source-1': {
+                agreement_count: 1,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: { mean: 3.49, median: 3.49, p90: 3.49 }
+            },
+            'source-2': {
+                agreement_count: 1,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: { mean: 3.49, median: 3.49, p90: 3.49 }
+            },
+            'source-4': {
+                agreement_count: 0,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: { mean: 39.52, median: 39.52, p90: 39.52 }
+            }
         },
         threshold: 25,
         sample_count: 1
@@ -129,9 +165,27 @@ test('consensus - no agreement', (t) => {
 
     t.deepEqual(results, {
         results: {
-            'source-1': { agreement_count: 0, hit_count: 1 },
-            'source-4': { agreement_count: 0, hit_count: 1 },
-            'source-5': { agreement_count: 0, hit_count: 1 }
+            'source-1': {
+                agreement_count: 0,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: null
+            },
+            'source-4': {
+                agreement_count: 0,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: null
+            },
+            'source-5': {
+                agreement_count: 0,
+                hit_count: 1,
+                name_disagreement_count: 0,
+                coverage: 1,
+                distance: null
+            }
         },
         threshold: 25,
         sample_count: 1
@@ -146,3 +200,27 @@ test('consensus - no agreement', (t) => {
     fs.unlinkSync('/tmp/error-test-set');
     t.end();
 });
+
+test('consensus - metrics are bucketed by boundary polygons', (t) => {
+    const results = worker({
+        sources: [
+            path.resolve(__dirname, './fixtures/dc-consensus-source-1-close.geojson'),
+            path.resolve(__dirname, './fixtures/dc-consensus-source-2-close.geojson'),
+            path.resolve(__dirname, './fixtures/dc-consensus-source-4-far.geojson')
+        ],
+        'query_points': path.resolve(__dirname, './fixtures/dc-consensus-test-set.geojson'),
+        buckets: path.resolve(__dirname, './fixtures/dc-consensus-buckets.geojson'),
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        db: 'pt_test'
+    });
+
+    t.deepEqual(Object.keys(results.buckets), ['northeast'], 'query point is only within the northeast bucket');
+    t.equals(results.buckets.northeast.sample_count, 1);
+    t.deepEqual(results.buckets.northeast.results, results.results, 'bucket metrics match the overall metrics');
+
+    t.end();
+});
@@ -0,0 +1,94 @@
+'use strict';
+
+const {
+    intersections
+} = require('../native/index.node');
+
+const test = require('tape');
+const Queue = require('d3-queue').queue;
+
+const db = require('./lib/db');
+
+db.init(test);
+
+test('intersections - merged intersections keep every network cluster', (t) => {
+    const pool = db.get();
+    const popQ = new Queue(1);
+
+    const main = '[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Main Street", "freq": 1, "priority": 0 }]';
+    const elm = '[{ "tokenized": [{ "token": "elm", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Elm Street", "freq": 1, "priority": 0 }]';
+
+    // Both carriageways of Main St, ~11m apart, are separate network clusters that cross Elm St
+    popQ.defer((done) => {
+        pool.query(`
+            BEGIN;
+            INSERT INTO network (id, names, geom) VALUES (1, '${main}', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -77.001, 38.0001 ], [ -76.999, 38.0001 ] ] ]}'), 4326));
+            INSERT INTO network (id, names, geom) VALUES (2, '${main}', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -77.001, 38.0 ], [ -76.999, 38.0 ] ] ]}'), 4326));
+            INSERT INTO network (id, names, geom) VALUES (3, '${elm}', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -77.0, 37.999 ], [ -77.0, 38.001 ] ] ]}'), 4326));
+            INSERT INTO network_cluster (id, names, geom, source_ids) SELECT id, names, ST_Force3D(geom), ARRAY[id] FROM network;
+            COMMIT;
+        `, (err) => {
+            t.error(err, 'no errors');
+
+            return done();
+        });
+    });
+
+    popQ.defer((done) => {
+        t.equals(intersections('pt_test'), true, 'ok - intersections generated');
+
+        return done();
+    });
+
+    popQ.defer((done) => {
+        pool.query(`
+            SELECT
+                a_id,
+                b_id,
+                a_ids,
+                b_ids,
+                a_display,
+                b_display
+            FROM
+                intersections
+            ORDER BY
+                id
+        `, (err, res) => {
+            t.error(err, 'no errors');
+
+            t.equals(res.rows.length, 1, 'ok - the crossings of both carriageways are merged');
+            t.deepEquals(res.rows[0].a_ids.map(Number).sort(), [1, 2], 'ok - both Main St clusters are kept');
+            t.deepEquals(res.rows[0].b_ids.map(Number), [3], 'ok - Elm St cluster is kept');
+            t.equals(res.rows[0].a_display, 'Main Street');
+            t.equals(res.rows[0].b_display, 'Elm Street');
+
+            return done();
+        });
+    });
+
+    popQ.defer((done) => {
+        pool.query(`
+            SELECT
+                count(*)::INT AS count
+            FROM
+                intersections
+            WHERE
+                2 = ANY(intersections.a_ids)
+                OR 2 = ANY(intersections.b_ids)
+        `, (err, res) => {
+            t.error(err, 'no errors');
+            t.equals(res.rows[0].count, 1, 'ok - the merged away cluster still finds the intersection');
+
+            return done();
+        });
+    });
+
+    popQ.await((err) => {
+        t.error(err, 'no errors');
+        pool.end(() => {
+            t.end();
+        });
+    });
+});
+
+db.init(test);
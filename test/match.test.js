@@ -105,3 +105,98 @@ test('Match', (t) => {
 });
 
 db.init(test);
+
+test('Match - memory backend links as link_process does', (t) => {
+    const pool = db.get();
+    const popQ = new Queue(1);
+
+    const links = `
+        SELECT
+            id,
+            netid,
+            link_score,
+            link_name,
+            link_net_name,
+            link_path,
+            link_candidates
+        FROM
+            address
+        ORDER BY id
+    `;
+
+    let postgres;
+
+    // POPULATE NETWORK_CLUSTER - Parallel Main Street clusters, a crossing Elm Street & a nearby Main Avenue
+    popQ.defer((done) => {
+        pool.query(`
+            BEGIN;
+            INSERT INTO network_cluster (id, names, geom) VALUES (1, '[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way"}], "display": "Main Street", "priority": 0, "freq": 1 }]', ST_Multi(ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "LineString", "coordinates": [ [ -66.0518, 45.2687, 1 ], [ -66.05, 45.269, 1 ] ] }'), 4326)));
+            INSERT INTO network_cluster (id, names, geom) VALUES (2, '[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way"}], "display": "Main Street", "priority": 0, "freq": 1 }]', ST_Multi(ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "LineString", "coordinates": [ [ -66.0518, 45.2697, 1 ], [ -66.05, 45.27, 1 ] ] }'), 4326)));
+            INSERT INTO network_cluster (id, names, geom) VALUES (3, '[{ "tokenized": [{ "token": "elm", "token_type": null }, { "token": "st", "token_type": "Way"}], "display": "Elm Street", "priority": 0, "freq": 1 }]', ST_Multi(ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "LineString", "coordinates": [ [ -66.051, 45.268, 1 ], [ -66.051, 45.27, 1 ] ] }'), 4326)));
+            INSERT INTO network_cluster (id, names, geom) VALUES (4, '[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "av", "token_type": "Way"}], "display": "Main Avenue", "priority": 0, "freq": 1 }]', ST_Multi(ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "LineString", "coordinates": [ [ -66.053, 45.268, 1 ], [ -66.053, 45.27, 1 ] ] }'), 4326)));
+            COMMIT;
+        `, (err) => {
+            t.error(err);
+            return done();
+        });
+    });
+
+    // POPULATE Address
+    popQ.defer((done) => {
+        pool.query(`
+            BEGIN;
+            ALTER TABLE address ADD COLUMN IF NOT EXISTS interpolate boolean;
+            INSERT INTO address (names, number, geom, interpolate) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way"}], "display": "Main Street", "priority": 0, "freq": 1 }]', 10, ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "Point", "coordinates": [ -66.0512, 45.2688 ] }'), 4326), 'true');
+            INSERT INTO address (names, number, geom, interpolate) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way"}], "display": "Main Street", "priority": 0, "freq": 1 }]', 12, ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "Point", "coordinates": [ -66.0512, 45.2696 ] }'), 4326), 'true');
+            INSERT INTO address (names, number, geom, interpolate) VALUES ('[{ "tokenized": [{ "token": "elm", "token_type": null }, { "token": "st", "token_type": "Way"}], "display": "Elm Street", "priority": 0, "freq": 1 }]', 14, ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "Point", "coordinates": [ -66.0511, 45.2692 ] }'), 4326), 'true');
+            INSERT INTO address (names, number, geom, interpolate) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "av", "token_type": "Way"}], "display": "Main Avenue", "priority": 0, "freq": 1 }]', 16, ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "Point", "coordinates": [ -66.0525, 45.2689 ] }'), 4326), 'true');
+            INSERT INTO address (names, number, geom, interpolate) VALUES ('[{ "tokenized": [{ "token": "fake", "token_type": null }, { "token": "rd", "token_type": "Way"}], "display": "Fake Road", "priority": 0, "freq": 1 }]', 18, ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "Point", "coordinates": [ -66.0512, 45.2688 ] }'), 4326), 'true');
+            INSERT INTO address (names, number, geom, interpolate) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way"}], "display": "Main Street", "priority": 0, "freq": 1 }]', 20, ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "Point", "coordinates": [ -66.0512, 45.2688 ] }'), 4326), 'false');
+            INSERT INTO address (names, number, geom, interpolate) VALUES ('[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way"}], "display": "Main Street", "priority": 0, "freq": 1 }]', 22, ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "Point", "coordinates": [ -66.2, 45.3 ] }'), 4326), 'true');
+            COMMIT;
+        `, (err) => {
+            t.error(err);
+
+            pg_optimize();
+
+            return done();
+        });
+    });
+
+    popQ.defer((done) => {
+        link_addr('pt_test');
+
+        pool.query(links, (err, res) => {
+            t.error(err);
+
+            postgres = res.rows;
+
+            t.deepEquals(postgres.map((row) => row.netid), ['1', '2', '3', '4', null, null, null], 'ok - link_process netids');
+
+            return done();
+        });
+    });
+
+    popQ.defer((done) => {
+        link_addr({
+            db: 'pt_test',
+            backend: 'memory'
+        });
+
+        pool.query(links, (err, res) => {
+            t.error(err);
+
+            t.deepEquals(res.rows, postgres, 'ok - memory backend links match link_process');
+
+            return done();
+        });
+    });
+
+    popQ.await((err) => {
+        t.error(err);
+
+        pool.end(t.end);
+    });
+});
+
+db.init(test);
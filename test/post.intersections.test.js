@@ -0,0 +1,49 @@
+'use strict';
+
+const post = require('../lib/post/intersections').post;
+const test = require('tape');
+
+test('Post: Intersections', (t) => {
+    const feat = () => {
+        return {
+            type: 'Feature',
+            properties: {
+                'internal:nid': 1,
+                'carmen:intersections': [{
+                    id: 1,
+                    a_id: 1,
+                    b_id: 2,
+                    a_street: [{ display: 'Main St', priority: 0, freq: 1, tokenized: [] }],
+                    b_street: [{ display: 'Elm St', priority: 0, freq: 1, tokenized: [] }],
+                    a_display: 'Main St',
+                    b_display: 'Elm St',
+                    synonyms: [['Main St', 'Elm St'], ['Main St', 'Highway 1'], ['Main Street', 'Elm St'], ['Main Street', 'Highway 1']],
+                    geom: { type: 'Point', coordinates: [-77.0, 38.0] }
+                }]
+            },
+            geometry: {
+                type: 'GeometryCollection',
+                geometries: [{ type: 'MultiLineString', coordinates: [] }]
+            }
+        };
+    };
+
+    t.equals(post(), undefined);
+
+    t.notOk(post(feat(), { args: {} }).properties['carmen:intersections'], 'intersections are only output when enabled');
+
+    const res = post(feat(), { args: { intersections: true } });
+
+    t.deepEquals(res.properties['carmen:intersections'], [null, ['Elm St', 'Highway 1']], 'names of the other street in synonym order');
+    t.deepEquals(res.geometry.geometries[1], {
+        type: 'MultiPoint',
+        coordinates: [[-77.0, 38.0], [-77.0, 38.0]]
+    }, 'a point for each name');
+
+    const b = feat();
+    b.properties['internal:nid'] = 2;
+
+    t.deepEquals(post(b, { args: { intersections: true } }).properties['carmen:intersections'], [null, ['Main St', 'Main Street']], 'names of the a street for the b street');
+
+    t.end();
+});
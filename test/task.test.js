@@ -0,0 +1,76 @@
+'use strict';
+
+const tasks = require('../lib/util/task');
+
+const test = require('tape');
+const path = require('path');
+const fs = require('fs');
+
+/**
+ * Arguments of a memory backed conflate, which requires no database
+ * @param {Object} extra Additional arguments
+ * @return {Object}
+ */
+function conflate(extra) {
+    return Object.assign({
+        'in_persistent': path.resolve(__dirname, './fixtures/dc-persistent.geojson'),
+        'in_address': path.resolve(__dirname, './fixtures/dc-new.geojson'),
+        output: '/tmp/task-output.geojson',
+        context: {
+            country: 'us',
+            region: 'dc',
+            languages: ['en']
+        },
+        backend: 'memory',
+        window: 1
+    }, extra);
+}
+
+test('task - conflate in the background with a progress callback', (t) => {
+    const events = [];
+
+    tasks.conflate(conflate({
+        progress: (event) => events.push(event)
+    })).then((res) => {
+        t.equals(res, true, 'ok - conflate resolved');
+        t.ok(fs.readFileSync('/tmp/task-output.geojson', 'utf8').length, 'ok - output written');
+        t.ok(events.length, 'ok - progress events delivered');
+        t.ok(events.some((event) => event.stage === 'conflate' && event.finished), 'ok - final conflate event delivered');
+
+        fs.unlinkSync('/tmp/task-output.geojson');
+        t.end();
+    }).catch((err) => {
+        t.error(err, 'no errors');
+        t.end();
+    });
+});
+
+test('task - cancel a running conflate', (t) => {
+    const task = tasks.conflate(conflate());
+
+    t.equals(task.cancel(), true, 'ok - running task was cancelled');
+
+    task.then(() => {
+        t.fail('cancelled task should not resolve');
+        t.end();
+    }).catch((err) => {
+        t.equals(err.message, 'Cancelled', 'ok - rejected as cancelled');
+
+        if (fs.existsSync('/tmp/task-output.geojson')) fs.unlinkSync('/tmp/task-output.geojson');
+        t.end();
+    });
+});
+
+test('task - a mode that throws before it is scheduled', (t) => {
+    const task = tasks.conflate('not an object');
+
+    t.equals(task.cancel(), false, 'ok - there is nothing to cancel');
+
+    task.then(() => {
+        t.fail('task should not resolve');
+        t.end();
+    }).catch((err) => {
+        t.ok(err instanceof Error, 'ok - rejected with the thrown error');
+        t.end();
+    });
+});
@@ -0,0 +1,78 @@
+'use strict';
+
+const {
+    pg_optimize,
+    topology
+} = require('../native/index.node');
+
+const tasks = require('../lib/util/task');
+
+const test = require('tape');
+const Queue = require('d3-queue').queue;
+
+const db = require('./lib/db');
+
+db.init(test);
+
+test('topology', (t) => {
+    const pool = db.get();
+    const popQ = new Queue(1);
+
+    // POPULATE NETWORK
+    popQ.defer((done) => {
+        pool.query(`
+            BEGIN;
+            INSERT INTO network (id, names, geom) VALUES (1, '[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Main Street", "freq": 1, "priority": 0 }]', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -77.0, 38.0 ], [ -77.001, 38.0 ] ] ]}'), 4326));
+            INSERT INTO network (id, names, geom) VALUES (2, '[{ "tokenized": [{ "token": "main", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Main Street", "freq": 1, "priority": 0 }]', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -77.001, 38.0 ], [ -77.002, 38.0 ] ] ]}'), 4326));
+            INSERT INTO network (id, names, geom) VALUES (3, '[{ "tokenized": [{ "token": "elm", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Elm Street", "freq": 1, "priority": 0 }]', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -77.001, 38.0000057 ], [ -77.001, 38.001 ] ] ]}'), 4326));
+            INSERT INTO network (id, names, geom) VALUES (4, '[{ "tokenized": [{ "token": "oak", "token_type": null }, { "token": "st", "token_type": "Way" }], "display": "Oak Street", "freq": 1, "priority": 0 }]', ST_SetSRID(ST_GeomFromGeoJSON('{ "type": "MultiLineString", "coordinates": [ [ [ -78.0, 38.0 ], [ -78.0, 38.001 ] ] ]}'), 4326));
+            COMMIT;
+        `, (err) => {
+            t.error(err, 'no errors');
+
+            pg_optimize();
+
+            return done();
+        });
+    });
+
+    popQ.defer((done) => {
+        t.deepEquals(topology('pt_test'), {
+            nodes: 6,
+            edges: 4,
+            components: 2,
+            dangling: 5,
+            junctions: 1
+        }, 'ok - Elm St snapped to Main St');
+
+        t.deepEquals(topology({
+            db: 'pt_test',
+            snap_tolerance: 0.1
+        }), {
+            nodes: 7,
+            edges: 4,
+            components: 3,
+            dangling: 6,
+            junctions: 1
+        }, 'ok - Elm St left dangling');
+
+        return done();
+    });
+
+    popQ.defer((done) => {
+        tasks.topology('pt_test').then((summary) => {
+            t.deepEquals(summary, topology('pt_test'), 'ok - background topology matches');
+
+            return done();
+        }).catch(done);
+    });
+
+    popQ.await((err) => {
+        t.error(err, 'no errors');
+        pool.end(() => {
+            t.end();
+        });
+    });
+});
+
+db.init(test);